@@ -0,0 +1,1094 @@
+//! Read and write Anki packages, i.e. `.apkg` files
+//!
+//! A package is a zip archive with the Anki collection as SQLite database and optionally media
+//! files. Each note of the collection has a note type, which names its fields, and its cards are
+//! in a deck. Notes become our cards, see `AnkiPackage::to_rows`, and our cards become notes, see
+//! `write_package`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use anki::{AnkiPackage, CategorySource, PackageOptions, write_package};
+//! let package = AnkiPackage::read(Path::new("tongan.apkg"))?;
+//! let (rows, columns) = package.to_rows(&mapping, CategorySource::Deck, false);
+//! let options = PackageOptions { category_ids: vec![], directions: vec![(1, 2)], descriptions: true };
+//! let count = write_package(&conn, Path::new("kaati_ako.apkg"), "Kaati Ako", &options)?;
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+
+use crate::database::DatabaseError;
+use crate::export::load_cards;
+use crate::import::Column;
+use crate::models::Model;
+use crate::models::filter::Filter;
+use crate::models::language::Language;
+use crate::models::setting::Setting;
+
+
+/// Name of the collection in packages of Anki 2.0
+const COLLECTION_ANKI2: &str = "collection.anki2";
+/// Name of the collection in packages of Anki 2.1, preferred over the one of Anki 2.0
+const COLLECTION_ANKI21: &str = "collection.anki21";
+/// Name of the compressed collection in packages of Anki 2.1.50 and later
+const COLLECTION_ANKI21B: &str = "collection.anki21b";
+/// Name of the file which maps the numbered media files of a package to their names
+const MEDIA: &str = "media";
+/// Separator of the fields of a note
+const FIELD_SEPARATOR: char = '\u{1f}';
+/// Schema of the collection in written packages, as in packages of Anki 2.0
+const SCHEMA: &str = "
+    CREATE TABLE col (
+        id INTEGER PRIMARY KEY, crt INTEGER NOT NULL, mod INTEGER NOT NULL, scm INTEGER NOT NULL,
+        ver INTEGER NOT NULL, dty INTEGER NOT NULL, usn INTEGER NOT NULL, ls INTEGER NOT NULL,
+        conf TEXT NOT NULL, models TEXT NOT NULL, decks TEXT NOT NULL, dconf TEXT NOT NULL,
+        tags TEXT NOT NULL
+    );
+    CREATE TABLE notes (
+        id INTEGER PRIMARY KEY, guid TEXT NOT NULL, mid INTEGER NOT NULL, mod INTEGER NOT NULL,
+        usn INTEGER NOT NULL, tags TEXT NOT NULL, flds TEXT NOT NULL, sfld INTEGER NOT NULL,
+        csum INTEGER NOT NULL, flags INTEGER NOT NULL, data TEXT NOT NULL
+    );
+    CREATE TABLE cards (
+        id INTEGER PRIMARY KEY, nid INTEGER NOT NULL, did INTEGER NOT NULL, ord INTEGER NOT NULL,
+        mod INTEGER NOT NULL, usn INTEGER NOT NULL, type INTEGER NOT NULL, queue INTEGER NOT NULL,
+        due INTEGER NOT NULL, ivl INTEGER NOT NULL, factor INTEGER NOT NULL, reps INTEGER NOT NULL,
+        lapses INTEGER NOT NULL, left INTEGER NOT NULL, odue INTEGER NOT NULL,
+        odid INTEGER NOT NULL, flags INTEGER NOT NULL, data TEXT NOT NULL
+    );
+    CREATE TABLE revlog (
+        id INTEGER PRIMARY KEY, cid INTEGER NOT NULL, usn INTEGER NOT NULL, ease INTEGER NOT NULL,
+        ivl INTEGER NOT NULL, lastIvl INTEGER NOT NULL, factor INTEGER NOT NULL,
+        time INTEGER NOT NULL, type INTEGER NOT NULL
+    );
+    CREATE TABLE graves (usn INTEGER NOT NULL, oid INTEGER NOT NULL, type INTEGER NOT NULL);
+    CREATE INDEX ix_notes_usn ON notes (usn);
+    CREATE INDEX ix_cards_usn ON cards (usn);
+    CREATE INDEX ix_revlog_usn ON revlog (usn);
+    CREATE INDEX ix_cards_nid ON cards (nid);
+    CREATE INDEX ix_cards_sched ON cards (did, queue, due);
+    CREATE INDEX ix_revlog_cid ON revlog (cid);
+    CREATE INDEX ix_notes_csum ON notes (csum);
+";
+
+
+/// An error which can occur while reading an Anki package
+#[derive(Debug)]
+pub enum AnkiError {
+    /// The file cannot be read or written, the error message should be in the string
+    Io(String),
+    /// The file is not a valid zip archive, the error message should be in the string
+    Zip(String),
+    /// The collection cannot be read
+    Database(DatabaseError),
+    /// The note types or decks of the collection are not valid JSON
+    Json(String),
+    /// The package is of a version which is not supported, the reason should be in the string
+    Unsupported(String),
+}
+
+/// The implementation of the Error trait is empty
+impl Error for AnkiError {}
+
+/// Implement the From trait to convert an io::Error to an AnkiError
+impl From<io::Error> for AnkiError {
+    fn from(err: io::Error) -> Self {
+        AnkiError::Io(err.to_string())
+    }
+}
+
+/// Implement the From trait to convert a zip::result::ZipError to an AnkiError
+impl From<zip::result::ZipError> for AnkiError {
+    fn from(err: zip::result::ZipError) -> Self {
+        AnkiError::Zip(err.to_string())
+    }
+}
+
+/// Implement the From trait to convert a DatabaseError to an AnkiError
+impl From<DatabaseError> for AnkiError {
+    fn from(err: DatabaseError) -> Self {
+        AnkiError::Database(err)
+    }
+}
+
+/// Implement the From trait to convert a sqlite::Error to an AnkiError
+impl From<sqlite::Error> for AnkiError {
+    fn from(err: sqlite::Error) -> Self {
+        AnkiError::Database(DatabaseError::from(err))
+    }
+}
+
+/// Implement the From trait to convert a serde_json::Error to an AnkiError
+impl From<serde_json::Error> for AnkiError {
+    fn from(err: serde_json::Error) -> Self {
+        AnkiError::Json(err.to_string())
+    }
+}
+
+/// Implement the Display trait to show an AnkiError
+impl fmt::Display for AnkiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let prefix = "AnkiError";
+        match self {
+            AnkiError::Io(msg) => write!(f, "{}: File error: {}!", prefix, msg),
+            AnkiError::Zip(msg) => write!(f, "{}: Not a valid package: {}!", prefix, msg),
+            AnkiError::Database(err) => write!(f, "{}: {}", prefix, err),
+            AnkiError::Json(msg) => write!(f, "{}: Invalid collection: {}!", prefix, msg),
+            AnkiError::Unsupported(reason) =>
+                write!(f, "{}: Package not supported: {}!", prefix, reason),
+        }
+    }
+}
+
+
+/// A private temporary directory, which is removed with its content when dropped
+///
+/// SQLite needs a file, so collections are read and written in such a directory. It is created
+/// exclusively and only the user may access it, so no one else can plant a file or a symlink in it.
+struct TempDir {
+    /// Path to the directory
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Create a directory with a unique name in the system's directory for temporary files
+    fn new() -> io::Result<TempDir> {
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.subsec_nanos());
+        for attempt in 0..100 {
+            let path = env::temp_dir().join(
+                format!("kaati_ako_{}_{}_{}", process::id(), nanos, attempt));
+            match builder.create(&path) {
+                Ok(_) => { return Ok(TempDir { path }); },
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => { continue; },
+                Err(err) => { return Err(err); },
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::AlreadyExists, "no unique temporary directory"))
+    }
+
+    /// Create a file in the directory, which must not exist yet
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the file
+    fn create_file(&self, name: &str) -> io::Result<(PathBuf, fs::File)> {
+        let path = self.path.join(name);
+        let file = fs::OpenOptions::new().write(true).create_new(true).open(&path)?;
+        Ok((path, file))
+    }
+}
+
+/// Implement the Drop trait to remove the directory with its content
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.path).ok();
+    }
+}
+
+
+/// Where the category of an imported note comes from, unless a field is mapped to it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CategorySource {
+    /// The name of the deck of the note's first card
+    Deck,
+    /// The first tag of the note
+    Tag,
+    /// No category, so the note gets the default category of the import
+    None,
+}
+
+
+/// A note type of an Anki collection
+#[derive(Clone, Debug)]
+pub struct NoteType {
+    /// Identifier of the note type
+    pub id: i64,
+    /// Name of the note type, e.g. `Basic`
+    pub name: String,
+    /// Names of the fields in order, e.g. `Front` and `Back`
+    pub fields: Vec<String>,
+}
+
+
+/// A note of an Anki collection
+#[derive(Clone, Debug)]
+pub struct Note {
+    /// Identifier of the note's type
+    pub note_type_id: i64,
+    /// Values of the fields in the order of the note type's fields; they may contain HTML
+    pub fields: Vec<String>,
+    /// Tags of the note
+    pub tags: Vec<String>,
+    /// Name of the deck of the note's first card; empty if unknown
+    pub deck: String,
+}
+
+
+/// The content of an Anki package
+#[derive(Debug, Default)]
+pub struct AnkiPackage {
+    /// The note types which are used by notes, ordered by name
+    pub note_types: Vec<NoteType>,
+    /// The notes in the order they have been created
+    pub notes: Vec<Note>,
+    /// The names of the media files by their names in the archive
+    media: HashMap<String, String>,
+}
+
+
+impl AnkiPackage {
+    /// Read an Anki package
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the `.apkg` file
+    pub fn read(path: &Path) -> Result<AnkiPackage, AnkiError> {
+        let mut archive = zip::ZipArchive::new(fs::File::open(path)?)?;
+        let names: Vec<String> = archive.file_names().map(|name| name.to_string()).collect();
+        // Newer packages contain a legacy collection, too, but it only holds a note which asks to
+        // update Anki, so it must not be imported instead
+        if names.iter().any(|name| name == COLLECTION_ANKI21B) {
+            return Err(AnkiError::Unsupported(
+                "export it with 'Support older Anki versions' checked".to_string()));
+        }
+        let collection = [COLLECTION_ANKI21, COLLECTION_ANKI2].iter()
+            .find(|name| names.iter().any(|found| found == *name));
+        let collection = match collection {
+            Some(collection) => collection,
+            None => { return Err(AnkiError::Unsupported("no collection found".to_string())); },
+        };
+        // SQLite needs a file, so the collection is extracted to a temporary one, which is removed
+        // with its directory on every path
+        let tmp_dir = TempDir::new()?;
+        let (tmp_path, mut tmp_file) = tmp_dir.create_file(COLLECTION_ANKI2)?;
+        io::copy(&mut archive.by_name(collection)?, &mut tmp_file)?;
+        drop(tmp_file);
+        let mut package = AnkiPackage::read_collection(&sqlite::open(&tmp_path)?)?;
+        drop(tmp_dir);
+        if let Ok(mut file) = archive.by_name(MEDIA) {
+            let mut media = Vec::new();
+            file.read_to_end(&mut media)?;
+            // Newer packages have a binary media list, so they are imported without media
+            package.media = serde_json::from_slice(&media).unwrap_or_default();
+        }
+        Ok(package)
+    }
+
+    /// Read the note types, decks and notes from an Anki collection
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the collection
+    fn read_collection(conn: &sqlite::Connection) -> Result<AnkiPackage, AnkiError> {
+        let mut cursor = conn.prepare("SELECT models, decks FROM col")?.cursor();
+        let (models, decks) = match cursor.next()? {
+            Some(row) => (
+                row[0].as_string().unwrap_or("{}").to_string(),
+                row[1].as_string().unwrap_or("{}").to_string(),
+            ),
+            None => { return Err(AnkiError::Unsupported("collection is empty".to_string())); },
+        };
+        let mut note_types = AnkiPackage::parse_note_types(&models)?;
+        let mut deck_names = AnkiPackage::parse_decks(&decks)?;
+        // Collections of schema version 15 and later keep them in tables instead
+        if note_types.is_empty() {
+            note_types = AnkiPackage::load_note_types(conn)?;
+            deck_names = AnkiPackage::load_decks(conn)?;
+        }
+
+        let mut notes = Vec::new();
+        let mut cursor = conn.prepare("
+            SELECT notes.mid, notes.flds, notes.tags,
+                (SELECT cards.did FROM cards WHERE cards.nid = notes.id ORDER BY cards.ord LIMIT 1)
+            FROM notes ORDER BY notes.id")?.cursor();
+        while let Some(row) = cursor.next()? {
+            let note_type_id = row[0].as_integer().ok_or(DatabaseError::ValueNotInteger)?;
+            let fields = row[1].as_string().ok_or(DatabaseError::ValueNotString)?;
+            let tags = row[2].as_string().ok_or(DatabaseError::ValueNotString)?;
+            let deck = row[3].as_integer().and_then(|id| deck_names.get(&id)).cloned();
+            notes.push(Note {
+                note_type_id,
+                fields: fields.split(FIELD_SEPARATOR).map(|field| field.to_string()).collect(),
+                tags: tags.split_whitespace().map(|tag| tag.to_string()).collect(),
+                deck: deck.unwrap_or_default(),
+            });
+        }
+        note_types.retain(
+            |note_type| notes.iter().any(|note| note.note_type_id == note_type.id));
+        note_types.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(AnkiPackage { note_types, notes, media: HashMap::new() })
+    }
+
+    /// Parse the note types of a collection from JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `models` - Note types by id as JSON, with their fields in `flds`
+    fn parse_note_types(models: &str) -> Result<Vec<NoteType>, AnkiError> {
+        let models: serde_json::Map<String, serde_json::Value> = serde_json::from_str(models)?;
+        let mut note_types = Vec::new();
+        for (id, model) in models {
+            let fields = model["flds"].as_array().map_or(Vec::new(), |fields| {
+                fields.iter()
+                    .map(|field| field["name"].as_str().unwrap_or("").to_string())
+                    .collect()
+            });
+            note_types.push(NoteType {
+                id: id.parse().map_err(|_| AnkiError::Json(format!("note type {}", id)))?,
+                name: model["name"].as_str().unwrap_or("").to_string(),
+                fields,
+            });
+        }
+        Ok(note_types)
+    }
+
+    /// Parse the names of the decks of a collection from JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `decks` - Decks by id as JSON
+    fn parse_decks(decks: &str) -> Result<HashMap<i64, String>, AnkiError> {
+        let decks: serde_json::Map<String, serde_json::Value> = serde_json::from_str(decks)?;
+        Ok(decks.iter()
+            .filter_map(|(id, deck)| Some((id.parse().ok()?, deck["name"].as_str()?.to_string())))
+            .collect())
+    }
+
+    /// Load the note types of a collection from its tables
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the collection
+    fn load_note_types(conn: &sqlite::Connection) -> Result<Vec<NoteType>, AnkiError> {
+        let mut note_types = Vec::new();
+        let mut cursor = conn.prepare("SELECT id, name FROM notetypes")?.cursor();
+        while let Some(row) = cursor.next()? {
+            note_types.push(NoteType {
+                id: row[0].as_integer().ok_or(DatabaseError::ValueNotInteger)?,
+                name: row[1].as_string().ok_or(DatabaseError::ValueNotString)?.to_string(),
+                fields: Vec::new(),
+            });
+        }
+        let mut cursor = conn.prepare("SELECT ntid, name FROM fields ORDER BY ntid, ord")?.cursor();
+        while let Some(row) = cursor.next()? {
+            let id = row[0].as_integer().ok_or(DatabaseError::ValueNotInteger)?;
+            let name = row[1].as_string().ok_or(DatabaseError::ValueNotString)?;
+            if let Some(note_type) = note_types.iter_mut().find(|note_type| note_type.id == id) {
+                note_type.fields.push(name.to_string());
+            }
+        }
+        Ok(note_types)
+    }
+
+    /// Load the names of the decks of a collection from its table
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the collection
+    fn load_decks(conn: &sqlite::Connection) -> Result<HashMap<i64, String>, AnkiError> {
+        let mut decks = HashMap::new();
+        let mut cursor = conn.prepare("SELECT id, name FROM decks")?.cursor();
+        while let Some(row) = cursor.next()? {
+            let id = row[0].as_integer().ok_or(DatabaseError::ValueNotInteger)?;
+            let name = row[1].as_string().ok_or(DatabaseError::ValueNotString)?;
+            // The levels of nested decks are separated like in JSON collections
+            decks.insert(id, name.replace(FIELD_SEPARATOR, "::"));
+        }
+        Ok(decks)
+    }
+
+    /// Check if the package has media files
+    pub fn has_media(&self) -> bool {
+        !self.media.is_empty()
+    }
+
+    /// Extract the media files of the package into a directory
+    ///
+    /// Returns the number of extracted files.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the `.apkg` file
+    /// * `dir` - The directory to extract to; it is created if missing
+    pub fn extract_media(&self, path: &Path, dir: &Path) -> Result<usize, AnkiError> {
+        let mut archive = zip::ZipArchive::new(fs::File::open(path)?)?;
+        fs::create_dir_all(dir)?;
+        let mut count = 0;
+        for (number, name) in self.media.iter() {
+            // Only the file name is used, so that no file is written outside the directory
+            let name = match Path::new(name).file_name() {
+                Some(name) => name,
+                None => { continue; },
+            };
+            let mut file = match archive.by_name(number) {
+                Ok(file) => file,
+                Err(_) => { continue; },
+            };
+            io::copy(&mut file, &mut fs::File::create(dir.join(name))?)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Convert the notes to rows which can be imported, see `crate::import::import_rows`
+    ///
+    /// Returns the rows together with what each column holds.
+    ///
+    /// # Arguments
+    ///
+    /// * `mapping` - What each field holds by note type id; notes of other types or types with
+    ///   only ignored fields are left out
+    /// * `category_source` - Where the category comes from, unless a field is mapped to it
+    /// * `keep_media` - Whether to keep references to media files in the texts
+    pub fn to_rows(
+        &self,
+        mapping: &HashMap<i64, Vec<Column>>,
+        category_source: CategorySource,
+        keep_media: bool,
+    ) -> (Vec<Vec<String>>, Vec<Column>) {
+        let mut columns = vec![Column::Category];
+        for field_columns in mapping.values() {
+            for column in field_columns {
+                if *column != Column::Ignore && !columns.contains(column) {
+                    columns.push(*column);
+                }
+            }
+        }
+        let mut rows = Vec::new();
+        for note in self.notes.iter() {
+            let field_columns = match mapping.get(&note.note_type_id) {
+                Some(field_columns) if field_columns.iter().any(|column| *column != Column::Ignore) =>
+                    field_columns,
+                _ => { continue; },
+            };
+            let mut row = vec![String::new(); columns.len()];
+            row[0] = match category_source {
+                CategorySource::Deck => note.deck.clone(),
+                CategorySource::Tag => note.tags.first().cloned().unwrap_or_default(),
+                CategorySource::None => String::new(),
+            };
+            for (field, column) in note.fields.iter().zip(field_columns.iter()) {
+                let text = html_to_text(field, keep_media);
+                if let Some(idx) = columns.iter().position(|found| found == column) {
+                    if *column != Column::Ignore && !text.is_empty() {
+                        row[idx] = text;
+                    }
+                }
+            }
+            rows.push(row);
+        }
+        (rows, columns)
+    }
+}
+
+
+/// Convert the HTML of a field to plain text
+///
+/// Line breaks are kept, other tags are removed and entities decoded. Images become
+/// `[image:name]` like sounds are `[sound:name]`; both are removed unless media is kept.
+///
+/// # Arguments
+///
+/// * `html` - The HTML of the field
+/// * `keep_media` - Whether to keep references to media files
+pub fn html_to_text(html: &str, keep_media: bool) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => { break; },
+        };
+        let tag = &rest[start + 1..end];
+        let name = tag.split_whitespace().next().unwrap_or("").to_lowercase();
+        if name == "br" || name == "br/" || name == "/div" || name == "/p" {
+            text.push('\n');
+        } else if name == "img" && keep_media {
+            let source = tag.split("src=").nth(1).and_then(|value| {
+                match value.strip_prefix('"').or_else(|| value.strip_prefix('\'')) {
+                    Some(quoted) => quoted.split(&['"', '\''][..]).next(),
+                    None => value.split(|c: char| c.is_whitespace() || c == '/').next(),
+                }
+            });
+            if let Some(source) = source.filter(|source| !source.is_empty()) {
+                text.push_str(&format!("[image:{}]", source));
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    if !keep_media {
+        while let Some(start) = text.find("[sound:") {
+            match text[start..].find(']') {
+                Some(end) => text.replace_range(start..start + end + 1, ""),
+                None => { break; },
+            }
+        }
+    }
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.lines().map(|line| line.trim()).collect::<Vec<_>>().join("\n").trim().to_string()
+}
+
+
+/// Options of a written Anki package
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PackageOptions {
+    /// Identifiers of the categories to export; empty for all categories
+    pub category_ids: Vec<i64>,
+    /// Directions to study as pairs of the prompt and the answer language id; each direction is
+    /// a card template of the note type
+    pub directions: Vec<(i64, i64)>,
+    /// Whether the descriptions are shown on the back of the cards
+    pub descriptions: bool,
+}
+
+
+/// Get the current time as Unix timestamp in milliseconds, which Anki uses for identifiers
+fn timestamp_millis() -> i64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_millis() as i64,
+        Err(_) => 0,
+    }
+}
+
+
+/// Get the identifier of the database for Anki, used for the deck, the notes and the note type;
+/// it is created on the first export
+///
+/// Using the same identifiers as well as the same note GUIDs for every export of a database lets
+/// Anki update the notes of a previous import instead of adding them again.
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+fn anki_id(conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+    if let Some(id) = Setting::get(conn, Setting::KEY_ANKI_ID)? {
+        return id.parse::<i64>().map_err(|_| DatabaseError::ValueInvalid(id));
+    }
+    let id = timestamp_millis();
+    Setting::set(conn, Setting::KEY_ANKI_ID, &id.to_string())?;
+    Ok(id)
+}
+
+
+/// Convert plain text to the HTML of a field
+///
+/// # Arguments
+///
+/// * `text` - The plain text
+fn text_to_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\n', "<br>")
+}
+
+
+/// Get the checksum of a note by which Anki finds duplicates
+///
+/// # Arguments
+///
+/// * `first_field` - The first field of the note as plain text
+fn checksum(first_field: &str) -> i64 {
+    let digest = sha1_smol::Sha1::from(first_field).digest().bytes();
+    i64::from(u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]))
+}
+
+
+/// Get the names of the note type's fields, the code of each language followed by its description
+///
+/// # Arguments
+///
+/// * `languages` - All languages in display order
+fn field_names(languages: &[Language]) -> Vec<String> {
+    let mut fields = Vec::new();
+    for language in languages {
+        fields.push(language.code.clone());
+        fields.push(format!("{} description", language.code));
+    }
+    fields
+}
+
+
+/// Get the identifier of the note type with the given fields
+///
+/// Anki keeps the fields of a note type it knows by its identifier, so a note type with other
+/// fields, e.g. after adding a language, needs a new identifier. It is derived from the fields,
+/// so the identifier does not change as long as the languages do not.
+///
+/// # Arguments
+///
+/// * `anki_id` - Identifier of the database for Anki, see `anki_id`
+/// * `fields` - The names of the fields, see `field_names`
+fn note_type_id(anki_id: i64, fields: &[String]) -> i64 {
+    let separator = FIELD_SEPARATOR.to_string();
+    let key = format!("{}{}{}", anki_id, separator, fields.join(&separator));
+    let digest = sha1_smol::Sha1::from(key).digest().bytes();
+    // 48 bits, positive and in the range of the millisecond timestamps Anki uses as identifiers
+    digest[..6].iter().fold(0, |id, byte| id << 8 | i64::from(*byte))
+}
+
+
+/// Build the note type as JSON with a field per language and its description
+///
+/// The fields are named by language code, see `field_names`, and the templates by language name.
+///
+/// # Arguments
+///
+/// * `id` - Identifier of the note type, see `note_type_id`
+/// * `deck_id` - Identifier of the deck
+/// * `languages` - All languages in display order
+/// * `options` - The options of the package
+fn note_type_json(
+    id: i64,
+    deck_id: i64,
+    languages: &[Language],
+    options: &PackageOptions,
+) -> serde_json::Value {
+    let fields = field_names(languages);
+    let index = |language_id: i64| languages.iter().position(|language| language.id == language_id);
+    let mut templates = Vec::new();
+    let mut requirements = Vec::new();
+    for (prompt_id, answer_id) in options.directions.iter() {
+        let (prompt, answer) = match (index(*prompt_id), index(*answer_id)) {
+            (Some(prompt), Some(answer)) => (prompt, answer),
+            _ => { continue; },
+        };
+        let prompt_name = &fields[prompt * 2];
+        let answer_name = &fields[answer * 2];
+        // The card is only generated if the note has both texts
+        let question = format!(
+            "{{{{#{a}}}}}{{{{#{p}}}}}<div class=text>{{{{{p}}}}}</div>{{{{/{p}}}}}{{{{/{a}}}}}",
+            p = prompt_name, a = answer_name,
+        );
+        let mut answer_html = format!(
+            "{{{{FrontSide}}}}<hr id=answer><div class=text>{{{{{}}}}}</div>", answer_name);
+        if options.descriptions {
+            for name in [prompt_name, answer_name].iter() {
+                answer_html.push_str(&format!(
+                    "{{{{#{n} description}}}}<div class=description>{{{{{n} description}}}}</div>\
+                    {{{{/{n} description}}}}",
+                    n = name,
+                ));
+            }
+        }
+        let ord = templates.len();
+        templates.push(json!({
+            "name": format!("{} → {}", languages[prompt].name, languages[answer].name),
+            "ord": ord,
+            "qfmt": question,
+            "afmt": answer_html,
+            "bqfmt": "",
+            "bafmt": "",
+            "did": null,
+        }));
+        requirements.push(json!([ord, "all", [prompt * 2, answer * 2]]));
+    }
+    let fields: Vec<serde_json::Value> = fields.iter().enumerate()
+        .map(|(ord, name)| json!({
+            "name": name,
+            "ord": ord,
+            "sticky": false,
+            "rtl": false,
+            "font": "Arial",
+            "size": 20,
+            "media": [],
+        }))
+        .collect();
+    json!({
+        "id": id,
+        "name": "Kaati Ako",
+        "type": 0,
+        "mod": timestamp_millis() / 1000,
+        "usn": -1,
+        "sortf": 0,
+        "did": deck_id,
+        "tmpls": templates,
+        "flds": fields,
+        "css": ".card { font-family: Arial; font-size: 30px; text-align: center; }\n\
+            .description { font-size: 18px; font-style: italic; }",
+        "latexPre": "\\documentclass[12pt]{article}\n\\pagestyle{empty}\n\\begin{document}\n",
+        "latexPost": "\\end{document}",
+        "latexsvg": false,
+        "req": requirements,
+        "tags": [],
+        "vers": [],
+    })
+}
+
+
+/// Build a deck as JSON
+///
+/// # Arguments
+///
+/// * `id` - Identifier of the deck
+/// * `name` - Name of the deck
+fn deck_json(id: i64, name: &str) -> serde_json::Value {
+    json!({
+        "id": id,
+        "name": name,
+        "mod": timestamp_millis() / 1000,
+        "usn": -1,
+        "desc": "",
+        "dyn": 0,
+        "conf": 1,
+        "collapsed": false,
+        "browserCollapsed": false,
+        "extendNew": 0,
+        "extendRev": 0,
+        "newToday": [0, 0],
+        "revToday": [0, 0],
+        "lrnToday": [0, 0],
+        "timeToday": [0, 0],
+    })
+}
+
+
+/// Write the collection of a package
+///
+/// Returns the number of written notes.
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `collection` - Connection to the empty collection
+/// * `name` - Name of the deck in Anki
+/// * `options` - The options of the package
+fn write_collection(
+    conn: &sqlite::Connection,
+    collection: &sqlite::Connection,
+    name: &str,
+    options: &PackageOptions,
+) -> Result<usize, AnkiError> {
+    let id = anki_id(conn)?;
+    let languages = Language::load_all(conn)?;
+    let filter = Filter { category_ids: options.category_ids.clone(), language_ids: Vec::new() };
+    let cards = load_cards(conn, &filter, &languages)?;
+    let now = timestamp_millis() / 1000;
+
+    collection.execute(SCHEMA)?;
+    let note_type_id = note_type_id(id, &field_names(&languages));
+    let models = json!({
+        note_type_id.to_string(): note_type_json(note_type_id, id, &languages, options),
+    });
+    let decks = json!({ "1": deck_json(1, "Default"), id.to_string(): deck_json(id, name) });
+    let deck_config = json!({ "1": {
+        "id": 1, "name": "Default", "mod": 0, "usn": 0, "maxTaken": 60, "autoplay": true,
+        "timer": 0, "replayq": true, "dyn": false,
+        "new": {
+            "delays": [1, 10], "ints": [1, 4, 7], "initialFactor": 2500, "order": 1,
+            "perDay": 20, "bury": false, "separate": true,
+        },
+        "rev": {
+            "perDay": 200, "ease4": 1.3, "fuzz": 0.05, "maxIvl": 36500, "ivlFct": 1,
+            "bury": false, "minSpace": 1,
+        },
+        "lapse": {"delays": [10], "mult": 0, "minInt": 1, "leechFails": 8, "leechAction": 0},
+    }});
+    let config = json!({
+        "nextPos": cards.len() + 1, "estTimes": true, "activeDecks": [1], "sortType": "noteFld",
+        "timeLim": 0, "sortBackwards": false, "addToCur": true, "curDeck": id, "newSpread": 0,
+        "dueCounts": true, "curModel": note_type_id, "collapseTime": 1200,
+    });
+    let mut cursor = collection.prepare(
+        "INSERT INTO col VALUES (1, ?, ?, ?, 11, 0, 0, 0, ?, ?, ?, ?, '{}')")?.cursor();
+    cursor.bind(&[
+        sqlite::Value::Integer(now),
+        sqlite::Value::Integer(now * 1000),
+        sqlite::Value::Integer(now * 1000),
+        sqlite::Value::String(config.to_string()),
+        sqlite::Value::String(models.to_string()),
+        sqlite::Value::String(decks.to_string()),
+        sqlite::Value::String(deck_config.to_string()),
+    ])?;
+    cursor.next()?;
+
+    let index = |language_id: i64| languages.iter().position(|language| language.id == language_id);
+    for (position, card) in cards.iter().enumerate() {
+        let mut fields = vec![String::new(); languages.len() * 2];
+        for translation in card.translations.iter() {
+            if let Some(idx) = index(translation.language_id) {
+                fields[idx * 2] = text_to_html(&translation.text);
+                fields[idx * 2 + 1] = text_to_html(&translation.description);
+            }
+        }
+        let first_field = html_to_text(fields.first().map_or("", |field| field.as_str()), false);
+        // Tags cannot contain spaces
+        let tag = card.category.split_whitespace().collect::<Vec<_>>().join("_");
+        // The ids only need to be unique, the GUID identifies the card across exports
+        let note_id = id + card.id;
+        let mut cursor = collection.prepare(
+            "INSERT INTO notes VALUES (?, ?, ?, ?, -1, ?, ?, ?, ?, 0, '')")?.cursor();
+        cursor.bind(&[
+            sqlite::Value::Integer(note_id),
+            sqlite::Value::String(format!("kaati_ako-{}-{}", id, card.id)),
+            sqlite::Value::Integer(note_type_id),
+            sqlite::Value::Integer(now),
+            sqlite::Value::String(if tag.is_empty() { String::new() } else { format!(" {} ", tag) }),
+            sqlite::Value::String(fields.join(&FIELD_SEPARATOR.to_string())),
+            sqlite::Value::String(first_field.clone()),
+            sqlite::Value::Integer(checksum(&first_field)),
+        ])?;
+        cursor.next()?;
+        let mut ord = 0;
+        for (prompt_id, answer_id) in options.directions.iter() {
+            let (prompt, answer) = match (index(*prompt_id), index(*answer_id)) {
+                (Some(prompt), Some(answer)) => (prompt, answer),
+                _ => { continue; },
+            };
+            if !fields[prompt * 2].is_empty() && !fields[answer * 2].is_empty() {
+                let mut cursor = collection.prepare("
+                    INSERT INTO cards VALUES (?, ?, ?, ?, ?, -1, 0, 0, ?, 0, 0, 0, 0, 0, 0, 0, 0, '')")?
+                    .cursor();
+                cursor.bind(&[
+                    sqlite::Value::Integer(note_id * 100 + ord),
+                    sqlite::Value::Integer(note_id),
+                    sqlite::Value::Integer(id),
+                    sqlite::Value::Integer(ord),
+                    sqlite::Value::Integer(now),
+                    sqlite::Value::Integer(position as i64 + 1),
+                ])?;
+                cursor.next()?;
+            }
+            ord += 1;
+        }
+    }
+    Ok(cards.len())
+}
+
+
+/// Write cards as notes to an Anki package
+///
+/// There is one note type with a field per language code and its description, and a card template
+/// per direction. Exporting the same database again yields the same note GUIDs, so Anki updates the
+/// notes of the previous import. Adding, removing or reordering languages yields a new note type
+/// instead, see `note_type_id`, which Anki adds besides the one of the previous import. The
+/// category of a card becomes the tag of its note. Returns the number of written notes.
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `path` - Path to the `.apkg` file
+/// * `name` - Name of the deck in Anki
+/// * `options` - The options of the package
+pub fn write_package(
+    conn: &sqlite::Connection,
+    path: &Path,
+    name: &str,
+    options: &PackageOptions,
+) -> Result<usize, AnkiError> {
+    // The temporary collection is removed with its directory on every path
+    let tmp_dir = TempDir::new()?;
+    let (tmp_path, _) = tmp_dir.create_file(COLLECTION_ANKI2)?;
+    let count = write_collection(conn, &sqlite::open(&tmp_path)?, name, options)?;
+    let mut archive = zip::ZipWriter::new(fs::File::create(path)?);
+    let file_options = zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    archive.start_file(COLLECTION_ANKI2, file_options)?;
+    io::copy(&mut fs::File::open(&tmp_path)?, &mut archive)?;
+    archive.start_file(MEDIA, file_options)?;
+    io::Write::write_all(&mut archive, b"{}")?;
+    archive.finish()?;
+    Ok(count)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrate;
+
+    /// Open a database with two languages and two cards in different categories
+    fn open_database() -> sqlite::Connection {
+        let conn = sqlite::open(":memory:").unwrap();
+        migrate(&conn).unwrap();
+        conn.execute("
+            INSERT INTO language (code, name, position) VALUES ('to', 'Tongan', 1);
+            INSERT INTO language (code, name, position) VALUES ('en', 'English', 2);
+            INSERT INTO category (name) VALUES ('default');
+            INSERT INTO category (name) VALUES ('farm animals');
+            INSERT INTO card (category_id) VALUES (1);
+            INSERT INTO translation (card_id, language_id, text, description)
+                VALUES (1, 1, 'kaati', '');
+            INSERT INTO translation (card_id, language_id, text, description)
+                VALUES (1, 2, 'card', 'A flash card');
+            INSERT INTO card (category_id) VALUES (2);
+            INSERT INTO translation (card_id, language_id, text, description)
+                VALUES (2, 1, 'puaka', '');
+            INSERT INTO translation (card_id, language_id, text, description)
+                VALUES (2, 2, 'pig', '');
+            ").unwrap();
+        conn
+    }
+
+    /// Write a package with both directions and read the ids and GUIDs of its notes and cards
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `dir` - The directory to write the package to
+    fn write_and_read(conn: &sqlite::Connection, dir: &TempDir) -> (AnkiPackage, Vec<String>) {
+        let path = dir.path.join("deck.apkg");
+        let options = PackageOptions {
+            category_ids: Vec::new(),
+            directions: vec![(1, 2), (2, 1)],
+            descriptions: true,
+        };
+        assert_eq!(write_package(conn, &path, "Kaati Ako", &options).unwrap(), 2);
+        let package = AnkiPackage::read(&path).unwrap();
+
+        let mut archive = zip::ZipArchive::new(fs::File::open(&path).unwrap()).unwrap();
+        let (collection_path, mut file) = dir.create_file("read.anki2").unwrap();
+        io::copy(&mut archive.by_name(COLLECTION_ANKI2).unwrap(), &mut file).unwrap();
+        let collection = sqlite::open(&collection_path).unwrap();
+        let mut rows = Vec::new();
+        let statement = collection.prepare("
+            SELECT 'note', id, guid, mid FROM notes
+            UNION ALL SELECT 'card', id, nid, did FROM cards
+            ORDER BY 1, 2").unwrap();
+        let mut cursor = statement.cursor();
+        while let Some(row) = cursor.next().unwrap() {
+            rows.push(format!(
+                "{} {} {} {}",
+                row[0].as_string().unwrap(),
+                row[1].as_integer().unwrap(),
+                row[2].as_string().map_or_else(
+                    || row[2].as_integer().unwrap().to_string(), |guid| guid.to_string()),
+                row[3].as_integer().unwrap(),
+            ));
+        }
+        drop(cursor);
+        drop(collection);
+        fs::remove_file(&collection_path).unwrap();
+        (package, rows)
+    }
+
+    #[test]
+    fn test_export_is_stable() {
+        let conn = open_database();
+        let dir = TempDir::new().unwrap();
+        let (package, rows) = write_and_read(&conn, &dir);
+        let id = anki_id(&conn).unwrap();
+        let note_type_id = note_type_id(id, &field_names(&Language::load_all(&conn).unwrap()));
+        assert_eq!(rows.len(), 6);
+        assert!(rows.contains(&format!("note {} kaati_ako-{}-1 {}", id + 1, id, note_type_id)));
+        assert!(rows.contains(&format!("note {} kaati_ako-{}-2 {}", id + 2, id, note_type_id)));
+        assert!(rows.contains(&format!("card {} {} {}", (id + 1) * 100 + 1, id + 1, id)));
+
+        assert_eq!(package.note_types.len(), 1);
+        assert_eq!(package.note_types[0].id, note_type_id);
+        assert_eq!(
+            package.note_types[0].fields, vec!["to", "to description", "en", "en description"]);
+        assert_eq!(package.notes[1].fields, vec!["puaka", "", "pig", ""]);
+        assert_eq!(package.notes[1].tags, vec!["farm_animals"]);
+        assert_eq!(package.notes[1].deck, "Kaati Ako");
+
+        // Exporting again keeps the identifiers, so Anki updates the notes
+        let (_, rows_again) = write_and_read(&conn, &dir);
+        assert_eq!(rows_again, rows);
+    }
+
+    #[test]
+    fn test_note_type_id_changes_with_fields() {
+        let conn = open_database();
+        let dir = TempDir::new().unwrap();
+        let (package, rows) = write_and_read(&conn, &dir);
+        conn.execute("INSERT INTO language (code, name, position) VALUES ('de', 'German', 3)")
+            .unwrap();
+        let (package_again, rows_again) = write_and_read(&conn, &dir);
+        assert_ne!(package_again.note_types[0].id, package.note_types[0].id);
+        assert_eq!(package_again.note_types[0].fields.len(), 6);
+        // The notes keep their GUIDs and ids
+        let ids = |rows: &[String]| -> Vec<String> {
+            rows.iter().map(|row| row.rsplit_once(' ').unwrap().0.to_string()).collect()
+        };
+        assert_eq!(ids(&rows_again), ids(&rows));
+
+        let fields = vec!["to".to_string(), "to description".to_string()];
+        assert_eq!(note_type_id(1, &fields), note_type_id(1, &fields.clone()));
+        assert_ne!(note_type_id(1, &fields), note_type_id(2, &fields));
+        assert_ne!(note_type_id(1, &fields), note_type_id(1, &fields[..1]));
+        assert!(note_type_id(1, &fields) > 0);
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        assert_eq!(html_to_text("a &amp;lt; b&nbsp;&quot;c&quot;", false), "a &lt; b \"c\"");
+        assert_eq!(html_to_text("one<br>two<br/>three", false), "one\ntwo\nthree");
+        assert_eq!(html_to_text("<div>one</div><div><b>two</b></div>", false), "one\ntwo");
+        let html = "kulī [sound:kuli.mp3]<img src=\"dog.png\">";
+        assert_eq!(html_to_text(html, false), "kulī");
+        assert_eq!(html_to_text(html, true), "kulī [sound:kuli.mp3][image:dog.png]");
+        assert_eq!(html_to_text("<img src='a b.jpg' />", true), "[image:a b.jpg]");
+    }
+
+    #[test]
+    fn test_to_rows() {
+        let package = AnkiPackage {
+            note_types: Vec::new(),
+            notes: vec![
+                Note {
+                    note_type_id: 1,
+                    fields: vec!["kulī<br>[sound:kuli.mp3]".to_string(), "dog".to_string()],
+                    tags: vec!["animals".to_string()],
+                    deck: "Tongan".to_string(),
+                },
+                Note {
+                    note_type_id: 2,
+                    fields: vec!["ignored".to_string()],
+                    tags: Vec::new(),
+                    deck: String::new(),
+                },
+            ],
+            media: HashMap::new(),
+        };
+        let mut mapping = HashMap::new();
+        mapping.insert(1, vec![Column::Text(1), Column::Text(2)]);
+        mapping.insert(2, vec![Column::Ignore]);
+        let (rows, columns) = package.to_rows(&mapping, CategorySource::Tag, false);
+        assert_eq!(columns, vec![Column::Category, Column::Text(1), Column::Text(2)]);
+        assert_eq!(rows, vec![vec!["animals".to_string(), "kulī".to_string(), "dog".to_string()]]);
+        let (rows, _) = package.to_rows(&mapping, CategorySource::Deck, true);
+        assert_eq!(rows[0][0], "Tongan");
+        assert_eq!(rows[0][1], "kulī\n[sound:kuli.mp3]");
+    }
+
+    #[test]
+    fn test_extract_media_keeps_file_names_only() {
+        let dir = TempDir::new().unwrap();
+        let (path, file) = dir.create_file("media.apkg").unwrap();
+        let mut archive = zip::ZipWriter::new(file);
+        archive.start_file("0", zip::write::FileOptions::default()).unwrap();
+        io::Write::write_all(&mut archive, b"sound").unwrap();
+        archive.finish().unwrap();
+
+        let mut package = AnkiPackage::default();
+        package.media.insert("0".to_string(), "../../x.mp3".to_string());
+        package.media.insert("1".to_string(), "missing.mp3".to_string());
+        let media_dir = dir.path.join("deck.media");
+        assert_eq!(package.extract_media(&path, &media_dir).unwrap(), 1);
+        assert_eq!(fs::read(media_dir.join("x.mp3")).unwrap(), b"sound");
+        assert!(!dir.path.join("x.mp3").exists());
+    }
+}
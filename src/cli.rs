@@ -0,0 +1,811 @@
+//! Command-line interface to script and inspect a database without a display
+//!
+//! The first argument names a command, the options may follow in any order. `--db` chooses the
+//! database file, which otherwise is found as for the GUI, see `crate::database::get_path`.
+//! `--json` prints JSON instead of plain text, which is tab separated for lists.
+//!
+//! # Example
+//!
+//! ```sh
+//! kaati_ako add --category animals --text to=pusi --text en=cat --description en="Miau"
+//! kaati_ako list --category animals --json
+//! kaati_ako export --format tsv --language to --language en --output animals.tsv
+//! kaati_ako review
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::VERSION;
+use crate::database::{DatabaseError, get_path};
+use crate::export::Format;
+use crate::import::{Column, ParseError, parse_file};
+use crate::models::Model;
+use crate::models::card::Card;
+use crate::models::filter::Filter;
+use crate::models::language::Language;
+use crate::models::translation::Translation;
+use crate::repository::Repository;
+use crate::session::Session;
+use crate::settings::Settings;
+use crate::tui::Tui;
+
+
+/// All commands with their arguments and a description, as shown by `help`
+const COMMANDS: [(&str, &str, &str); 12] = [
+    ("init", "", "Create an empty database, or migrate an existing one"),
+    ("add", "--text LANG=TEXT... [--description LANG=TEXT]... [--category NAME]",
+        "Add a card; a missing category is created"),
+    ("list", "[--category NAME]", "List the cards with their category and texts"),
+    ("show", "ID", "Show a card with its translations"),
+    ("edit", "ID [--text LANG=TEXT]... [--description LANG=TEXT]... [--category NAME]",
+        "Change the category or translations of a card"),
+    ("delete", "ID", "Delete a card with its translations and review history"),
+    ("categories", "", "List the categories with their number of cards"),
+    ("languages", "[add CODE NAME]",
+        "List the languages in display order, or add one by its ISO 639-1 code and name"),
+    ("import", "FILE [--category NAME]",
+        "Import a CSV or TSV file whose header names the languages, or an exported JSON file"),
+    ("export", "[--format csv|tsv|json] [--category NAME]... [--language LANG]... [--output FILE]",
+        "Export cards, by default all of them as CSV to standard output"),
+    ("review", "", "Study the cards in the terminal as in the main window, e.g. over SSH"),
+    ("help", "", "Show this help"),
+];
+
+/// Options which are followed by a value
+const VALUE_OPTIONS: [&str; 7] =
+    ["--category", "--db", "--description", "--format", "--language", "--output", "--text"];
+
+
+/// An error which can occur when running a command
+#[derive(Debug)]
+pub enum CliError {
+    /// The command line is not valid, the reason should be in the string
+    Usage(String),
+    /// An error within the database occurred
+    Database(DatabaseError),
+    /// A file could not be read or written, the error message should be in the string
+    Io(String),
+    /// A file to import could not be parsed
+    Parse(ParseError),
+    /// The terminal could not be used, the error message should be in the string
+    Terminal(String),
+}
+
+/// The implementation of the Error trait is empty
+impl Error for CliError {}
+
+
+/// Implement the From trait to convert a DatabaseError to a CliError
+impl From<DatabaseError> for CliError {
+    fn from(err: DatabaseError) -> Self {
+        CliError::Database(err)
+    }
+}
+
+
+/// Implement the From trait to convert an io::Error to a CliError
+impl From<io::Error> for CliError {
+    fn from(err: io::Error) -> Self {
+        CliError::Io(err.to_string())
+    }
+}
+
+
+/// Implement the From trait to convert a ParseError to a CliError
+impl From<ParseError> for CliError {
+    fn from(err: ParseError) -> Self {
+        CliError::Parse(err)
+    }
+}
+
+
+/// Implement the Display trait to show a CliError
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(reason) => write!(f, "Usage error: {}! See `kaati_ako help`.", reason),
+            CliError::Database(err) => write!(f, "{}", err),
+            CliError::Io(msg) => write!(f, "File error: {}!", msg),
+            CliError::Parse(err) => write!(f, "{}", err),
+            CliError::Terminal(msg) => write!(f, "Terminal error: {}!", msg),
+        }
+    }
+}
+
+
+/// The parsed command line
+#[derive(Debug, Default)]
+struct Arguments {
+    /// Name of the command
+    command: String,
+    /// Arguments which are not options, e.g. the id of a card
+    positional: Vec<String>,
+    /// Options with their values in the given order, e.g. `("--text", "en=cat")`
+    options: Vec<(String, String)>,
+    /// Whether to print JSON instead of plain text
+    json: bool,
+}
+
+
+impl Arguments {
+    /// Parse the command line, without the program name
+    ///
+    /// Options are given as `--name value` or `--name=value`.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments, starting with the command
+    fn parse(args: &[String]) -> Result<Arguments, CliError> {
+        let mut arguments = Arguments::default();
+        let mut args = args.iter();
+        arguments.command = args.next().cloned().unwrap_or_else(|| "help".to_string());
+        while let Some(arg) = args.next() {
+            if arg == "--json" {
+                arguments.json = true;
+            } else if let Some((name, value)) = arg.strip_prefix("--").and_then(
+                |option| option.split_once('=')) {
+                let name = format!("--{}", name);
+                if !VALUE_OPTIONS.contains(&name.as_str()) {
+                    return Err(CliError::Usage(format!("Unknown option {}", name)));
+                }
+                arguments.options.push((name, value.to_string()));
+            } else if VALUE_OPTIONS.contains(&arg.as_str()) {
+                match args.next() {
+                    Some(value) => arguments.options.push((arg.clone(), value.clone())),
+                    None => { return Err(CliError::Usage(format!("{} needs a value", arg))); },
+                }
+            } else if arg.starts_with("--") {
+                return Err(CliError::Usage(format!("Unknown option {}", arg)));
+            } else {
+                arguments.positional.push(arg.clone());
+            }
+        }
+        Ok(arguments)
+    }
+
+    /// Get all values of an option in the given order
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the option, e.g. `--text`
+    fn values(&self, name: &str) -> Vec<&str> {
+        self.options.iter()
+            .filter(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    /// Get the last value of an option
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the option, e.g. `--category`
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values(name).pop()
+    }
+
+    /// Get the id given as the first positional argument
+    fn id(&self) -> Result<i64, CliError> {
+        match self.positional.first() {
+            Some(id) => id.parse::<i64>()
+                .map_err(|_| CliError::Usage(format!("{} is not a card id", id))),
+            None => Err(CliError::Usage(format!("{} needs a card id", self.command))),
+        }
+    }
+}
+
+
+/// Check if an argument is a command, i.e. whether to run the command-line interface
+///
+/// # Arguments
+///
+/// * `arg` - The first argument of the command line
+pub fn is_command(arg: &str) -> bool {
+    COMMANDS.iter().any(|(name, _, _)| *name == arg)
+}
+
+
+/// Get the help text with all commands
+fn help() -> String {
+    let mut text = format!(
+        "kaati_ako {} - learn language vocabulary by flash cards\n\n\
+        Usage: kaati_ako COMMAND [ARGUMENTS] [--db PATH] [--json]\n\n\
+        Without a command, the GUI is started. LANG is the code or name of a language.\n\n\
+        Commands:\n",
+        VERSION,
+    );
+    for (name, arguments, description) in COMMANDS.iter() {
+        text.push_str(&format!("  {} {}\n      {}\n", name, arguments, description));
+    }
+    text
+}
+
+
+/// Find a language by its code or name, ignoring the case
+///
+/// # Arguments
+///
+/// * `languages` - All languages of the database
+/// * `key` - The code or name of the language
+fn find_language<'a>(languages: &'a [Language], key: &str) -> Result<&'a Language, CliError> {
+    let key = key.trim().to_lowercase();
+    languages.iter()
+        .find(|language| language.code == key || language.name.to_lowercase() == key)
+        .ok_or_else(|| CliError::Usage(format!("Unknown language {}", key)))
+}
+
+
+/// Get the id of the default category: the category called default, otherwise the first one, as
+/// in the import wizard
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+fn default_category_id(repository: &Repository) -> Result<i64, CliError> {
+    let categories = repository.categories()?;
+    categories.iter()
+        .find(|category| category.name == "default")
+        .or_else(|| categories.first())
+        .map(|category| category.id)
+        .ok_or(CliError::Database(DatabaseError::NotFound))
+}
+
+
+/// Save a card with its translations in the category given by `--category`, which is created
+/// within the same transaction if missing
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `arguments` - The parsed command line
+/// * `card` - The card to save; its id is set if it is new
+/// * `translations` - The translations of the card
+fn save_card(
+    repository: &Repository,
+    arguments: &Arguments,
+    card: &mut Card,
+    translations: &mut [Translation],
+) -> Result<i64, CliError> {
+    match arguments.value("--category").map(|name| name.trim()) {
+        Some(name) if !name.is_empty() => {
+            Ok(repository.save_card_in_category(card, translations, name)?)
+        },
+        _ => Ok(repository.save_card(card, translations)?),
+    }
+}
+
+
+/// Set the texts and descriptions given by `--text` and `--description` on the translations
+/// of a card
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `arguments` - The parsed command line
+/// * `card_id` - Identifier of the card; 0 for a new card
+fn apply_translations(
+    repository: &Repository,
+    arguments: &Arguments,
+    card_id: i64,
+) -> Result<Vec<Translation>, CliError> {
+    let languages = repository.languages()?;
+    let mut translations: Vec<Translation> = Vec::new();
+    for (option, value) in arguments.options.iter() {
+        if option != "--text" && option != "--description" {
+            continue;
+        }
+        let (key, text) = value.split_once('=').ok_or_else(|| CliError::Usage(
+            format!("{} needs the form LANG=TEXT, not {}", option, value)))?;
+        let language = find_language(&languages, key)?;
+        let idx = match translations.iter().position(
+            |translation| translation.language_id == language.id) {
+            Some(idx) => idx,
+            None => {
+                translations.push(repository.translation(card_id, language.id)?);
+                translations.len() - 1
+            },
+        };
+        if option == "--text" {
+            translations[idx].text = text.trim().to_string();
+        } else {
+            translations[idx].description = text.trim().to_string();
+        }
+    }
+    Ok(translations)
+}
+
+
+/// Convert a card with its translations to JSON
+///
+/// # Arguments
+///
+/// * `card` - The card
+/// * `category` - Name of the card's category
+/// * `translations` - The translations of the card
+/// * `languages` - All languages of the database
+fn card_json(
+    card: &Card,
+    category: &str,
+    translations: &[Translation],
+    languages: &[Language],
+) -> serde_json::Value {
+    let translations: Vec<serde_json::Value> = translations.iter()
+        .map(|translation| json!({
+            "language": languages.iter()
+                .find(|language| language.id == translation.language_id)
+                .map_or("", |language| language.code.as_str()),
+            "text": translation.text,
+            "description": translation.description,
+        }))
+        .collect();
+    json!({"id": card.id, "category": category, "translations": translations})
+}
+
+
+/// Create the database if it is missing, otherwise migrate it
+///
+/// # Arguments
+///
+/// * `db_path` - Path to the database file
+/// * `arguments` - The parsed command line
+fn init(db_path: &str, arguments: &Arguments) -> Result<String, CliError> {
+    let is_new = !Path::new(db_path).exists();
+    // Unlike the first start of the application, without example cards
+    let repository = Repository::create(db_path)?;
+    let version = repository.schema_version()?;
+    Ok(if arguments.json {
+        json!({"path": db_path, "created": is_new, "schema_version": version}).to_string()
+    } else if is_new {
+        format!("Created {} with schema version {}", db_path, version)
+    } else {
+        format!("Migrated {} to schema version {}", db_path, version)
+    })
+}
+
+
+/// Add a card
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `arguments` - The parsed command line
+fn add(repository: &Repository, arguments: &Arguments) -> Result<String, CliError> {
+    let mut translations = apply_translations(repository, arguments, 0)?;
+    if translations.iter().all(|translation| translation.text.is_empty()) {
+        return Err(CliError::Usage("add needs at least one --text".to_string()));
+    }
+    let mut card = Card::from_empty();
+    card.category_id = default_category_id(repository)?;
+    let id = save_card(repository, arguments, &mut card, &mut translations)?;
+    Ok(if arguments.json { json!({"id": id}).to_string() } else { format!("Added card {}", id) })
+}
+
+
+/// List the cards, optionally of one category only
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `arguments` - The parsed command line
+fn list(repository: &Repository, arguments: &Arguments) -> Result<String, CliError> {
+    let categories = repository.categories()?;
+    let languages = repository.languages()?;
+    let only = match arguments.value("--category") {
+        Some(name) => Some(repository.category_by_name(name.trim())?.id),
+        None => None,
+    };
+    let mut cards = Vec::new();
+    let mut lines = Vec::new();
+    for card in repository.cards()? {
+        if only.is_some() && only != Some(card.category_id) {
+            continue;
+        }
+        let category = categories.iter()
+            .find(|category| category.id == card.category_id)
+            .map_or("", |category| category.name.as_str());
+        let translations = repository.translations(card.id)?;
+        if arguments.json {
+            cards.push(card_json(&card, category, &translations, &languages));
+        } else {
+            let texts: Vec<&str> = translations.iter()
+                .filter(|translation| !translation.text.is_empty())
+                .map(|translation| translation.text.as_str())
+                .collect();
+            lines.push(format!("{}\t{}\t{}", card.id, category, texts.join(" | ")));
+        }
+    }
+    Ok(if arguments.json { serde_json::Value::from(cards).to_string() } else { lines.join("\n") })
+}
+
+
+/// Show a card with its translations
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `arguments` - The parsed command line
+fn show(repository: &Repository, arguments: &Arguments) -> Result<String, CliError> {
+    let card = repository.card(arguments.id()?)?;
+    let category = repository.category(card.category_id)?;
+    let languages = repository.languages()?;
+    let translations = repository.translations(card.id)?;
+    if arguments.json {
+        return Ok(card_json(&card, &category.name, &translations, &languages).to_string());
+    }
+    let mut lines = vec![format!("Card {}", card.id), format!("Category: {}", category.name)];
+    for translation in translations.iter() {
+        let name = languages.iter()
+            .find(|language| language.id == translation.language_id)
+            .map_or("", |language| language.name.as_str());
+        lines.push(format!("{}: {}", name, translation.text));
+        if !translation.description.is_empty() {
+            lines.push(format!("  {}", translation.description.replace('\n', "\n  ")));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+
+/// Change the category or translations of a card
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `arguments` - The parsed command line
+fn edit(repository: &Repository, arguments: &Arguments) -> Result<String, CliError> {
+    let mut card = repository.card(arguments.id()?)?;
+    let mut translations = apply_translations(repository, arguments, card.id)?;
+    let id = save_card(repository, arguments, &mut card, &mut translations)?;
+    Ok(if arguments.json { json!({"id": id}).to_string() } else { format!("Saved card {}", id) })
+}
+
+
+/// Delete a card
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `arguments` - The parsed command line
+fn delete(repository: &Repository, arguments: &Arguments) -> Result<String, CliError> {
+    // Loading the card first reports a missing card instead of silently deleting nothing
+    let card = repository.card(arguments.id()?)?;
+    repository.delete_card(card.id)?;
+    Ok(if arguments.json {
+        json!({"id": card.id}).to_string()
+    } else {
+        format!("Deleted card {}", card.id)
+    })
+}
+
+
+/// List the categories with their number of cards
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `arguments` - The parsed command line
+fn categories(repository: &Repository, arguments: &Arguments) -> Result<String, CliError> {
+    let mut items = Vec::new();
+    let mut lines = Vec::new();
+    for category in repository.categories()? {
+        let count = repository.count_cards(category.id)?;
+        items.push(json!({"id": category.id, "name": category.name, "cards": count}));
+        lines.push(format!("{}\t{}\t{}", category.id, category.name, count));
+    }
+    Ok(if arguments.json { serde_json::Value::from(items).to_string() } else { lines.join("\n") })
+}
+
+
+/// Add a language after the last one, e.g. `languages add to Tongan`
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `arguments` - The parsed command line
+fn add_language(repository: &Repository, arguments: &Arguments) -> Result<String, CliError> {
+    let (code, name) = match arguments.positional.as_slice() {
+        [_, code, name] => (code.trim().to_lowercase(), name.trim()),
+        _ => { return Err(CliError::Usage("languages add needs a CODE and a NAME".to_string())); },
+    };
+    // The same checks as in the language manager
+    if !Language::is_valid_code(&code) {
+        return Err(CliError::Usage(
+            format!("{} is not a two-letter ISO 639-1 language code", code)));
+    }
+    if name.is_empty() {
+        return Err(CliError::Usage("the name of a language must not be empty".to_string()));
+    }
+    for other in repository.languages()? {
+        if other.code == code || other.name == name {
+            return Err(CliError::Usage(
+                format!("the language {} ({}) exists already", other.name, other.code)));
+        }
+    }
+    let mut language = Language::from_empty();
+    language.code = code;
+    language.name = name.to_string();
+    let id = repository.save_language(&mut language)?;
+    Ok(if arguments.json {
+        json!({"id": id, "code": language.code, "name": language.name}).to_string()
+    } else {
+        format!("Added language {} ({})", language.name, language.code)
+    })
+}
+
+
+/// List the languages in display order, or add one with `languages add CODE NAME`
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `arguments` - The parsed command line
+fn languages(repository: &Repository, arguments: &Arguments) -> Result<String, CliError> {
+    match arguments.positional.first().map(|action| action.as_str()) {
+        Some("add") => { return add_language(repository, arguments); },
+        Some(action) => { return Err(CliError::Usage(format!("Unknown action {}", action))); },
+        None => {},
+    }
+    let mut items = Vec::new();
+    let mut lines = Vec::new();
+    for language in repository.languages()? {
+        items.push(json!({"id": language.id, "code": language.code, "name": language.name}));
+        lines.push(format!("{}\t{}\t{}", language.id, language.code, language.name));
+    }
+    Ok(if arguments.json { serde_json::Value::from(items).to_string() } else { lines.join("\n") })
+}
+
+
+/// Import a CSV or TSV file whose header names the languages, e.g. `Category,English,Tongan`, or
+/// a JSON file as written by `export --format json`
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `arguments` - The parsed command line
+fn import(repository: &Repository, arguments: &Arguments) -> Result<String, CliError> {
+    let path = match arguments.positional.first() {
+        Some(path) => Path::new(path),
+        None => { return Err(CliError::Usage("import needs a file".to_string())); },
+    };
+    let text = fs::read_to_string(path)?;
+    let mut rows = parse_file(path, &text)?;
+    let languages = repository.languages()?;
+    let mut columns: Vec<Column> = rows.first().map_or(Vec::new(), |header| header.iter()
+        .map(|header| Column::guess(header, &languages))
+        .collect());
+    if !columns.iter().any(|column| matches!(column, Column::Text(_))) {
+        return Err(CliError::Usage(
+            "the header of the file names no language, e.g. English,Tongan".to_string()));
+    }
+    if let Some(name) = arguments.value("--category").map(|name| name.trim()) {
+        // Rows without a category get the given one, which the import creates with the first
+        // card in it if missing, within the same transaction
+        let idx = match columns.iter().position(|column| *column == Column::Category) {
+            Some(idx) => idx,
+            None => {
+                columns.push(Column::Category);
+                columns.len() - 1
+            },
+        };
+        for row in rows.iter_mut().skip(1) {
+            row.resize(row.len().max(idx + 1), String::new());
+            if row[idx].trim().is_empty() {
+                row[idx] = name.to_string();
+            }
+        }
+    }
+    let category_id = default_category_id(repository)?;
+    let summary = repository.import_rows(&rows[1..], &columns, category_id)?;
+    Ok(if arguments.json {
+        json!({
+            "imported": summary.imported,
+            "skipped": summary.skipped,
+            "failed": summary.failed,
+        }).to_string()
+    } else {
+        format!(
+            "Imported {} cards, skipped {} duplicates, {} rows failed.",
+            summary.imported, summary.skipped, summary.failed,
+        )
+    })
+}
+
+
+/// Export cards to standard output or a file
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `arguments` - The parsed command line
+fn export(repository: &Repository, arguments: &Arguments) -> Result<String, CliError> {
+    let name = arguments.value("--format").unwrap_or("csv");
+    let format = Format::from_name(&name.to_lowercase())
+        .ok_or_else(|| CliError::Usage(format!("Unknown format {}", name)))?;
+    let mut filter = Filter::default();
+    for name in arguments.values("--category") {
+        filter.category_ids.push(repository.category_by_name(name.trim())?.id);
+    }
+    let languages = repository.languages()?;
+    for key in arguments.values("--language") {
+        filter.language_ids.push(find_language(&languages, key)?.id);
+    }
+    let text = repository.export_cards(format, &filter)?;
+    match arguments.value("--output") {
+        Some(path) => {
+            fs::write(path, text)?;
+            Ok(if arguments.json {
+                json!({"path": path}).to_string()
+            } else {
+                format!("Exported the cards to {}", path)
+            })
+        },
+        None => Ok(text.trim_end().to_string()),
+    }
+}
+
+
+/// Study the cards in the terminal with the languages and study mode of the settings
+///
+/// # Arguments
+///
+/// * `repository` - The repository of flash cards
+/// * `settings` - The settings of the user
+/// * `arguments` - The parsed command line
+fn review(
+    repository: Repository,
+    settings: &Settings,
+    arguments: &Arguments,
+) -> Result<String, CliError> {
+    let mut session = Session::new();
+    session.mode = settings.mode;
+    session.prompt_language_id = settings.prompt_language_id;
+    session.answer_language_ids = settings.answer_language_ids.clone();
+    let language_ids: Vec<i64> = repository.languages()?.iter()
+        .map(|language| language.id)
+        .collect();
+    session.retain_languages(&language_ids);
+    let reviewed = Tui::run(repository, session)
+        .map_err(|err| CliError::Terminal(err.to_string()))?;
+    Ok(if arguments.json {
+        json!({"reviewed": reviewed}).to_string()
+    } else {
+        format!("Reviewed {} cards", reviewed)
+    })
+}
+
+
+/// Run a command, which is not `help`
+///
+/// # Arguments
+///
+/// * `arguments` - The parsed command line
+fn run_command(arguments: &Arguments) -> Result<String, CliError> {
+    let settings = Settings::load();
+    let db_path = match arguments.value("--db") {
+        Some(path) => path.to_string(),
+        None => get_path(settings.db_path.as_deref()),
+    };
+    if arguments.command == "init" {
+        return init(&db_path, arguments);
+    }
+    if !Path::new(&db_path).exists() {
+        // Opening would create it, which only `init` should do
+        return Err(CliError::Io(format!("{} does not exist, run `kaati_ako init` first", db_path)));
+    }
+    let repository = Repository::open(&db_path)?;
+    match arguments.command.as_str() {
+        "add" => add(&repository, arguments),
+        "list" => list(&repository, arguments),
+        "show" => show(&repository, arguments),
+        "edit" => edit(&repository, arguments),
+        "delete" => delete(&repository, arguments),
+        "categories" => categories(&repository, arguments),
+        "languages" => languages(&repository, arguments),
+        "import" => import(&repository, arguments),
+        "export" => export(&repository, arguments),
+        "review" => review(repository, &settings, arguments),
+        command => Err(CliError::Usage(format!("Unknown command {}", command))),
+    }
+}
+
+
+/// Run the command-line interface and return the exit code
+///
+/// The output is printed to standard output, errors to standard error. The exit code is 0 on
+/// success, 1 on errors and 2 on an invalid command line.
+///
+/// # Arguments
+///
+/// * `args` - The arguments without the program name, starting with the command
+pub fn run(args: &[String]) -> i32 {
+    let result = Arguments::parse(args).and_then(|arguments| {
+        if arguments.command == "help" {
+            Ok(help())
+        } else {
+            run_command(&arguments)
+        }
+    });
+    match result {
+        Ok(output) => {
+            // Not `println!`, which panics when the output is piped into e.g. `head`
+            if !output.is_empty() {
+                writeln!(io::stdout(), "{}", output).ok();
+            }
+            0
+        },
+        Err(err) => {
+            eprintln!("{}", err);
+            match err {
+                CliError::Usage(_) => 2,
+                _ => 1,
+            }
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    /// Run a command on the given database and return its output
+    ///
+    /// # Arguments
+    ///
+    /// * `db_path` - Path to the database file
+    /// * `args` - The arguments, starting with the command
+    fn run_on(db_path: &str, args: &[&str]) -> Result<String, CliError> {
+        let mut args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        args.push(format!("--db={}", db_path));
+        run_command(&Arguments::parse(&args)?)
+    }
+
+    #[test]
+    fn test_init_add_list() {
+        let path = env::temp_dir().join(format!("kaati_ako_cli_{}.sqlite", process::id()));
+        fs::remove_file(&path).ok();
+        let db_path = path.to_str().unwrap();
+
+        assert!(run_on(db_path, &["list"]).is_err());
+        assert!(run_on(db_path, &["init"]).unwrap().starts_with("Created"));
+        // A new database has no languages yet
+        assert!(matches!(
+            run_on(db_path, &["add", "--text", "to=kulī"]), Err(CliError::Usage(_))));
+        run_on(db_path, &["languages", "add", "to", "Tongan"]).unwrap();
+        run_on(db_path, &["languages", "add", "EN", "English"]).unwrap();
+        assert!(matches!(
+            run_on(db_path, &["languages", "add", "xx", "Unknown"]), Err(CliError::Usage(_))));
+        assert!(matches!(
+            run_on(db_path, &["languages", "add", "en", "Other"]), Err(CliError::Usage(_))));
+
+        let output = run_on(
+            db_path,
+            &["add", "--json", "--text", "to=kulī", "--text", "en=dog", "--category", "animals"],
+        ).unwrap();
+        assert_eq!(output, r#"{"id":1}"#);
+        // A card which cannot be saved does not leave its new category behind
+        assert!(run_on(db_path, &["add", "--text", "de=Hund", "--category", "birds"]).is_err());
+
+        let output = run_on(db_path, &["list", "--json"]).unwrap();
+        let expected = json!([{
+            "id": 1,
+            "category": "animals",
+            "translations": [
+                {"language": "to", "text": "kulī", "description": ""},
+                {"language": "en", "text": "dog", "description": ""},
+            ],
+        }]);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&output).unwrap(), expected);
+        let output = run_on(db_path, &["categories"]).unwrap();
+        assert!(!output.contains("birds"));
+        fs::remove_file(&path).unwrap();
+    }
+}
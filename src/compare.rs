@@ -0,0 +1,315 @@
+//! Compare a typed answer with the expected text of a translation
+//!
+//! # Example
+//!
+//! ```rust
+//! use compare::{Comparison, Strictness};
+//! let comparison = Comparison::new("fakaʻofoʻofa", "faka'ofo'ofa", &Strictness::new());
+//! assert!(comparison.is_correct());
+//! ```
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+use crate::models::review::Grade;
+
+
+/// Characters which are accepted in place of the fakauʻa (glottal stop) if apostrophes are lenient
+const APOSTROPHES: [char; 5] = ['\u{02BB}', '\'', '\u{2018}', '\u{2019}', '`'];
+
+
+/// How strictly a typed answer is compared with the expected text
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Strictness {
+    /// Compose characters first, so that e.g. `a` followed by a combining macron equals `ā`
+    pub normalize_unicode: bool,
+    /// Ignore differences between upper and lower case
+    pub ignore_case: bool,
+    /// Ignore diacritics, e.g. the macron of the Tongan toloi (`ā` equals `a`)
+    pub ignore_diacritics: bool,
+    /// Accept any apostrophe-like character for the fakauʻa (`ʻ` equals `'`)
+    pub lenient_apostrophes: bool,
+}
+
+
+/// Implement the Default trait for the strictness which only insists on the diacritics
+impl Default for Strictness {
+    fn default() -> Self {
+        Strictness {
+            normalize_unicode: true,
+            ignore_case: true,
+            ignore_diacritics: false,
+            lenient_apostrophes: true,
+        }
+    }
+}
+
+
+impl Strictness {
+    /// Create the default strictness which only insists on the diacritics
+    pub fn new() -> Strictness {
+        Strictness::default()
+    }
+
+    /// Get the key of a single character which is used for comparison
+    ///
+    /// # Arguments
+    ///
+    /// * `character` - The character to get the key for
+    fn key(&self, character: char) -> String {
+        if self.lenient_apostrophes && APOSTROPHES.contains(&character) {
+            return APOSTROPHES[0].to_string();
+        }
+        let mut key: String = if self.ignore_diacritics {
+            character.to_string().nfd().filter(|c| !is_combining_mark(*c)).collect()
+        } else {
+            character.to_string()
+        };
+        if self.ignore_case {
+            key = key.to_lowercase();
+        }
+        key
+    }
+
+    /// Split a text into units of displayed characters and their comparison keys
+    ///
+    /// Characters whose key is empty, e.g. combining diacritics which are ignored, are attached to
+    /// the preceding unit.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to split
+    fn units(&self, text: &str) -> Vec<(String, String)> {
+        let text: String = if self.normalize_unicode { text.nfc().collect() } else { text.to_string() };
+        let mut units: Vec<(String, String)> = Vec::new();
+        for character in text.trim().chars() {
+            let key = self.key(character);
+            match units.last_mut() {
+                Some((display, _)) if key.is_empty() => display.push(character),
+                _ => units.push((character.to_string(), key)),
+            }
+        }
+        units
+    }
+}
+
+
+/// The difference between a typed and an expected character
+#[derive(Clone, Debug, PartialEq)]
+pub enum Difference {
+    /// The typed character matches the expected one
+    Equal(String),
+    /// The expected character is missing in the typed answer
+    Missing(String),
+    /// The typed character is not expected
+    Extra(String),
+    /// The typed character (first) should be the expected one (second)
+    Wrong(String, String),
+}
+
+
+/// The result of comparing a typed answer with the expected text
+#[derive(Debug)]
+pub struct Comparison {
+    /// Character by character differences in order of the texts
+    pub differences: Vec<Difference>,
+    /// Number of characters in the expected text
+    pub expected_length: usize,
+}
+
+
+impl Comparison {
+    /// Compare a typed answer with the expected text, aligning them with minimal edits
+    ///
+    /// # Arguments
+    ///
+    /// * `typed` - The answer typed by the user
+    /// * `expected` - The expected answer
+    /// * `strictness` - How strictly to compare
+    pub fn new(typed: &str, expected: &str, strictness: &Strictness) -> Comparison {
+        let typed = strictness.units(typed);
+        let expected = strictness.units(expected);
+        // Edit distances between all prefixes of both texts
+        let mut distances = vec![vec![0; expected.len() + 1]; typed.len() + 1];
+        for (i, row) in distances.iter_mut().enumerate() {
+            row[0] = i;
+        }
+        for (j, distance) in distances[0].iter_mut().enumerate() {
+            *distance = j;
+        }
+        for i in 1..=typed.len() {
+            for j in 1..=expected.len() {
+                let cost = if typed[i - 1].1 == expected[j - 1].1 { 0 } else { 1 };
+                distances[i][j] = (distances[i - 1][j - 1] + cost)
+                    .min(distances[i - 1][j] + 1)
+                    .min(distances[i][j - 1] + 1);
+            }
+        }
+        let mut differences = Vec::new();
+        let (mut i, mut j) = (typed.len(), expected.len());
+        while i > 0 || j > 0 {
+            if i > 0 && j > 0 {
+                let cost = if typed[i - 1].1 == expected[j - 1].1 { 0 } else { 1 };
+                if distances[i][j] == distances[i - 1][j - 1] + cost {
+                    differences.push(if cost == 0 {
+                        Difference::Equal(typed[i - 1].0.clone())
+                    } else {
+                        Difference::Wrong(typed[i - 1].0.clone(), expected[j - 1].0.clone())
+                    });
+                    i -= 1;
+                    j -= 1;
+                    continue;
+                }
+            }
+            if i > 0 && distances[i][j] == distances[i - 1][j] + 1 {
+                differences.push(Difference::Extra(typed[i - 1].0.clone()));
+                i -= 1;
+            } else {
+                differences.push(Difference::Missing(expected[j - 1].0.clone()));
+                j -= 1;
+            }
+        }
+        differences.reverse();
+        Comparison { differences, expected_length: expected.len() }
+    }
+
+    /// Get the number of edits needed to turn the typed answer into the expected one
+    pub fn distance(&self) -> usize {
+        self.differences.iter()
+            .filter(|difference| !matches!(difference, Difference::Equal(_)))
+            .count()
+    }
+
+    /// Suggest a grade for the answer: good if correct, hard if almost correct, again otherwise
+    pub fn grade(&self) -> Grade {
+        let distance = self.distance();
+        if distance == 0 {
+            Grade::Good
+        } else if distance <= 1.max(self.expected_length / 5) {
+            Grade::Hard
+        } else {
+            Grade::Again
+        }
+    }
+
+    /// Check if the typed answer matches the expected one
+    pub fn is_correct(&self) -> bool {
+        self.distance() == 0
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get a strictness with all options set as given
+    ///
+    /// # Arguments
+    ///
+    /// * `normalize_unicode`, `ignore_case`, `ignore_diacritics`, `lenient_apostrophes` - The
+    ///   options of the strictness
+    fn strictness(
+        normalize_unicode: bool,
+        ignore_case: bool,
+        ignore_diacritics: bool,
+        lenient_apostrophes: bool,
+    ) -> Strictness {
+        Strictness { normalize_unicode, ignore_case, ignore_diacritics, lenient_apostrophes }
+    }
+
+    /// Compare a typed answer with the expected one and get the distance
+    fn distance(typed: &str, expected: &str, strictness: &Strictness) -> usize {
+        Comparison::new(typed, expected, strictness).distance()
+    }
+
+    #[test]
+    fn test_default_strictness() {
+        let strictness = Strictness::new();
+        assert!(Comparison::new("fakaʻofoʻofa", "faka'ofo'ofa", &strictness).is_correct());
+        assert!(Comparison::new("  Ako ", "ako", &strictness).is_correct());
+        assert!(!Comparison::new("kaka", "kākā", &strictness).is_correct());
+    }
+
+    #[test]
+    fn test_diacritics() {
+        let strict = strictness(true, false, false, false);
+        let lenient = strictness(true, false, true, false);
+        assert_eq!(distance("kaka", "kākā", &strict), 2);
+        assert_eq!(distance("kaka", "kākā", &lenient), 0);
+        assert_eq!(distance("kākā", "kaka", &lenient), 0);
+        // A decomposed macron equals the composed character only if normalised
+        assert_eq!(distance("ka\u{0304}", "kā", &strict), 0);
+        assert!(distance("ka\u{0304}", "kā", &strictness(false, false, false, false)) > 0);
+        // Ignored diacritics are attached to their character instead of standing alone
+        assert_eq!(distance("ka\u{0304}", "ka", &strictness(false, false, true, false)), 0);
+    }
+
+    #[test]
+    fn test_case() {
+        let case_sensitive = strictness(true, false, false, false);
+        let case_insensitive = strictness(true, true, false, false);
+        assert_eq!(distance("Ako", "ako", &case_sensitive), 1);
+        assert_eq!(distance("Ako", "ako", &case_insensitive), 0);
+        assert_eq!(distance("ĀKO", "āko", &case_insensitive), 0);
+        assert_eq!(distance("ĀKO", "ako", &strictness(true, true, true, false)), 0);
+        assert_eq!(distance("ĀKO", "ako", &case_insensitive), 1);
+    }
+
+    #[test]
+    fn test_apostrophes() {
+        let strict = strictness(true, false, false, false);
+        let lenient = strictness(true, false, false, true);
+        for apostrophe in APOSTROPHES.iter() {
+            let typed = format!("faka{}ofa", apostrophe);
+            assert_eq!(distance(&typed, "fakaʻofa", &lenient), 0);
+        }
+        assert_eq!(distance("faka'ofa", "fakaʻofa", &strict), 1);
+        assert_eq!(distance("fakaʻofa", "fakaʻofa", &strict), 0);
+    }
+
+    #[test]
+    fn test_empty_input() {
+        let strictness = Strictness::new();
+        let comparison = Comparison::new("", "ako", &strictness);
+        assert_eq!(comparison.differences, vec![
+            Difference::Missing("a".to_string()),
+            Difference::Missing("k".to_string()),
+            Difference::Missing("o".to_string()),
+        ]);
+        assert_eq!(comparison.grade(), Grade::Again);
+        let comparison = Comparison::new("   ", "", &strictness);
+        assert!(comparison.differences.is_empty());
+        assert!(comparison.is_correct());
+        let comparison = Comparison::new("a", "", &strictness);
+        assert_eq!(comparison.differences, vec![Difference::Extra("a".to_string())]);
+    }
+
+    #[test]
+    fn test_differences() {
+        let comparison = Comparison::new("aoo", "ako", &Strictness::new());
+        assert_eq!(comparison.differences, vec![
+            Difference::Equal("a".to_string()),
+            Difference::Wrong("o".to_string(), "k".to_string()),
+            Difference::Equal("o".to_string()),
+        ]);
+        assert_eq!(comparison.expected_length, 3);
+    }
+
+    #[test]
+    fn test_grade_thresholds() {
+        let strictness = Strictness::new();
+        // Up to one edit per five expected characters is hard, but at least one edit
+        let grade = |typed: &str, expected: &str| {
+            Comparison::new(typed, expected, &strictness).grade()
+        };
+        assert_eq!(grade("ako", "ako"), Grade::Good);
+        assert_eq!(grade("aka", "ako"), Grade::Hard);
+        assert_eq!(grade("aaa", "ako"), Grade::Again);
+        assert_eq!(grade("abcdefghij", "abcdefghij"), Grade::Good);
+        assert_eq!(grade("abcdefghiX", "abcdefghij"), Grade::Hard);
+        assert_eq!(grade("abcdefghXX", "abcdefghij"), Grade::Hard);
+        assert_eq!(grade("abcdefgXXX", "abcdefghij"), Grade::Again);
+        assert_eq!(grade("abcdefghi", "abcdefghij"), Grade::Hard);
+    }
+}
@@ -3,9 +3,9 @@
 //! # Example
 //!
 //! ```rust
-//! use database::{get_connection, initialise};
-//! let conn = get_connection()?;
-//! initialise(conn);
+//! use database::{get_path, open};
+//! // Creates and seeds a missing database, migrates an existing one to the latest schema
+//! let conn = open(&get_path(None))?;
 //! ```
 
 use std::env;
@@ -13,24 +13,28 @@ use std::error::Error;
 use std::fmt;
 use std::path;
 
-use sqlite;
-
 use crate::DEFAULT_DB_PATH;
 
 
 /// An custom error which can occur during access to the database
 #[derive(Debug)]
 pub enum DatabaseError {
-    /// A file could not be found, the filename should be in the string
-    FileNotFound(String),
     /// Item has not been found
     NotFound,
     /// An error within SQLite occurred, the error message should be in the string
     SQLiteError(String),
+    /// The value returned by the database is not valid, the value should be in the string
+    ValueInvalid(String),
+    /// The value returned by the database is not the expected float
+    ValueNotFloat,
     /// The value returned by the database is not the expected integer
     ValueNotInteger,
     /// The value returned by the database is not the expected string
     ValueNotString,
+    /// Item cannot be deleted, because other items refer to it; the reason should be in the string
+    StillReferenced(String),
+    /// The schema version of the database is newer than this application knows about
+    VersionUnsupported(i64),
 
 }
 
@@ -51,25 +55,72 @@ impl fmt::Display for DatabaseError {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
       let prefix = "DatabaseError";
       match self {
-          DatabaseError::FileNotFound(db_path) =>
-              write!(f, "{}: File not found: {}!", prefix, db_path),
           DatabaseError::NotFound => write!(f, "{}: Item has not been found!", prefix),
           DatabaseError::SQLiteError(msg) =>
               write!(f, "{}: SQLite error: {}!", prefix, msg),
+          DatabaseError::ValueInvalid(value) =>
+              write!(f, "{}: Value invalid: {}!", prefix, value),
+          DatabaseError::ValueNotFloat => write!(f, "{}: Value not a float!", prefix),
           DatabaseError::ValueNotInteger => write!(f, "{}: Value not an integer!", prefix),
           DatabaseError::ValueNotString => write!(f, "{}: Value not a string!", prefix),
+          DatabaseError::StillReferenced(reason) =>
+              write!(f, "{}: Item is still referenced: {}!", prefix, reason),
+          DatabaseError::VersionUnsupported(version) =>
+              write!(f, "{}: Schema version {} is newer than supported!", prefix, version),
       }
   }
 }
 
 
-/// Get a database connection using a DB path from the environment
-pub fn get_connection() -> Result<sqlite::Connection, DatabaseError> {
-    let db_path = env::var("DB_PATH").unwrap_or(DEFAULT_DB_PATH.to_string());
-    if !path::Path::new(&db_path).exists() {
-        return Err(DatabaseError::FileNotFound(db_path));
+/// Get the path to the database file from the environment, the settings or the default path
+///
+/// # Arguments
+///
+/// * `configured` - Path to the database file from the settings, if any
+pub fn get_path(configured: Option<&str>) -> String {
+    match env::var("DB_PATH") {
+        Ok(path) => path,
+        Err(_) => configured.unwrap_or(DEFAULT_DB_PATH).to_string(),
+    }
+}
+
+
+/// Open a database connection
+///
+/// A missing database is created and seeded with fixtures, an existing one is migrated to the
+/// latest schema version. Foreign keys are enforced on the returned connection.
+///
+/// # Arguments
+///
+/// * `db_path` - Path to the database file
+pub fn open(db_path: &str) -> Result<sqlite::Connection, DatabaseError> {
+    let is_new = !path::Path::new(db_path).exists();
+    let conn = sqlite::open(db_path)?;
+    if is_new {
+        initialise(&conn)?;
+    } else {
+        migrate(&conn)?;
     }
+    // Only enabled after migrating, because migrations may need to rebuild referenced tables
+    conn.execute("PRAGMA foreign_keys = ON;")?;
+    Ok(conn)
+}
+
+
+/// Create an empty database with the latest schema, but without fixture data, e.g. for a new deck
+///
+/// Only the `default` category is added, which new cards are put in. An existing database is
+/// migrated instead. Foreign keys are enforced on the returned connection.
+///
+/// # Arguments
+///
+/// * `db_path` - Path to the database file
+pub fn create(db_path: &str) -> Result<sqlite::Connection, DatabaseError> {
     let conn = sqlite::open(db_path)?;
+    migrate(&conn)?;
+    conn.execute(
+        "INSERT INTO category (name) SELECT 'default' WHERE NOT EXISTS (SELECT id FROM category);")?;
+    conn.execute("PRAGMA foreign_keys = ON;")?;
     Ok(conn)
 }
 
@@ -79,9 +130,8 @@ pub fn get_connection() -> Result<sqlite::Connection, DatabaseError> {
 /// # Arguments
 ///
 /// * `conn` - Connection to the database
-#[allow(dead_code)]
 pub fn initialise(conn: &sqlite::Connection) -> Result<(), DatabaseError> {
-    init_schema(conn)?;
+    migrate(conn)?;
     init_fixtures(conn)?;
     Ok(())
 }
@@ -93,10 +143,10 @@ pub fn initialise(conn: &sqlite::Connection) -> Result<(), DatabaseError> {
 ///
 /// * `conn` - Connection to the database
 fn init_fixtures(conn: &sqlite::Connection) -> Result<(), DatabaseError> {
-    let result = conn.execute("
-        INSERT INTO language (code, name) VALUES ('to', 'Tongan');
-        INSERT INTO language (code, name) VALUES ('en', 'English');
-        INSERT INTO language (code, name) VALUES ('de', 'German');
+    conn.execute("
+        INSERT INTO language (code, name, position) VALUES ('to', 'Tongan', 1);
+        INSERT INTO language (code, name, position) VALUES ('en', 'English', 2);
+        INSERT INTO language (code, name, position) VALUES ('de', 'German', 3);
         INSERT INTO category (name) VALUES ('default');
         INSERT INTO card (category_id) VALUES (1);
         INSERT INTO translation (card_id, language_id, text, description) VALUES (1, 1, 'kaati', '');
@@ -111,36 +161,35 @@ fn init_fixtures(conn: &sqlite::Connection) -> Result<(), DatabaseError> {
         INSERT INTO translation (card_id, language_id, text, description) VALUES (3, 2, 'language', 'Learn a language');
         INSERT INTO translation (card_id, language_id, text, description) VALUES (3, 3, 'Sprache', 'Eine Sprache lernen');
         ")?;
-    Ok(result)
+    Ok(())
 }
 
 
-/// Initialise the database schema
+/// Ordered steps to migrate the database schema, the schema version is the number of applied steps
 ///
-/// # Arguments
+/// # Notes
 ///
-/// * `conn` - Connection to the database
-fn init_schema(conn: &sqlite::Connection) -> Result<(), DatabaseError> {
-    let result = conn.execute("
-        DROP TABLE IF EXISTS category;
-        CREATE TABLE category (
+/// * Never change a released step, append a new one instead.
+/// * Tables are created with `IF NOT EXISTS`, because databases created before the schema was
+///   versioned have version 0 but may contain any of the tables already.
+const MIGRATIONS: [&str; 7] = [
+    // 1: Cards with their translations
+    "
+        CREATE TABLE IF NOT EXISTS category (
             id INTEGER NOT NULL PRIMARY KEY,
             name TEXT
         );
-        DROP TABLE IF EXISTS card;
-        CREATE TABLE card (
+        CREATE TABLE IF NOT EXISTS card (
             id INTEGER NOT NULL PRIMARY KEY,
             category_id INTEGER,
             FOREIGN KEY (category_id) REFERENCES category (id)
         );
-        DROP TABLE IF EXISTS language;
-        CREATE TABLE language (
+        CREATE TABLE IF NOT EXISTS language (
             id INTEGER NOT NULL PRIMARY KEY,
             code TEXT,
             name TEXT
         );
-        DROP TABLE IF EXISTS translation;
-        CREATE TABLE translation (
+        CREATE TABLE IF NOT EXISTS translation (
             id INTEGER NOT NULL PRIMARY KEY,
             card_id INTEGER,
             language_id INTEGER,
@@ -149,8 +198,250 @@ fn init_schema(conn: &sqlite::Connection) -> Result<(), DatabaseError> {
             FOREIGN KEY (card_id) REFERENCES card (id),
             FOREIGN KEY (language_id) REFERENCES language (id)
         );
-    ")?;
-    Ok(result)
+    ",
+    // 2: Reviews scheduled with SM-2 and the log of all answers
+    "
+        CREATE TABLE IF NOT EXISTS review (
+            id INTEGER NOT NULL PRIMARY KEY,
+            card_id INTEGER UNIQUE,
+            ease_factor REAL,
+            interval INTEGER,
+            repetitions INTEGER,
+            due INTEGER,
+            FOREIGN KEY (card_id) REFERENCES card (id)
+        );
+        CREATE TABLE IF NOT EXISTS review_log (
+            id INTEGER NOT NULL PRIMARY KEY,
+            card_id INTEGER,
+            prompt_language_id INTEGER,
+            answer_language_id INTEGER,
+            grade INTEGER,
+            timestamp INTEGER,
+            response_time INTEGER,
+            FOREIGN KEY (card_id) REFERENCES card (id),
+            FOREIGN KEY (prompt_language_id) REFERENCES language (id),
+            FOREIGN KEY (answer_language_id) REFERENCES language (id)
+        );
+    ",
+    // 3: Settings stored with the database
+    "
+        CREATE TABLE IF NOT EXISTS setting (
+            id INTEGER NOT NULL PRIMARY KEY,
+            key TEXT UNIQUE,
+            value TEXT
+        );
+    ",
+    // 4: Cards scheduled with Leitner boxes
+    "
+        CREATE TABLE IF NOT EXISTS leitner_card (
+            id INTEGER NOT NULL PRIMARY KEY,
+            card_id INTEGER UNIQUE,
+            box INTEGER,
+            due INTEGER,
+            FOREIGN KEY (card_id) REFERENCES card (id)
+        );
+    ",
+    // 5: Delete translations and the review history together with their card
+    "
+        CREATE TABLE translation_new (
+            id INTEGER NOT NULL PRIMARY KEY,
+            card_id INTEGER,
+            language_id INTEGER,
+            text TEXT,
+            description TEXT,
+            FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE,
+            FOREIGN KEY (language_id) REFERENCES language (id)
+        );
+        INSERT INTO translation_new SELECT id, card_id, language_id, text, description
+            FROM translation;
+        DROP TABLE translation;
+        ALTER TABLE translation_new RENAME TO translation;
+        CREATE TABLE review_new (
+            id INTEGER NOT NULL PRIMARY KEY,
+            card_id INTEGER UNIQUE,
+            ease_factor REAL,
+            interval INTEGER,
+            repetitions INTEGER,
+            due INTEGER,
+            FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE
+        );
+        INSERT INTO review_new SELECT id, card_id, ease_factor, interval, repetitions, due
+            FROM review;
+        DROP TABLE review;
+        ALTER TABLE review_new RENAME TO review;
+        CREATE TABLE review_log_new (
+            id INTEGER NOT NULL PRIMARY KEY,
+            card_id INTEGER,
+            prompt_language_id INTEGER,
+            answer_language_id INTEGER,
+            grade INTEGER,
+            timestamp INTEGER,
+            response_time INTEGER,
+            FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE,
+            FOREIGN KEY (prompt_language_id) REFERENCES language (id) ON DELETE CASCADE,
+            FOREIGN KEY (answer_language_id) REFERENCES language (id) ON DELETE CASCADE
+        );
+        INSERT INTO review_log_new SELECT id, card_id, prompt_language_id, answer_language_id,
+            grade, timestamp, response_time FROM review_log;
+        DROP TABLE review_log;
+        ALTER TABLE review_log_new RENAME TO review_log;
+        CREATE TABLE leitner_card_new (
+            id INTEGER NOT NULL PRIMARY KEY,
+            card_id INTEGER UNIQUE,
+            box INTEGER,
+            due INTEGER,
+            FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE
+        );
+        INSERT INTO leitner_card_new SELECT id, card_id, box, due FROM leitner_card;
+        DROP TABLE leitner_card;
+        ALTER TABLE leitner_card_new RENAME TO leitner_card;
+    ",
+    // 6: Full-text search over translations, kept in sync by triggers
+    "
+        CREATE VIRTUAL TABLE translation_search USING fts5(
+            text,
+            description,
+            content='translation',
+            content_rowid='id'
+        );
+        CREATE TRIGGER translation_search_insert AFTER INSERT ON translation BEGIN
+            INSERT INTO translation_search (rowid, text, description)
+                VALUES (new.id, new.text, new.description);
+        END;
+        CREATE TRIGGER translation_search_delete AFTER DELETE ON translation BEGIN
+            INSERT INTO translation_search (translation_search, rowid, text, description)
+                VALUES ('delete', old.id, old.text, old.description);
+        END;
+        CREATE TRIGGER translation_search_update AFTER UPDATE ON translation BEGIN
+            INSERT INTO translation_search (translation_search, rowid, text, description)
+                VALUES ('delete', old.id, old.text, old.description);
+            INSERT INTO translation_search (rowid, text, description)
+                VALUES (new.id, new.text, new.description);
+        END;
+        INSERT INTO translation_search (translation_search) VALUES ('rebuild');
+    ",
+    // 7: User-defined display order of languages, initially in the order they were added
+    "
+        ALTER TABLE language ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+        UPDATE language SET position = id;
+    ",
+];
+
+
+/// Get the schema version of the database
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+pub fn schema_version(conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+    let mut cursor = conn.prepare("PRAGMA user_version")?.cursor();
+    match cursor.next()? {
+        Some(row) => row[0].as_integer().ok_or(DatabaseError::ValueNotInteger),
+        None => Err(DatabaseError::NotFound),
+    }
+}
+
+
+/// Migrate the database schema to the latest version
+///
+/// Each pending step is applied in its own transaction together with the new version, so that a
+/// failing step leaves the database at the previous version.
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+pub fn migrate(conn: &sqlite::Connection) -> Result<(), DatabaseError> {
+    let version = schema_version(conn)?;
+    if version > MIGRATIONS.len() as i64 {
+        return Err(DatabaseError::VersionUnsupported(version));
+    }
+    for (idx, step) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        with_transaction(conn, |tx| -> Result<(), DatabaseError> {
+            tx.execute(format!("{} PRAGMA user_version = {};", step, idx + 1))?;
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+
+/// Clean up items which refer to items which do not exist (anymore)
+///
+/// Translations, reviews and review logs of missing cards or languages are deleted. Cards of
+/// missing categories are moved to the first category. Returns the number of repaired items.
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+///
+/// # Notes
+///
+/// * Orphans can only exist in databases which were modified without enforced foreign keys.
+pub fn repair(conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+    let before = total_changes(conn)?;
+    let statement = "
+        DELETE FROM translation WHERE card_id NOT IN (SELECT id FROM card)
+            OR language_id NOT IN (SELECT id FROM language);
+        DELETE FROM review WHERE card_id NOT IN (SELECT id FROM card);
+        DELETE FROM review_log WHERE card_id NOT IN (SELECT id FROM card)
+            OR prompt_language_id NOT IN (SELECT id FROM language)
+            OR answer_language_id NOT IN (SELECT id FROM language);
+        DELETE FROM leitner_card WHERE card_id NOT IN (SELECT id FROM card);
+        UPDATE card SET category_id = (SELECT MIN(id) FROM category)
+            WHERE category_id NOT IN (SELECT id FROM category)
+            AND EXISTS (SELECT id FROM category);
+    ";
+    with_transaction(conn, |tx| -> Result<(), DatabaseError> {
+        tx.execute(statement)?;
+        Ok(())
+    })?;
+    Ok(total_changes(conn)? - before)
+}
+
+
+/// Run the given function in a transaction, which is committed if it succeeds and rolled back
+/// otherwise
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `f` - Function which is given the connection to run its statements on
+///
+/// # Notes
+///
+/// * Transactions can be nested, e.g. a model's save within a whole dialog's changes. They are
+///   savepoints, so an inner transaction is only committed together with the outermost one.
+pub fn with_transaction<T, E, F>(conn: &sqlite::Connection, f: F) -> Result<T, E>
+where
+    E: From<DatabaseError>,
+    F: FnOnce(&sqlite::Connection) -> Result<T, E>,
+{
+    conn.execute("SAVEPOINT kaati_ako;").map_err(DatabaseError::from)?;
+    match f(conn) {
+        Ok(value) => {
+            conn.execute("RELEASE kaati_ako;").map_err(DatabaseError::from)?;
+            Ok(value)
+        },
+        Err(err) => {
+            // The error of the function is more relevant than a failing rollback
+            conn.execute("ROLLBACK TO kaati_ako; RELEASE kaati_ako;").ok();
+            Err(err)
+        },
+    }
+}
+
+
+/// Get the number of rows changed since the connection was opened
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+fn total_changes(conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+    let mut cursor = conn.prepare("SELECT total_changes()")?.cursor();
+    match cursor.next()? {
+        Some(row) => row[0].as_integer().ok_or(DatabaseError::ValueNotInteger),
+        None => Err(DatabaseError::NotFound),
+    }
 }
 
 
@@ -164,11 +455,8 @@ pub fn last_insert_id(conn: &sqlite::Connection, table_name: &str) -> Result<i64
     // Cannot prepare `SELECT last_insert_rowid() FROM ?` ... Bug?
     let statement = format!("SELECT last_insert_rowid() FROM {}", table_name);
     let mut cursor = conn.prepare(&statement)?.cursor();
-    for row in cursor.next()? {
-        match row[0].as_integer() {
-            Some(id) => { return Ok(id); },
-            None => { return Err(DatabaseError::ValueNotInteger); },
-        }
+    if let Some(row) = cursor.next()? {
+        return row[0].as_integer().ok_or(DatabaseError::ValueNotInteger);
     }
     Ok(0)
 }
\ No newline at end of file
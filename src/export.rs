@@ -0,0 +1,252 @@
+//! Export flash cards with their category and translations to CSV, TSV or JSON
+//!
+//! CSV and TSV files have a header with the category and the name of each language, followed by
+//! its description, e.g. `Category,Tongan,Tongan description,English,English description`. So they
+//! can be imported again, see `crate::import`. So can JSON, whose translations refer to the
+//! languages by code.
+//!
+//! # Example
+//!
+//! ```rust
+//! use export::{Format, export_cards};
+//! use models::filter::Filter;
+//! let csv = export_cards(&conn, Format::Csv, &Filter::default())?;
+//! ```
+
+use std::collections::HashMap;
+
+use serde_json::json;
+
+use crate::database::DatabaseError;
+use crate::models::Model;
+use crate::models::filter::Filter;
+use crate::models::language::Language;
+use crate::models::translation::Translation;
+
+
+/// The format of exported cards
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// Comma separated values
+    Csv,
+    /// Tab separated values
+    Tsv,
+    /// JSON with the languages and the cards with their translations
+    Json,
+}
+
+
+impl Format {
+    /// All formats in the order they are offered
+    pub const ALL: [Format; 3] = [Format::Csv, Format::Tsv, Format::Json];
+
+    /// Get a format by its name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the format as given by `Format::name`
+    pub fn from_name(name: &str) -> Option<Format> {
+        Format::ALL.iter().copied().find(|format| format.name() == name)
+    }
+
+    /// Get the name of the format, which is also the extension of its files
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+            Format::Json => "json",
+        }
+    }
+
+    /// Get the label of the format to show to the user
+    pub fn label(&self) -> &'static str {
+        match self {
+            Format::Csv => "CSV (comma separated)",
+            Format::Tsv => "TSV (tab separated)",
+            Format::Json => "JSON",
+        }
+    }
+}
+
+
+/// A card to export with the name of its category and its translations in display order
+pub struct ExportCard {
+    /// Identifier of the card
+    pub id: i64,
+    /// Name of the card's category
+    pub category: String,
+    /// Translations of the card in the exported languages
+    pub translations: Vec<Translation>,
+}
+
+
+/// Load the cards which pass the filter with their translations in the given languages
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `filter` - Restricts the categories to export
+/// * `languages` - The languages to export, in display order
+pub fn load_cards(
+    conn: &sqlite::Connection,
+    filter: &Filter,
+    languages: &[Language],
+) -> Result<Vec<ExportCard>, DatabaseError> {
+    let positions: HashMap<i64, usize> = languages.iter().enumerate()
+        .map(|(idx, language)| (language.id, idx))
+        .collect();
+    let mut translations: HashMap<i64, Vec<Translation>> = HashMap::new();
+    for translation in Translation::load_all(conn)? {
+        if positions.contains_key(&translation.language_id) {
+            translations.entry(translation.card_id).or_default().push(translation);
+        }
+    }
+    for card_translations in translations.values_mut() {
+        card_translations.sort_by_key(|translation| positions[&translation.language_id]);
+    }
+    let statement = format!("
+        SELECT card.id, COALESCE(category.name, '') FROM card
+        LEFT JOIN category ON category.id = card.category_id
+        WHERE {} ORDER BY card.id",
+        filter.export_sql_condition(),
+    );
+    let mut cursor = conn.prepare(statement)?.cursor();
+    let mut cards = Vec::new();
+    while let Some(row) = cursor.next()? {
+        let id = row[0].as_integer().ok_or(DatabaseError::ValueNotInteger)?;
+        let category = row[1].as_string().ok_or(DatabaseError::ValueNotString)?.to_string();
+        cards.push(ExportCard {
+            id,
+            category,
+            translations: translations.remove(&id).unwrap_or_default(),
+        });
+    }
+    Ok(cards)
+}
+
+
+/// Quote a field of delimited text if necessary, see `crate::import::parse`
+///
+/// # Arguments
+///
+/// * `field` - The field to quote
+/// * `delimiter` - The character which separates the fields
+fn quote_field(field: &str, delimiter: char) -> String {
+    if field.contains(&[delimiter, '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+
+/// Export cards as delimited text with a header row
+///
+/// # Arguments
+///
+/// * `cards` - The cards to export
+/// * `languages` - The languages to export, in display order
+/// * `delimiter` - The character which separates the fields
+fn to_delimited(cards: &[ExportCard], languages: &[Language], delimiter: char) -> String {
+    let mut header = vec!["Category".to_string()];
+    for language in languages {
+        header.push(language.name.clone());
+        header.push(format!("{} description", language.name));
+    }
+    let mut rows = vec![header];
+    for card in cards {
+        let mut row = vec![card.category.clone()];
+        for language in languages {
+            match card.translations.iter().find(
+                |translation| translation.language_id == language.id) {
+                Some(translation) => {
+                    row.push(translation.text.clone());
+                    row.push(translation.description.clone());
+                },
+                None => {
+                    row.push(String::new());
+                    row.push(String::new());
+                },
+            }
+        }
+        rows.push(row);
+    }
+    let separator = delimiter.to_string();
+    rows.iter()
+        .map(|row| {
+            let fields: Vec<String> = row.iter()
+                .map(|field| quote_field(field, delimiter))
+                .collect();
+            fields.join(&separator) + "\n"
+        })
+        .collect()
+}
+
+
+/// Export cards as JSON
+///
+/// The languages are listed with their code and name, the translations of the cards refer to
+/// them by code, see `crate::import::parse_json`:
+///
+/// ```json
+/// {
+///   "languages": [{"code": "to", "name": "Tongan"}],
+///   "cards": [
+///     {"category": "default", "translations": [
+///       {"language": "to", "text": "kaati", "description": ""}
+///     ]}
+///   ]
+/// }
+/// ```
+///
+/// # Arguments
+///
+/// * `cards` - The cards to export
+/// * `languages` - The languages to export, in display order
+fn to_json(cards: &[ExportCard], languages: &[Language]) -> String {
+    let codes: HashMap<i64, &str> = languages.iter()
+        .map(|language| (language.id, language.code.as_str()))
+        .collect();
+    let languages: Vec<serde_json::Value> = languages.iter()
+        .map(|language| json!({"code": language.code, "name": language.name}))
+        .collect();
+    let cards: Vec<serde_json::Value> = cards.iter()
+        .map(|card| {
+            let translations: Vec<serde_json::Value> = card.translations.iter()
+                .map(|translation| json!({
+                    "language": codes.get(&translation.language_id).copied().unwrap_or(""),
+                    "text": translation.text,
+                    "description": translation.description,
+                }))
+                .collect();
+            json!({"category": card.category, "translations": translations})
+        })
+        .collect();
+    let json = json!({"languages": languages, "cards": cards});
+    serde_json::to_string_pretty(&json).unwrap_or_default() + "\n"
+}
+
+
+/// Export the cards which pass the filter with their category and their translations in the
+/// filter's languages
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `format` - The format to export to
+/// * `filter` - Restricts the categories and languages to export; an empty filter exports all
+pub fn export_cards(
+    conn: &sqlite::Connection,
+    format: Format,
+    filter: &Filter,
+) -> Result<String, DatabaseError> {
+    let languages: Vec<Language> = Language::load_all(conn)?.into_iter()
+        .filter(|language| filter.is_language_active(language.id))
+        .collect();
+    let cards = load_cards(conn, filter, &languages)?;
+    Ok(match format {
+        Format::Csv => to_delimited(&cards, &languages, ','),
+        Format::Tsv => to_delimited(&cards, &languages, '\t'),
+        Format::Json => to_json(&cards, &languages),
+    })
+}
@@ -0,0 +1,449 @@
+//! Import flash cards from delimited text files, i.e. CSV or TSV, or from JSON
+//!
+//! Each row of the file becomes a card. The columns are mapped to the texts and descriptions of
+//! languages and to the category of the card. JSON as exported by `crate::export` is converted to
+//! such rows, each card becomes a row.
+//!
+//! # Example
+//!
+//! ```rust
+//! use import::{Column, import_rows, parse, parse_json};
+//! let rows = parse("english,tongan\nhello,mālō e lelei\n", ',')?;
+//! let columns = [Column::Text(1), Column::Text(2)];
+//! let summary = import_rows(&conn, &rows[1..], &columns, 1)?;
+//! let rows = parse_json(r#"{"cards": [{"category": "default", "translations": []}]}"#)?;
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::path::Path;
+
+use crate::database::{DatabaseError, with_transaction};
+use crate::models::Model;
+use crate::models::card::Card;
+use crate::models::category::Category;
+use crate::models::language::Language;
+use crate::models::translation::Translation;
+
+
+/// What a column of the imported file holds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+    /// The column is not imported
+    Ignore,
+    /// The text of the translation in the language with the given id
+    Text(i64),
+    /// The description of the translation in the language with the given id
+    Description(i64),
+    /// The name of the card's category; a missing category is created
+    Category,
+}
+
+
+impl Column {
+    /// Guess what a column holds from its header, e.g. `English`, `en description` or `Category`
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - The header of the column
+    /// * `languages` - All languages of the database
+    pub fn guess(header: &str, languages: &[Language]) -> Column {
+        let header = header.trim().to_lowercase();
+        if header == "category" {
+            return Column::Category;
+        }
+        let (name, is_description) = match header.strip_suffix("description") {
+            Some(name) => (name.trim(), true),
+            None => (header.as_str(), false),
+        };
+        let language = languages.iter().find(
+            |language| language.name.to_lowercase() == name || language.code == name);
+        match language {
+            Some(language) if is_description => Column::Description(language.id),
+            Some(language) => Column::Text(language.id),
+            None => Column::Ignore,
+        }
+    }
+}
+
+
+/// How many rows of a file have been imported, skipped and failed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Summary {
+    /// Number of rows imported as new cards
+    pub imported: usize,
+    /// Number of rows skipped because a text is already in the database
+    pub skipped: usize,
+    /// Number of rows which could not be imported, e.g. because they have no text
+    pub failed: usize,
+}
+
+
+/// Error when a file cannot be parsed
+#[derive(Debug)]
+pub enum ParseError {
+    /// A quoted field starts in the given line of the file, but is never closed
+    UnclosedQuote(usize),
+    /// The JSON is not valid or not shaped like an export, the reason should be in the string
+    Json(String),
+}
+
+/// The implementation of the Error trait is empty
+impl Error for ParseError {}
+
+/// Implement the Display trait to describe a ParseError
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnclosedQuote(line) => write!(
+                f, "Import error: Quoted field starting in line {} is not closed!", line),
+            ParseError::Json(reason) => write!(f, "Import error: Invalid JSON, {}!", reason),
+        }
+    }
+}
+
+/// Implement the From trait to convert a serde_json::Error to a ParseError
+impl From<serde_json::Error> for ParseError {
+    fn from(err: serde_json::Error) -> Self {
+        ParseError::Json(err.to_string())
+    }
+}
+
+
+/// Guess the delimiter of a file: tabs for `.tsv` files or if the first line has a tab, otherwise
+/// commas
+///
+/// # Arguments
+///
+/// * `path` - Path to the file
+/// * `text` - Content of the file
+pub fn guess_delimiter(path: &Path, text: &str) -> char {
+    let is_tsv = path.extension().and_then(|extension| extension.to_str()) == Some("tsv");
+    if is_tsv || text.lines().next().unwrap_or("").contains('\t') {
+        '\t'
+    } else {
+        ','
+    }
+}
+
+
+/// Parse delimited text into rows of fields
+///
+/// Fields may be quoted by `"` to contain the delimiter or line breaks; a `"` within a quoted
+/// field is doubled. Blank lines and a leading byte order mark are ignored.
+///
+/// # Arguments
+///
+/// * `text` - The text to parse
+/// * `delimiter` - The character which separates the fields, e.g. `,` or `\t`
+pub fn parse(text: &str, delimiter: char) -> Result<Vec<Vec<String>>, ParseError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut is_quoted = false;
+    let mut line = 1;
+    let mut quote_line = 0;
+    let mut push_row = |row: Vec<String>| {
+        if row.iter().any(|field| !field.trim().is_empty()) {
+            rows.push(row);
+        }
+    };
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(character) = chars.next() {
+        if character == '\n' {
+            line += 1;
+        }
+        if is_quoted {
+            if character != '"' {
+                field.push(character);
+            } else if chars.peek() == Some(&'"') {
+                field.push(character);
+                chars.next();
+            } else {
+                is_quoted = false;
+            }
+        } else if character == '"' && field.is_empty() {
+            is_quoted = true;
+            quote_line = line;
+        } else if character == delimiter {
+            row.push(mem::take(&mut field));
+        } else if character == '\n' {
+            row.push(mem::take(&mut field));
+            push_row(mem::take(&mut row));
+        } else if character != '\r' {
+            field.push(character);
+        }
+    }
+    if is_quoted {
+        return Err(ParseError::UnclosedQuote(quote_line));
+    }
+    if !row.is_empty() || !field.is_empty() {
+        row.push(field);
+        push_row(row);
+    }
+    Ok(rows)
+}
+
+
+/// Parse JSON as written by `crate::export` into rows of fields with a header
+///
+/// The header names the category and the code of each language, followed by its description,
+/// e.g. `Category,to,to description`, so the columns can be guessed as for delimited text. The
+/// languages are taken in the order they are listed, then in the order the translations use them.
+///
+/// # Arguments
+///
+/// * `text` - The text to parse, e.g. `{"languages": [...], "cards": [...]}`
+pub fn parse_json(text: &str) -> Result<Vec<Vec<String>>, ParseError> {
+    let json: serde_json::Value = serde_json::from_str(text.trim_start_matches('\u{feff}'))?;
+    let cards = json["cards"].as_array()
+        .ok_or_else(|| ParseError::Json("no list of cards".to_string()))?;
+    let string = |value: &serde_json::Value| value.as_str().unwrap_or("").trim().to_string();
+    let mut codes: Vec<String> = json["languages"].as_array()
+        .map_or(Vec::new(), |languages| languages.iter()
+            .map(|language| string(&language["code"]))
+            .collect());
+    let mut translations = Vec::with_capacity(cards.len());
+    for card in cards {
+        let mut card_translations = HashMap::new();
+        for translation in card["translations"].as_array().map_or(&[][..], |list| list) {
+            let code = string(&translation["language"]);
+            if !codes.contains(&code) {
+                codes.push(code.clone());
+            }
+            card_translations.insert(
+                code, (string(&translation["text"]), string(&translation["description"])));
+        }
+        translations.push((string(&card["category"]), card_translations));
+    }
+    codes.retain(|code| !code.is_empty());
+
+    let mut header = vec!["Category".to_string()];
+    for code in codes.iter() {
+        header.push(code.clone());
+        header.push(format!("{} description", code));
+    }
+    let mut rows = vec![header];
+    for (category, mut card_translations) in translations {
+        let mut row = vec![category];
+        for code in codes.iter() {
+            let (text, description) = card_translations.remove(code).unwrap_or_default();
+            row.push(text);
+            row.push(description);
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+
+/// Parse a file to import into rows of fields, JSON for `.json` files, otherwise delimited text
+///
+/// # Arguments
+///
+/// * `path` - Path to the file
+/// * `text` - Content of the file
+pub fn parse_file(path: &Path, text: &str) -> Result<Vec<Vec<String>>, ParseError> {
+    if path.extension().and_then(|extension| extension.to_str()) == Some("json") {
+        parse_json(text)
+    } else {
+        parse(text, guess_delimiter(path, text))
+    }
+}
+
+
+/// Get the key by which texts are compared to detect duplicates
+///
+/// # Arguments
+///
+/// * `language_id` - Identifier of the text's language
+/// * `text` - The text
+fn duplicate_key(language_id: i64, text: &str) -> (i64, String) {
+    (language_id, text.trim().to_lowercase())
+}
+
+
+/// Import rows as new cards, all in one transaction
+///
+/// A row is skipped if one of its texts is already in the database or in a previous row. A row
+/// without any text fails, as does a row which cannot be saved.
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `rows` - The rows to import, without a header
+/// * `columns` - What each column holds; missing columns are ignored
+/// * `category_id` - Identifier of the category for rows without one
+pub fn import_rows(
+    conn: &sqlite::Connection,
+    rows: &[Vec<String>],
+    columns: &[Column],
+    category_id: i64,
+) -> Result<Summary, DatabaseError> {
+    let mut existing: HashSet<(i64, String)> = Translation::load_all(conn)?.iter()
+        .map(|translation| duplicate_key(translation.language_id, &translation.text))
+        .collect();
+    let mut category_ids: HashMap<String, i64> = Category::load_all(conn)?.into_iter()
+        .map(|category| (category.name, category.id))
+        .collect();
+    let mut summary = Summary::default();
+    with_transaction(conn, |tx| {
+        for row in rows {
+            let mut category_name = "";
+            let mut translations: Vec<Translation> = Vec::new();
+            for (column, field) in columns.iter().zip(row.iter()) {
+                let field = field.trim();
+                let language_id = match column {
+                    Column::Text(language_id) | Column::Description(language_id) => *language_id,
+                    Column::Category => {
+                        category_name = field;
+                        continue;
+                    },
+                    Column::Ignore => { continue; },
+                };
+                let idx = match translations.iter().position(
+                    |translation| translation.language_id == language_id) {
+                    Some(idx) => idx,
+                    None => {
+                        let mut translation = Translation::from_empty();
+                        translation.language_id = language_id;
+                        translations.push(translation);
+                        translations.len() - 1
+                    },
+                };
+                match column {
+                    Column::Text(_) => translations[idx].text = field.to_string(),
+                    _ => translations[idx].description = field.to_string(),
+                }
+            }
+            translations.retain(|translation| !translation.text.is_empty());
+            if translations.is_empty() {
+                summary.failed += 1;
+                continue;
+            }
+            let keys: Vec<(i64, String)> = translations.iter()
+                .map(|translation| duplicate_key(translation.language_id, &translation.text))
+                .collect();
+            if keys.iter().any(|key| existing.contains(key)) {
+                summary.skipped += 1;
+                continue;
+            }
+            // Each row is a nested transaction, so a failing row leaves the others intact. A
+            // category created by the row is returned, as it only exists if the row succeeds.
+            let result = with_transaction(tx, |tx| -> Result<Option<Category>, DatabaseError> {
+                let mut card = Card::from_empty();
+                let mut new_category = None;
+                card.category_id = if category_name.is_empty() {
+                    category_id
+                } else {
+                    match category_ids.get(category_name) {
+                        Some(id) => *id,
+                        None => {
+                            let mut category = Category::from_empty();
+                            category.name = category_name.to_string();
+                            let id = category.save(tx)?;
+                            new_category = Some(category);
+                            id
+                        },
+                    }
+                };
+                card.save(tx)?;
+                for translation in translations.iter_mut() {
+                    translation.card_id = card.id;
+                    translation.save(tx)?;
+                }
+                Ok(new_category)
+            });
+            match result {
+                Ok(new_category) => {
+                    if let Some(category) = new_category {
+                        category_ids.insert(category.name, category.id);
+                    }
+                    existing.extend(keys);
+                    summary.imported += 1;
+                },
+                Err(_) => summary.failed += 1,
+            }
+        }
+        Ok(summary)
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrate;
+    use crate::export::{Format, export_cards};
+    use crate::models::filter::Filter;
+
+    #[test]
+    fn test_category_of_failed_row_is_created_again() {
+        let conn = sqlite::open(":memory:").unwrap();
+        migrate(&conn).unwrap();
+        conn.execute("
+            PRAGMA foreign_keys = ON;
+            INSERT INTO language (code, name, position) VALUES ('en', 'English', 1);
+            INSERT INTO category (name) VALUES ('default');
+            ").unwrap();
+        let columns = [Column::Category, Column::Text(1), Column::Text(99)];
+        let rows = vec![
+            // The unknown language fails the row after its category has been created
+            vec!["new".to_string(), "one".to_string(), "eins".to_string()],
+            vec!["new".to_string(), "two".to_string(), String::new()],
+            vec!["new".to_string(), "three".to_string(), String::new()],
+        ];
+        let summary = import_rows(&conn, &rows, &columns, 1).unwrap();
+        assert_eq!(summary, Summary { imported: 2, skipped: 0, failed: 1 });
+        let categories: Vec<String> = Category::load_all(&conn).unwrap().into_iter()
+            .map(|category| category.name)
+            .collect();
+        assert_eq!(categories, vec!["default".to_string(), "new".to_string()]);
+    }
+
+    #[test]
+    fn test_exported_json_is_imported_again() {
+        let languages = "
+            INSERT INTO language (code, name, position) VALUES ('to', 'Tongan', 1);
+            INSERT INTO language (code, name, position) VALUES ('en', 'English', 2);
+            INSERT INTO category (name) VALUES ('default');
+            ";
+        let source = sqlite::open(":memory:").unwrap();
+        migrate(&source).unwrap();
+        source.execute(languages).unwrap();
+        source.execute("
+            INSERT INTO category (name) VALUES ('animals');
+            INSERT INTO card (category_id) VALUES (2);
+            INSERT INTO translation (card_id, language_id, text, description)
+                VALUES (1, 1, 'kulī', 'a \"dog\"');
+            INSERT INTO translation (card_id, language_id, text, description)
+                VALUES (1, 2, 'dog', 'line 1
+line 2');
+            INSERT INTO card (category_id) VALUES (1);
+            INSERT INTO translation (card_id, language_id, text, description)
+                VALUES (2, 2, 'hello', '');
+            ").unwrap();
+        let json = export_cards(&source, Format::Json, &Filter::default()).unwrap();
+
+        let target = sqlite::open(":memory:").unwrap();
+        migrate(&target).unwrap();
+        target.execute(languages).unwrap();
+        let rows = parse_json(&json).unwrap();
+        let languages = Language::load_all(&target).unwrap();
+        let columns: Vec<Column> = rows[0].iter()
+            .map(|header| Column::guess(header, &languages))
+            .collect();
+        let summary = import_rows(&target, &rows[1..], &columns, 1).unwrap();
+        assert_eq!(summary, Summary { imported: 2, skipped: 0, failed: 0 });
+        assert_eq!(export_cards(&target, Format::Json, &Filter::default()).unwrap(), json);
+    }
+
+    #[test]
+    fn test_parse_json_without_cards_fails() {
+        assert!(matches!(parse_json("{\"languages\": []}"), Err(ParseError::Json(_))));
+        assert!(matches!(parse_json("{\"cards\": ["), Err(ParseError::Json(_))));
+        assert_eq!(parse_json("{\"cards\": []}").unwrap(), vec![vec!["Category".to_string()]]);
+    }
+}
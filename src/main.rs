@@ -8,35 +8,105 @@
 //! kaati_ako
 //! ```
 //!
-//! Or you can specify the database file by an environment variable:
+//! Or you can specify the database file by an environment variable, which takes precedence over
+//! the database file chosen in the preferences:
 //!
 //! ```sh
 //! DB_PATH=db.sqlite kaati_ako
 //! ```
+//!
+//! Options choose the database, a card, a category to study and the study mode. Given while the
+//! application is running, they are forwarded to its window instead of starting another one, see
+//! `options` or run `kaati_ako --help`:
+//!
+//! ```sh
+//! kaati_ako --db db.sqlite --card 42 --category animals --mode typed
+//! ```
+//!
+//! Given a command, it runs without a display instead, see `cli`:
+//!
+//! ```sh
+//! kaati_ako list --db db.sqlite --json
+//! ```
+
 
+use std::ffi::CString;
+use std::os::raw::c_char;
 
 use gio::prelude::{ApplicationExt, ApplicationExtManual};
+use gio::ApplicationFlags;
+use glib::translate::ToGlibPtr;
 
+mod anki;
+mod cli;
+mod compare;
 mod database;
+mod export;
+mod import;
 mod models;
+mod options;
+mod repository;
+mod session;
+mod settings;
+mod statistics;
+mod tui;
 mod ui;
 
+use options::Options;
 use ui::UI;
 
 
 /// Default path to database file
 const DEFAULT_DB_PATH: &str = "kaati_ako.sqlite";
 
-/// The version of application (ideally, this would be taken from `Cargo.toml`)
-const VERSION: &str = "0.1.0";
+/// The version of application as given in `Cargo.toml`
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+
+/// Print an error message to the standard error of the invocation a command line comes from
+///
+/// The command line may be forwarded from another process, whose terminal shows the message then.
+///
+/// # Arguments
+///
+/// * `command_line` - The command line passed to the primary instance
+/// * `message` - The error message
+fn printerr(command_line: &gio::ApplicationCommandLine, message: &str) {
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    // gio has no binding of the variadic function, so the message is passed as argument of "%s"
+    unsafe {
+        gio_sys::g_application_command_line_printerr(
+            command_line.to_glib_none().0,
+            b"%s\n\0".as_ptr() as *const c_char,
+            message.as_ptr(),
+        );
+    }
+}
 
 
-/// Build the application and run it
+/// Run the given command or build the application and run it
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 && cli::is_command(&args[1]) {
+        std::process::exit(cli::run(&args[1..]));
+    }
     let application = gtk::Application::new(
-        Some("com.github.kodeaffe.kaati_ako"), Default::default()).unwrap();
-    application.connect_activate(|app| {
-        UI::build(app);
+        Some("com.github.kodeaffe.kaati_ako"), ApplicationFlags::HANDLES_COMMAND_LINE).unwrap();
+    Options::add(&application);
+    // Runs in the primary instance, also for the command line of a later invocation
+    application.connect_command_line(|app, command_line| {
+        match Options::from_command_line(command_line) {
+            Ok(options) => {
+                UI::start(app, &options);
+                0
+            },
+            Err(err) => {
+                printerr(command_line, &err);
+                2
+            },
+        }
     });
-    application.run(&std::env::args().collect::<Vec<_>>());
+    // E.g. when started by D-Bus activation without a command line
+    application.connect_activate(|app| UI::start(app, &Options::default()));
+    application.run(&args);
 }
\ No newline at end of file
@@ -1,15 +1,106 @@
 //! Contains various (database) models
 
-use sqlite;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::database::{DatabaseError, last_insert_id};
 
 
 pub mod category;
 pub mod card;
+pub mod filter;
 pub mod language;
+pub mod leitner;
+pub mod review;
+pub mod reviewlog;
+pub mod setting;
 pub mod translation;
 
+use filter::Filter;
+use leitner::Leitner;
+use review::Sm2;
+use reviewlog::ReviewLog;
+use setting::Setting;
+
+
+/// Seconds of a day, used to convert review intervals into due dates
+pub const SECONDS_PER_DAY: i64 = 86400;
+
+
+/// Get the scheduler which is active for the database; SM-2 if none has been chosen yet
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+pub fn active_scheduler(conn: &sqlite::Connection) -> Result<Box<dyn Scheduler>, DatabaseError> {
+    match Setting::get(conn, Setting::KEY_SCHEDULER)?.as_deref() {
+        None | Some(Sm2::NAME) => Ok(Box::new(Sm2)),
+        Some(Leitner::NAME) => Ok(Box::new(Leitner::load(conn)?)),
+        Some(name) => Err(DatabaseError::ValueInvalid(name.to_string())),
+    }
+}
+
+
+/// Count the items in a table which refer to the item with the given id
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `table_name` - Name of the table which holds the referring items
+/// * `column` - Name of the column which holds the id of the referred item
+/// * `id` - Identifier of the referred item
+pub fn count_references(
+    conn: &sqlite::Connection,
+    table_name: &str,
+    column: &str,
+    id: i64,
+) -> Result<i64, DatabaseError> {
+    let statement = format!("SELECT COUNT(*) FROM {} WHERE {} = ?", table_name, column);
+    let mut cursor = conn.prepare(statement)?.cursor();
+    cursor.bind(&[sqlite::Value::Integer(id)])?;
+    match cursor.next()? {
+        Some(row) => row[0].as_integer().ok_or(DatabaseError::ValueNotInteger),
+        None => Ok(0),
+    }
+}
+
+
+/// Get the id of the most overdue card according to the due dates in the given table
+///
+/// Cards which are not in the table yet are due, but come after cards which are overdue. Only
+/// cards which pass the study filter are considered.
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `table_name` - Name of the table which holds the `card_id` and `due` columns
+pub fn next_due_card_id(conn: &sqlite::Connection, table_name: &str) -> Result<i64, DatabaseError> {
+    let statement = format!("
+        SELECT card.id FROM card LEFT JOIN {} AS schedule ON schedule.card_id = card.id
+        WHERE (schedule.due IS NULL OR schedule.due <= ?) AND {}
+        ORDER BY schedule.due IS NULL, schedule.due, RANDOM() LIMIT 1",
+        table_name,
+        Filter::load(conn)?.sql_condition(),
+    );
+    let mut cursor = conn.prepare(statement)?.cursor();
+    cursor.bind(&[sqlite::Value::Integer(timestamp())])?;
+    if let Some(row) = cursor.next()? {
+        return match row[0].as_integer() {
+            Some(id) => Ok(id),
+            None => Err(DatabaseError::ValueNotInteger),
+        }
+    }
+    Err(DatabaseError::NotFound)
+}
+
+
+/// Get the current time as Unix timestamp in seconds
+pub fn timestamp() -> i64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(_) => 0,
+    }
+}
+
 
 /// A trait to implement a (database) model
 pub trait Model {
@@ -34,6 +125,11 @@ pub trait Model {
     ///
     /// * `conn` - Connection to the database
     /// * `id` - Identifier of the item to delete
+    ///
+    /// # Notes
+    ///
+    /// * Items which refer to the deleted one are deleted by the database if their foreign key
+    ///   cascades, otherwise the model should refuse with `DatabaseError::StillReferenced`.
     fn delete(conn: &sqlite::Connection, id: i64) -> Result<bool, DatabaseError> where Self: Sized {
         let statement = format!("DELETE FROM {} where id = ?", Self::TABLE_NAME);
         let mut cursor = conn.prepare(statement)?.cursor();
@@ -60,13 +156,13 @@ pub trait Model {
     ///
     /// * `conn` - Connection to the database
     /// * `values` - Values to insert into the database, they must correspond to
-    /// `Self::STATEMENT_INSERT`
+    ///   `Self::STATEMENT_INSERT`
     ///
     /// # Notes
     ///
     /// * `Self::STATEMENT_INSERT` is used to update the data
     fn insert(
-        conn: &sqlite::Connection, values: &Vec<sqlite::Value>,
+        conn: &sqlite::Connection, values: &[sqlite::Value],
     ) -> Result<i64, DatabaseError> where Self: Sized {
         let mut cursor = conn.prepare(Self::STATEMENT_INSERT)?.cursor();
         cursor.bind(values)?;
@@ -89,11 +185,10 @@ pub trait Model {
     fn load(conn: &sqlite::Connection, id: i64) -> Result<Self, DatabaseError> where Self: Sized {
         let mut cursor = conn.prepare(Self::STATEMENT_SELECT)?.cursor();
         cursor.bind(&[sqlite::Value::Integer(id)])?;
-        while let Some(row) = cursor.next()? {
-            let item = Self::from_row(row)?;
-            return Ok(item);
+        match cursor.next()? {
+            Some(row) => Self::from_row(row),
+            None => Err(DatabaseError::NotFound),
         }
-        Err(DatabaseError::NotFound)
     }
 
     /// Load all items from database
@@ -122,7 +217,7 @@ pub trait Model {
     ///
     /// * `conn` - Connection to the database
     /// * `values` - Values to update in the database, they must correspond to
-    /// `Self::STATEMENT_UPDATE`
+    ///   `Self::STATEMENT_UPDATE`
     ///
     /// # Notes
     ///
@@ -130,7 +225,7 @@ pub trait Model {
     /// * `Self::STATEMENT_UPDATE` is used to update the data
     fn update(
         conn: &sqlite::Connection,
-        values: &Vec<sqlite::Value>,
+        values: &[sqlite::Value],
     ) -> Result<bool, DatabaseError> where Self: Sized {
         let mut cursor = conn.prepare(Self::STATEMENT_UPDATE)?.cursor();
         cursor.bind(values)?;
@@ -140,4 +235,30 @@ pub trait Model {
 
     // I wish a save function with the following signature to set the object's new id was possible:
     //fn save(&mut self, conn: &sqlite::Connection) -> Result<i64, DatabaseError>
+}
+
+
+/// A trait to implement a learning algorithm which schedules the flash cards
+pub trait Scheduler {
+    /// Name of the scheduler as stored in the settings
+    fn name(&self) -> &'static str;
+
+    /// Get the id of the next card which is due for review
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    ///
+    /// # Notes
+    ///
+    /// * `DatabaseError::NotFound` is returned if no card is due
+    fn next_card_id(&self, conn: &sqlite::Connection) -> Result<i64, DatabaseError>;
+
+    /// Reschedule a card after it has been answered
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `log` - The log entry of the answer
+    fn answer(&self, conn: &sqlite::Connection, log: &ReviewLog) -> Result<(), DatabaseError>;
 }
\ No newline at end of file
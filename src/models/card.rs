@@ -1,9 +1,18 @@
 //! Model Card
 
-use sqlite;
+use crate::database::{DatabaseError, with_transaction};
+use super::{Model, active_scheduler};
+use super::filter::Filter;
 
-use crate::database::DatabaseError;
-use super::Model;
+
+/// How to select the next card to study
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selection {
+    /// Pick any card at random
+    Random,
+    /// Pick the most overdue card according to the active scheduler
+    Scheduled,
+}
 
 
 /// A flash card
@@ -22,23 +31,44 @@ impl Card {
     /// # Arguments
     ///
     /// * `conn` - Connection to the database
-    /// * `card_id` - Identifier of the card to get. Will get a random card if 0.
+    /// * `card_id` - Identifier of the card to get. Will get the next due card which passes the
+    ///   study filter if 0.
     pub fn get(conn: &sqlite::Connection, card_id: i64) -> Result<Card, DatabaseError> {
-        let id = if card_id == 0 { Card::random_id(&conn)? } else { card_id };
+        let id = if card_id == 0 { Card::next_id(conn, Selection::Scheduled)? } else { card_id };
         let card = Card::load(conn, id)?;
         Ok(card)
     }
 
-    /// Get the id of a random Card
+    /// Get the id of the next Card to study
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `selection` - How to select the card. If no card is due for a scheduled selection, a
+    ///   random card is picked instead.
+    pub fn next_id(conn: &sqlite::Connection, selection: Selection) -> Result<i64, DatabaseError> {
+        match selection {
+            Selection::Random => Card::random_id(conn),
+            Selection::Scheduled => match active_scheduler(conn)?.next_card_id(conn) {
+                Err(DatabaseError::NotFound) => Card::next_id(conn, Selection::Random),
+                result => result,
+            },
+        }
+    }
+
+    /// Get the id of a random Card which passes the study filter
     ///
     /// # Arguments
     ///
     /// * `conn` - Connection to the database
     pub fn random_id(conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
         let statement = format!(
-            "SELECT id FROM {} ORDER BY RANDOM() LIMIT 1", Card::TABLE_NAME);
+            "SELECT id FROM {} AS card WHERE {} ORDER BY RANDOM() LIMIT 1",
+            Card::TABLE_NAME,
+            Filter::load(conn)?.sql_condition(),
+        );
         let mut cursor = conn.prepare(statement)?.cursor();
-        while let Some(row) = cursor.next()? {
+        if let Some(row) = cursor.next()? {
             return match row[0].as_integer() {
                 Some(id) => Ok(id),
                 None => Err(DatabaseError::ValueNotInteger),
@@ -54,13 +84,15 @@ impl Card {
     /// * `conn` - Connection to the database
     pub fn save(&mut self, conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
         let mut values = vec![sqlite::Value::Integer(self.category_id)];
-        if self.id > 0 {
-            values.push(sqlite::Value::Integer(self.id));
-            Card::update(conn, &values)?;
-        } else {
-            self.id = Card::insert(conn, &values)?;
-        }
-        Ok(self.id)
+        with_transaction(conn, |tx| {
+            if self.id > 0 {
+                values.push(sqlite::Value::Integer(self.id));
+                Card::update(tx, &values)?;
+            } else {
+                self.id = Card::insert(tx, &values)?;
+            }
+            Ok(self.id)
+        })
     }
 }
 
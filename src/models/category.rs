@@ -1,9 +1,7 @@
 //! Model Category
 
-use sqlite;
-
-use crate::database::DatabaseError;
-use super::Model;
+use crate::database::{DatabaseError, with_transaction};
+use super::{Model, count_references};
 
 
 /// A flash card category
@@ -28,11 +26,43 @@ impl Category {
     pub fn load_by_name(conn: &sqlite::Connection, name: String) -> Result<Category, DatabaseError> {
         let mut cursor = conn.prepare(Category::STATEMENT_LOAD_BY_NAME)?.cursor();
         cursor.bind(&[sqlite::Value::String(name)])?;
-        while let Some(row) = cursor.next()? {
-            let item = Self::from_row(row)?;
-            return Ok(item);
+        match cursor.next()? {
+            Some(row) => Self::from_row(row),
+            None => Err(DatabaseError::NotFound),
         }
-        Err(DatabaseError::NotFound)
+    }
+
+    /// Count the cards in a category
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `id` - Identifier of the category
+    pub fn count_cards(conn: &sqlite::Connection, id: i64) -> Result<i64, DatabaseError> {
+        count_references(conn, "card", "category_id", id)
+    }
+
+    /// Merge a category into another one: move its cards and delete it
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `id` - Identifier of the category to merge and delete
+    /// * `into_id` - Identifier of the category to move the cards to
+    pub fn merge(conn: &sqlite::Connection, id: i64, into_id: i64) -> Result<(), DatabaseError> {
+        if id == into_id {
+            return Ok(());
+        }
+        // Fail early, before any card is moved to a category which does not exist
+        Category::load(conn, into_id)?;
+        with_transaction(conn, |tx| {
+            let mut cursor = tx.prepare("UPDATE card SET category_id = ? WHERE category_id = ?")?
+                .cursor();
+            cursor.bind(&[sqlite::Value::Integer(into_id), sqlite::Value::Integer(id)])?;
+            cursor.next()?;
+            Category::delete(tx, id)?;
+            Ok(())
+        })
     }
 
     /// Save a Category to database (insert or update)
@@ -40,7 +70,6 @@ impl Category {
     /// # Arguments
     ///
     /// * `conn` - Connection to the database
-    #[allow(dead_code)]
     pub fn save(&mut self, conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
         let mut values = vec![sqlite::Value::String(self.name.clone())];
         if self.id > 0 {
@@ -61,6 +90,24 @@ impl Model for Category {
     const STATEMENT_SELECT_ALL: &'static str = "SELECT id, name FROM category ORDER BY name";
     const STATEMENT_UPDATE: &'static str = "UPDATE category SET name = ? WHERE id = ?";
 
+    /// Delete a category from database by id, refused if any card still uses it
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `id` - Identifier of the category to delete
+    fn delete(conn: &sqlite::Connection, id: i64) -> Result<bool, DatabaseError> {
+        let count = count_references(conn, "card", "category_id", id)?;
+        if count > 0 {
+            return Err(DatabaseError::StillReferenced(
+                format!("category is used by {} cards", count)));
+        }
+        let mut cursor = conn.prepare("DELETE FROM category WHERE id = ?")?.cursor();
+        cursor.bind(&[sqlite::Value::Integer(id)])?;
+        cursor.next()?;
+        Ok(true)
+    }
+
     fn from_empty() -> Category {
         Category { id: 0, name: "".to_string() }
     }
@@ -0,0 +1,208 @@
+//! Model Filter
+
+use std::collections::HashSet;
+
+use crate::database::{DatabaseError, with_transaction};
+use super::setting::Setting;
+
+
+/// A filter which restricts the cards and translations to study, stored in the database's settings
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    /// Identifiers of the categories to study; empty for all categories
+    pub category_ids: Vec<i64>,
+    /// Identifiers of the active languages; empty for all languages
+    pub language_ids: Vec<i64>,
+}
+
+
+impl Filter {
+    /// Load the filter from the settings of the database
+    ///
+    /// Categories and languages which do not exist anymore are dropped from the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    pub fn load(conn: &sqlite::Connection) -> Result<Filter, DatabaseError> {
+        Ok(Filter {
+            category_ids: Filter::load_ids(conn, Setting::KEY_FILTER_CATEGORIES, "category")?,
+            language_ids: Filter::load_ids(conn, Setting::KEY_FILTER_LANGUAGES, "language")?,
+        })
+    }
+
+    /// Load the identifiers of a setting which still exist in the given table
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `key` - Key of the setting which holds the comma separated identifiers
+    /// * `table_name` - Name of the table the identifiers refer to
+    fn load_ids(
+        conn: &sqlite::Connection,
+        key: &str,
+        table_name: &str,
+    ) -> Result<Vec<i64>, DatabaseError> {
+        let ids = match Setting::get(conn, key)? {
+            Some(value) => Filter::parse_ids(&value)?,
+            None => { return Ok(Vec::new()); },
+        };
+        let mut existing = HashSet::new();
+        let mut cursor = conn.prepare(format!("SELECT id FROM {}", table_name))?.cursor();
+        while let Some(row) = cursor.next()? {
+            existing.insert(row[0].as_integer().ok_or(DatabaseError::ValueNotInteger)?);
+        }
+        Ok(ids.into_iter().filter(|id| existing.contains(id)).collect())
+    }
+
+    /// Parse comma separated identifiers, e.g. `1, 3, 7`; an empty value has no identifiers
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The identifiers as stored in the settings
+    fn parse_ids(value: &str) -> Result<Vec<i64>, DatabaseError> {
+        let mut ids = Vec::new();
+        for id in value.split(',').filter(|id| !id.trim().is_empty()) {
+            match id.trim().parse::<i64>() {
+                Ok(id) => ids.push(id),
+                Err(_) => { return Err(DatabaseError::ValueInvalid(value.to_string())); },
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Save the filter to the settings of the database
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), DatabaseError> {
+        let join = |ids: &[i64]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",");
+        with_transaction(conn, |tx| {
+            Setting::set(tx, Setting::KEY_FILTER_CATEGORIES, &join(&self.category_ids))?;
+            Setting::set(tx, Setting::KEY_FILTER_LANGUAGES, &join(&self.language_ids))
+        })
+    }
+
+    /// Check if translations in the given language are studied
+    ///
+    /// # Arguments
+    ///
+    /// * `language_id` - Identifier of the language to check
+    pub fn is_language_active(&self, language_id: i64) -> bool {
+        self.language_ids.is_empty() || self.language_ids.contains(&language_id)
+    }
+
+    /// Get the SQL condition which selects the cards to study from the table `card`
+    ///
+    /// A card passes if it is in an active category and, if the languages are filtered, has
+    /// translations in at least two active languages, one to prompt with and one to answer.
+    pub fn sql_condition(&self) -> String {
+        self.condition(2)
+    }
+
+    /// Get the SQL condition which selects the cards to export from the table `card`
+    ///
+    /// A card passes if it is in an active category and, if the languages are filtered, has a
+    /// translation in an active language.
+    pub fn export_sql_condition(&self) -> String {
+        self.condition(1)
+    }
+
+    /// Get the SQL condition which selects the cards passing the filter from the table `card`
+    ///
+    /// # Arguments
+    ///
+    /// * `min_translations` - Number of translations in active languages a card needs to pass,
+    ///   if the languages are filtered
+    fn condition(&self, min_translations: usize) -> String {
+        let join = |ids: &[i64]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ");
+        let mut conditions = vec!["1".to_string()];
+        if !self.category_ids.is_empty() {
+            conditions.push(format!("card.category_id IN ({})", join(&self.category_ids)));
+        }
+        if !self.language_ids.is_empty() {
+            conditions.push(format!(
+                "(SELECT COUNT(*) FROM translation WHERE translation.card_id = card.id
+                    AND translation.language_id IN ({})) >= {}",
+                join(&self.language_ids),
+                min_translations,
+            ));
+        }
+        conditions.join(" AND ")
+    }
+
+    /// Check if a card is studied with the filter, see `Filter::sql_condition`
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `card_id` - Identifier of the card to check
+    pub fn passes(&self, conn: &sqlite::Connection, card_id: i64) -> Result<bool, DatabaseError> {
+        let statement = format!(
+            "SELECT COUNT(*) FROM card WHERE card.id = ? AND {}", self.sql_condition());
+        let mut cursor = conn.prepare(statement)?.cursor();
+        cursor.bind(&[sqlite::Value::Integer(card_id)])?;
+        match cursor.next()? {
+            Some(row) => Ok(row[0].as_integer().ok_or(DatabaseError::ValueNotInteger)? > 0),
+            None => Ok(false),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrate;
+
+    fn open_database() -> sqlite::Connection {
+        let conn = sqlite::open(":memory:").unwrap();
+        migrate(&conn).unwrap();
+        conn.execute("
+            INSERT INTO language (code, name, position) VALUES ('to', 'Tongan', 1);
+            INSERT INTO language (code, name, position) VALUES ('en', 'English', 2);
+            INSERT INTO language (code, name, position) VALUES ('de', 'German', 3);
+            INSERT INTO category (name) VALUES ('default');
+            INSERT INTO category (name) VALUES ('animals');
+            INSERT INTO card (category_id) VALUES (1);
+            INSERT INTO translation (card_id, language_id, text, description)
+                VALUES (1, 1, 'kaati', '');
+            INSERT INTO translation (card_id, language_id, text, description)
+                VALUES (1, 2, 'card', '');
+            INSERT INTO card (category_id) VALUES (2);
+            INSERT INTO translation (card_id, language_id, text, description)
+                VALUES (2, 1, 'kulī', '');
+            INSERT INTO translation (card_id, language_id, text, description)
+                VALUES (2, 3, 'Hund', '');
+            ").unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_passes_categories() {
+        let conn = open_database();
+        let filter = Filter { category_ids: vec![2], language_ids: Vec::new() };
+        assert!(!filter.passes(&conn, 1).unwrap());
+        assert!(filter.passes(&conn, 2).unwrap());
+        assert!(Filter::default().passes(&conn, 1).unwrap());
+        assert!(!Filter::default().passes(&conn, 3).unwrap());
+    }
+
+    #[test]
+    fn test_passes_languages() {
+        let conn = open_database();
+        // A card needs two translations in the active languages to be studied, but only one to
+        // be exported
+        let filter = Filter { category_ids: Vec::new(), language_ids: vec![1, 2] };
+        assert!(filter.passes(&conn, 1).unwrap());
+        assert!(!filter.passes(&conn, 2).unwrap());
+        let count = |condition: String| -> i64 {
+            let statement = format!("SELECT COUNT(*) FROM card WHERE {}", condition);
+            let mut cursor = conn.prepare(statement).unwrap().cursor();
+            cursor.next().unwrap().unwrap()[0].as_integer().unwrap()
+        };
+        assert_eq!(count(filter.sql_condition()), 1);
+        assert_eq!(count(filter.export_sql_condition()), 2);
+    }
+}
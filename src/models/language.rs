@@ -1,9 +1,7 @@
 //! Model Language
 
-use sqlite;
-
-use crate::database::DatabaseError;
-use super::Model;
+use crate::database::{DatabaseError, with_transaction};
+use super::{Model, count_references};
 
 
 /// The language of a flash card translation
@@ -11,45 +9,138 @@ use super::Model;
 pub struct Language {
     /// Identifier of the language
     pub id: i64,
-    /// Code of the language as in [ISO 3166-1 alpha-2](https://en.wikipedia.org/wiki/ISO_3166-1_alpha-2)
+    /// Code of the language as in [ISO 639-1](https://en.wikipedia.org/wiki/List_of_ISO_639-1_codes)
     pub code: String,
     /// Name of the language
     pub name: String,
+    /// Position of the language when languages are shown in order, starting with 1
+    pub position: i64,
 }
 
 impl Language {
+    /// All current ISO 639-1 language codes in alphabetical order, withdrawn ones like `bh` are
+    /// left out
+    const CODES: &'static [&'static str] = &[
+        "aa", "ab", "ae", "af", "ak", "am", "an", "ar", "as", "av", "ay", "az", "ba", "be", "bg",
+        "bi", "bm", "bn", "bo", "br", "bs", "ca", "ce", "ch", "co", "cr", "cs", "cu", "cv", "cy",
+        "da", "de", "dv", "dz", "ee", "el", "en", "eo", "es", "et", "eu", "fa", "ff", "fi", "fj",
+        "fo", "fr", "fy", "ga", "gd", "gl", "gn", "gu", "gv", "ha", "he", "hi", "ho", "hr", "ht",
+        "hu", "hy", "hz", "ia", "id", "ie", "ig", "ii", "ik", "io", "is", "it", "iu", "ja", "jv",
+        "ka", "kg", "ki", "kj", "kk", "kl", "km", "kn", "ko", "kr", "ks", "ku", "kv", "kw", "ky",
+        "la", "lb", "lg", "li", "ln", "lo", "lt", "lu", "lv", "mg", "mh", "mi", "mk", "ml", "mn",
+        "mr", "ms", "mt", "my", "na", "nb", "nd", "ne", "ng", "nl", "nn", "no", "nr", "nv", "ny",
+        "oc", "oj", "om", "or", "os", "pa", "pi", "pl", "ps", "pt", "qu", "rm", "rn", "ro", "ru",
+        "rw", "sa", "sc", "sd", "se", "sg", "si", "sk", "sl", "sm", "sn", "so", "sq", "sr", "ss",
+        "st", "su", "sv", "sw", "ta", "te", "tg", "th", "ti", "tk", "tl", "tn", "to", "tr", "ts",
+        "tt", "tw", "ty", "ug", "uk", "ur", "uz", "ve", "vi", "vo", "wa", "wo", "xh", "yi", "yo",
+        "za", "zh", "zu",
+    ];
+
+    /// Check if a code is a valid ISO 639-1 language code
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The code to check, in lower case
+    pub fn is_valid_code(code: &str) -> bool {
+        Language::CODES.binary_search(&code).is_ok()
+    }
+
+    /// Get the position after the last language
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    fn next_position(conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+        let mut cursor = conn.prepare("SELECT COALESCE(MAX(position), 0) + 1 FROM language")?
+            .cursor();
+        match cursor.next()? {
+            Some(row) => row[0].as_integer().ok_or(DatabaseError::ValueNotInteger),
+            None => Ok(1),
+        }
+    }
+
     /// Save a Language to database (insert or update)
     ///
+    /// A new language without a position is put after the last one.
+    ///
     /// # Arguments
     ///
     /// * `conn` - Connection to the database
-    #[allow(dead_code)]
     pub fn save(&mut self, conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+        if !Language::is_valid_code(&self.code) {
+            return Err(DatabaseError::ValueInvalid(self.code.clone()));
+        }
+        if self.position <= 0 {
+            self.position = Language::next_position(conn)?;
+        }
         let mut values = vec![
             sqlite::Value::String(self.code.clone()),
             sqlite::Value::String(self.name.clone()),
+            sqlite::Value::Integer(self.position),
         ];
         if self.id > 0 {
             values.push(sqlite::Value::Integer(self.id));
             Language::update(conn, &values)?;
         } else {
-
             self.id = Language::insert(conn, &values)?;
         }
         Ok(self.id)
     }
+
+    /// Set the display order of languages
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `ids` - Identifiers of the languages in their new order
+    pub fn reorder(conn: &sqlite::Connection, ids: &[i64]) -> Result<(), DatabaseError> {
+        with_transaction(conn, |tx| {
+            for (idx, id) in ids.iter().enumerate() {
+                let mut cursor = tx.prepare("UPDATE language SET position = ? WHERE id = ?")?
+                    .cursor();
+                let position = idx as i64 + 1;
+                cursor.bind(&[sqlite::Value::Integer(position), sqlite::Value::Integer(*id)])?;
+                cursor.next()?;
+            }
+            Ok(())
+        })
+    }
 }
 
 
 impl Model for Language {
     const TABLE_NAME: &'static str = "language";
-    const STATEMENT_INSERT: &'static str = "INSERT INTO language (code, name) VALUES (?, ?)";
-    const STATEMENT_SELECT: &'static str = "SELECT id, code, name FROM language WHERE id = ?";
-    const STATEMENT_SELECT_ALL: &'static str = "SELECT id, code, name FROM language ORDER BY name";
-    const STATEMENT_UPDATE: &'static str = "UPDATE language SET code = ?, name = ? WHERE id = ?";
+    const STATEMENT_INSERT: &'static str =
+        "INSERT INTO language (code, name, position) VALUES (?, ?, ?)";
+    const STATEMENT_SELECT: &'static str =
+        "SELECT id, code, name, position FROM language WHERE id = ?";
+    const STATEMENT_SELECT_ALL: &'static str =
+        "SELECT id, code, name, position FROM language ORDER BY position, id";
+    const STATEMENT_UPDATE: &'static str =
+        "UPDATE language SET code = ?, name = ?, position = ? WHERE id = ?";
+
+    /// Delete a language from database by id, refused if any translation still uses it
+    ///
+    /// The review history in the language is deleted with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `id` - Identifier of the language to delete
+    fn delete(conn: &sqlite::Connection, id: i64) -> Result<bool, DatabaseError> {
+        let count = count_references(conn, "translation", "language_id", id)?;
+        if count > 0 {
+            return Err(DatabaseError::StillReferenced(
+                format!("language is used by {} translations", count)));
+        }
+        let mut cursor = conn.prepare("DELETE FROM language WHERE id = ?")?.cursor();
+        cursor.bind(&[sqlite::Value::Integer(id)])?;
+        cursor.next()?;
+        Ok(true)
+    }
 
     fn from_empty() -> Language {
-        Language { id: 0, code: "".to_string(), name: "".to_string() }
+        Language { id: 0, code: "".to_string(), name: "".to_string(), position: 0 }
     }
 
     fn from_row(row: &[sqlite::Value]) -> Result<Language, DatabaseError> {
@@ -65,6 +156,10 @@ impl Model for Language {
             Some(name) => name.to_string(),
             None => { return Err(DatabaseError::ValueNotString); },
         };
-        Ok(Language { id, code, name })
+        let position = match row[3].as_integer() {
+            Some(position) => position,
+            None => { return Err(DatabaseError::ValueNotInteger); },
+        };
+        Ok(Language { id, code, name, position })
     }
 }
\ No newline at end of file
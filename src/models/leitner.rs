@@ -0,0 +1,286 @@
+//! Model LeitnerCard
+//!
+//! Holds the box of a flash card and implements the
+//! [Leitner system](https://en.wikipedia.org/wiki/Leitner_system) to compute when the card is due
+//! again.
+
+use crate::database::DatabaseError;
+use super::{Model, Scheduler, SECONDS_PER_DAY, next_due_card_id};
+use super::review::Grade;
+use super::reviewlog::ReviewLog;
+use super::setting::Setting;
+
+
+/// The Leitner system as scheduler
+#[derive(Debug)]
+pub struct Leitner {
+    /// Review interval in days for each box, the number of boxes is the number of intervals
+    pub intervals: Vec<i64>,
+}
+
+
+impl Leitner {
+    /// Name of the scheduler as stored in the settings
+    pub const NAME: &'static str = "leitner";
+    /// Review intervals of the boxes if not configured otherwise
+    const DEFAULT_INTERVALS: [i64; 5] = [1, 2, 4, 8, 16];
+
+    /// Load the Leitner system as configured in the database
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    pub fn load(conn: &sqlite::Connection) -> Result<Leitner, DatabaseError> {
+        let intervals = match Setting::get(conn, Setting::KEY_LEITNER_INTERVALS)? {
+            Some(value) => Leitner::parse_intervals(&value)?,
+            None => Leitner::DEFAULT_INTERVALS.to_vec(),
+        };
+        Ok(Leitner { intervals })
+    }
+
+    /// Parse the comma separated review intervals of the boxes, e.g. `1, 3, 7`
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The intervals as stored in the settings
+    pub fn parse_intervals(value: &str) -> Result<Vec<i64>, DatabaseError> {
+        let mut intervals = Vec::new();
+        for interval in value.split(',') {
+            match interval.trim().parse::<i64>() {
+                Ok(interval) if interval > 0 => intervals.push(interval),
+                _ => { return Err(DatabaseError::ValueInvalid(value.to_string())); },
+            }
+        }
+        Ok(intervals)
+    }
+
+    /// Get the review intervals of the boxes as stored in the settings, e.g. `1, 3, 7`
+    pub fn intervals_text(&self) -> String {
+        let intervals: Vec<String> = self.intervals.iter()
+            .map(|interval| interval.to_string())
+            .collect();
+        intervals.join(", ")
+    }
+
+    /// Save the review intervals of the boxes to the database
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), DatabaseError> {
+        Setting::set(conn, Setting::KEY_LEITNER_INTERVALS, &self.intervals_text())
+    }
+}
+
+
+impl Scheduler for Leitner {
+    fn name(&self) -> &'static str {
+        Leitner::NAME
+    }
+
+    fn next_card_id(&self, conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+        next_due_card_id(conn, LeitnerCard::TABLE_NAME)
+    }
+
+    fn answer(&self, conn: &sqlite::Connection, log: &ReviewLog) -> Result<(), DatabaseError> {
+        let mut card = LeitnerCard::load_for_card(conn, log.card_id)?;
+        if log.grade == Grade::Again {
+            card.box_index = 0;
+        } else if card.box_index + 1 < self.intervals.len() as i64 {
+            card.box_index += 1;
+        }
+        let interval = match self.intervals.get(card.box_index as usize) {
+            Some(interval) => *interval,
+            None => 1,
+        };
+        card.due = log.timestamp + interval * SECONDS_PER_DAY;
+        card.save(conn)?;
+        Ok(())
+    }
+}
+
+
+/// The Leitner box a flash card is currently in
+#[derive(Debug)]
+pub struct LeitnerCard {
+    /// Identifier of the Leitner card
+    pub id: i64,
+    /// Card which is in the box
+    pub card_id: i64,
+    /// Index of the box, starting with 0 for the first box
+    pub box_index: i64,
+    /// Unix timestamp when the card is due for the next review
+    pub due: i64,
+}
+
+
+impl LeitnerCard {
+    /// Load the Leitner card for a given card from the database
+    ///
+    /// If the card has not been reviewed yet, a new Leitner card in the first box is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `card_id` - Identifier of the card for which to load the Leitner card
+    pub fn load_for_card(
+        conn: &sqlite::Connection,
+        card_id: i64,
+    ) -> Result<LeitnerCard, DatabaseError> {
+        let statement = format!(
+            "SELECT id, card_id, box, due FROM {} WHERE card_id = ?", LeitnerCard::TABLE_NAME);
+        let mut cursor = conn.prepare(statement)?.cursor();
+        cursor.bind(&[sqlite::Value::Integer(card_id)])?;
+        if let Some(row) = cursor.next()? {
+            return LeitnerCard::from_row(row);
+        }
+        let mut card = LeitnerCard::from_empty();
+        card.card_id = card_id;
+        Ok(card)
+    }
+
+    /// Save a LeitnerCard to database (insert or update)
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    pub fn save(&mut self, conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+        let mut values = vec![
+            sqlite::Value::Integer(self.card_id),
+            sqlite::Value::Integer(self.box_index),
+            sqlite::Value::Integer(self.due),
+        ];
+        if self.id > 0 {
+            values.push(sqlite::Value::Integer(self.id));
+            LeitnerCard::update(conn, &values)?;
+        } else {
+            self.id = LeitnerCard::insert(conn, &values)?;
+        }
+        Ok(self.id)
+    }
+}
+
+
+impl Model for LeitnerCard {
+    const TABLE_NAME: &'static str = "leitner_card";
+    const STATEMENT_INSERT: &'static str =
+        "INSERT INTO leitner_card (card_id, box, due) VALUES (?, ?, ?)";
+    const STATEMENT_SELECT: &'static str =
+        "SELECT id, card_id, box, due FROM leitner_card WHERE id = ?";
+    const STATEMENT_SELECT_ALL: &'static str =
+        "SELECT id, card_id, box, due FROM leitner_card ORDER BY due";
+    const STATEMENT_UPDATE: &'static str =
+        "UPDATE leitner_card SET card_id = ?, box = ?, due = ? WHERE id = ?";
+
+    fn from_empty() -> LeitnerCard {
+        LeitnerCard { id: 0, card_id: 0, box_index: 0, due: 0 }
+    }
+
+    fn from_row(row: &[sqlite::Value]) -> Result<LeitnerCard, DatabaseError> {
+        let id = match row[0].as_integer() {
+            Some(id) => id,
+            None => { return Err(DatabaseError::ValueNotInteger); },
+        };
+        let card_id = match row[1].as_integer() {
+            Some(id) => id,
+            None => { return Err(DatabaseError::ValueNotInteger); },
+        };
+        let box_index = match row[2].as_integer() {
+            Some(box_index) => box_index,
+            None => { return Err(DatabaseError::ValueNotInteger); },
+        };
+        let due = match row[3].as_integer() {
+            Some(due) => due,
+            None => { return Err(DatabaseError::ValueNotInteger); },
+        };
+        Ok(LeitnerCard { id, card_id, box_index, due })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrate;
+
+    /// Open an empty database with one card
+    fn open_database() -> sqlite::Connection {
+        let conn = sqlite::open(":memory:").unwrap();
+        migrate(&conn).unwrap();
+        conn.execute("
+            INSERT INTO category (name) VALUES ('default');
+            INSERT INTO card (category_id) VALUES (1);
+            ").unwrap();
+        conn
+    }
+
+    /// Answer card 1 and get its box index and due date
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `leitner` - The scheduler
+    /// * `grade` - The grade of the answer
+    /// * `timestamp` - Unix timestamp of the answer
+    fn answer(
+        conn: &sqlite::Connection,
+        leitner: &Leitner,
+        grade: Grade,
+        timestamp: i64,
+    ) -> (i64, i64) {
+        let mut log = ReviewLog::new(1, 1, 2, grade, 1000);
+        log.timestamp = timestamp;
+        leitner.answer(conn, &log).unwrap();
+        let card = LeitnerCard::load_for_card(conn, 1).unwrap();
+        (card.box_index, card.due)
+    }
+
+    #[test]
+    fn test_box_moves() {
+        let conn = open_database();
+        let leitner = Leitner { intervals: vec![1, 3, 7] };
+        // Each recalled answer moves the card up to the last box, where it stays
+        assert_eq!(answer(&conn, &leitner, Grade::Good, 0), (1, 3 * SECONDS_PER_DAY));
+        assert_eq!(answer(&conn, &leitner, Grade::Hard, 10), (2, 10 + 7 * SECONDS_PER_DAY));
+        assert_eq!(answer(&conn, &leitner, Grade::Easy, 20), (2, 20 + 7 * SECONDS_PER_DAY));
+        // Again moves the card back to the first box
+        assert_eq!(answer(&conn, &leitner, Grade::Again, 30), (0, 30 + SECONDS_PER_DAY));
+        assert_eq!(answer(&conn, &leitner, Grade::Again, 40), (0, 40 + SECONDS_PER_DAY));
+        assert_eq!(answer(&conn, &leitner, Grade::Good, 50), (1, 50 + 3 * SECONDS_PER_DAY));
+    }
+
+    #[test]
+    fn test_single_box() {
+        let conn = open_database();
+        let leitner = Leitner { intervals: vec![2] };
+        assert_eq!(answer(&conn, &leitner, Grade::Good, 0), (0, 2 * SECONDS_PER_DAY));
+        assert_eq!(answer(&conn, &leitner, Grade::Again, 0), (0, 2 * SECONDS_PER_DAY));
+    }
+
+    #[test]
+    fn test_load_intervals() {
+        let conn = open_database();
+        assert_eq!(Leitner::load(&conn).unwrap().intervals, Leitner::DEFAULT_INTERVALS.to_vec());
+        Setting::set(&conn, Setting::KEY_LEITNER_INTERVALS, "1, 3,7").unwrap();
+        assert_eq!(Leitner::load(&conn).unwrap().intervals, vec![1, 3, 7]);
+    }
+
+    #[test]
+    fn test_save_intervals() {
+        let conn = open_database();
+        let leitner = Leitner { intervals: vec![2, 5, 10] };
+        assert_eq!(leitner.intervals_text(), "2, 5, 10");
+        leitner.save(&conn).unwrap();
+        assert_eq!(Leitner::load(&conn).unwrap().intervals, vec![2, 5, 10]);
+    }
+
+    #[test]
+    fn test_parse_intervals() {
+        assert_eq!(Leitner::parse_intervals("2").unwrap(), vec![2]);
+        assert!(Leitner::parse_intervals("").is_err());
+        assert!(Leitner::parse_intervals("1, 0").is_err());
+        assert!(Leitner::parse_intervals("1, -2").is_err());
+        assert!(Leitner::parse_intervals("1,,2").is_err());
+        assert!(Leitner::parse_intervals("1, a").is_err());
+    }
+}
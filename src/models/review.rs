@@ -0,0 +1,317 @@
+//! Model Review
+//!
+//! Holds the scheduling state of a flash card and implements the
+//! [SM-2](https://www.supermemo.com/en/archives1990-2015/english/ol/sm2) algorithm to compute when
+//! the card is due again.
+
+use crate::database::DatabaseError;
+use super::{Model, Scheduler, SECONDS_PER_DAY, next_due_card_id};
+use super::reviewlog::ReviewLog;
+
+
+/// The grade given by the user when answering a flash card
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Grade {
+    /// The answer was wrong
+    Again,
+    /// The answer was right, but with serious difficulty
+    Hard,
+    /// The answer was right after some hesitation
+    Good,
+    /// The answer was perfect
+    Easy,
+}
+
+
+impl Grade {
+    /// All grades, from worst to best
+    pub const ALL: [Grade; 4] = [Grade::Again, Grade::Hard, Grade::Good, Grade::Easy];
+
+    /// Get the grade from the value stored in the database
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The stored value as returned by `Grade::value`
+    pub fn from_value(value: i64) -> Option<Grade> {
+        Grade::ALL.iter().find(|grade| grade.value() == value).copied()
+    }
+
+    /// Get the name of the grade, e.g. to be used in action names
+    pub fn name(&self) -> &'static str {
+        match self {
+            Grade::Again => "again",
+            Grade::Hard => "hard",
+            Grade::Good => "good",
+            Grade::Easy => "easy",
+        }
+    }
+
+    /// Get the quality of response as used by SM-2, ranging from 0 (blackout) to 5 (perfect)
+    pub fn quality(&self) -> i64 {
+        match self {
+            Grade::Again => 1,
+            Grade::Hard => 3,
+            Grade::Good => 4,
+            Grade::Easy => 5,
+        }
+    }
+
+    /// Get the value to store the grade in the database
+    pub fn value(&self) -> i64 {
+        match self {
+            Grade::Again => 1,
+            Grade::Hard => 2,
+            Grade::Good => 3,
+            Grade::Easy => 4,
+        }
+    }
+}
+
+
+/// The SM-2 algorithm as scheduler
+#[derive(Debug)]
+pub struct Sm2;
+
+
+impl Sm2 {
+    /// Name of the scheduler as stored in the settings
+    pub const NAME: &'static str = "sm2";
+}
+
+
+impl Scheduler for Sm2 {
+    fn name(&self) -> &'static str {
+        Sm2::NAME
+    }
+
+    fn next_card_id(&self, conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+        next_due_card_id(conn, Review::TABLE_NAME)
+    }
+
+    fn answer(&self, conn: &sqlite::Connection, log: &ReviewLog) -> Result<(), DatabaseError> {
+        let mut review = Review::load_for_card(conn, log.card_id)?;
+        review.answer(log.grade, log.timestamp);
+        review.save(conn)?;
+        Ok(())
+    }
+}
+
+
+/// The scheduling state of a flash card
+#[derive(Debug)]
+pub struct Review {
+    /// Identifier of the review
+    pub id: i64,
+    /// Card the review belongs to
+    pub card_id: i64,
+    /// Easiness factor of the card, the higher the easier
+    pub ease_factor: f64,
+    /// Number of days until the card is due again after the last review
+    pub interval: i64,
+    /// Number of successive correct answers
+    pub repetitions: i64,
+    /// Unix timestamp when the card is due for the next review
+    pub due: i64,
+}
+
+
+impl Review {
+    /// The ease factor of a card which has not been reviewed yet
+    const DEFAULT_EASE_FACTOR: f64 = 2.5;
+    /// The ease factor never drops below this value
+    const MINIMUM_EASE_FACTOR: f64 = 1.3;
+
+    /// Answer the card with the given grade and compute the next due date
+    ///
+    /// # Arguments
+    ///
+    /// * `grade` - The grade given by the user
+    /// * `now` - Unix timestamp of the answer
+    pub fn answer(&mut self, grade: Grade, now: i64) {
+        let quality = grade.quality();
+        if quality < 3 {
+            self.repetitions = 0;
+            self.interval = 1;
+        } else {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f64 * self.ease_factor).round() as i64,
+            };
+            self.repetitions += 1;
+        }
+        let penalty = (5 - quality) as f64;
+        self.ease_factor += 0.1 - penalty * (0.08 + penalty * 0.02);
+        if self.ease_factor < Review::MINIMUM_EASE_FACTOR {
+            self.ease_factor = Review::MINIMUM_EASE_FACTOR;
+        }
+        self.due = now + self.interval * SECONDS_PER_DAY;
+    }
+
+    /// Load the review for a given card from the database
+    ///
+    /// If the card has not been reviewed yet, a new review for the card is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `card_id` - Identifier of the card for which to load the review
+    pub fn load_for_card(conn: &sqlite::Connection, card_id: i64) -> Result<Review, DatabaseError> {
+        let statement = format!(
+            "SELECT id, card_id, ease_factor, interval, repetitions, due FROM {} WHERE card_id = ?",
+            Review::TABLE_NAME,
+        );
+        let mut cursor = conn.prepare(statement)?.cursor();
+        cursor.bind(&[sqlite::Value::Integer(card_id)])?;
+        if let Some(row) = cursor.next()? {
+            return Review::from_row(row);
+        }
+        let mut review = Review::from_empty();
+        review.card_id = card_id;
+        Ok(review)
+    }
+
+    /// Save a Review to database (insert or update)
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    pub fn save(&mut self, conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+        let mut values = vec![
+            sqlite::Value::Integer(self.card_id),
+            sqlite::Value::Float(self.ease_factor),
+            sqlite::Value::Integer(self.interval),
+            sqlite::Value::Integer(self.repetitions),
+            sqlite::Value::Integer(self.due),
+        ];
+        if self.id > 0 {
+            values.push(sqlite::Value::Integer(self.id));
+            Review::update(conn, &values)?;
+        } else {
+            self.id = Review::insert(conn, &values)?;
+        }
+        Ok(self.id)
+    }
+}
+
+
+impl Model for Review {
+    const TABLE_NAME: &'static str = "review";
+    const STATEMENT_INSERT: &'static str =
+        "INSERT INTO review (card_id, ease_factor, interval, repetitions, due) VALUES (?, ?, ?, ?, ?)";
+    const STATEMENT_SELECT: &'static str =
+        "SELECT id, card_id, ease_factor, interval, repetitions, due FROM review WHERE id = ?";
+    const STATEMENT_SELECT_ALL: &'static str =
+        "SELECT id, card_id, ease_factor, interval, repetitions, due FROM review ORDER BY due";
+    const STATEMENT_UPDATE: &'static str =
+        "UPDATE review SET card_id = ?, ease_factor = ?, interval = ?, repetitions = ?, due = ? WHERE id = ?";
+
+    fn from_empty() -> Review {
+        Review {
+            id: 0,
+            card_id: 0,
+            ease_factor: Review::DEFAULT_EASE_FACTOR,
+            interval: 0,
+            repetitions: 0,
+            due: 0,
+        }
+    }
+
+    fn from_row(row: &[sqlite::Value]) -> Result<Review, DatabaseError> {
+        let id = match row[0].as_integer() {
+            Some(id) => id,
+            None => { return Err(DatabaseError::ValueNotInteger); },
+        };
+        let card_id = match row[1].as_integer() {
+            Some(id) => id,
+            None => { return Err(DatabaseError::ValueNotInteger); },
+        };
+        let ease_factor = match row[2].as_float() {
+            Some(ease_factor) => ease_factor,
+            None => { return Err(DatabaseError::ValueNotFloat); },
+        };
+        let interval = match row[3].as_integer() {
+            Some(interval) => interval,
+            None => { return Err(DatabaseError::ValueNotInteger); },
+        };
+        let repetitions = match row[4].as_integer() {
+            Some(repetitions) => repetitions,
+            None => { return Err(DatabaseError::ValueNotInteger); },
+        };
+        let due = match row[5].as_integer() {
+            Some(due) => due,
+            None => { return Err(DatabaseError::ValueNotInteger); },
+        };
+        Ok(Review { id, card_id, ease_factor, interval, repetitions, due })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compare two floats up to rounding errors
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_interval_progression() {
+        let mut review = Review::from_empty();
+        review.answer(Grade::Good, 1000);
+        assert_eq!((review.interval, review.repetitions), (1, 1));
+        assert_eq!(review.due, 1000 + SECONDS_PER_DAY);
+        review.answer(Grade::Good, 2000);
+        assert_eq!((review.interval, review.repetitions), (6, 2));
+        assert_eq!(review.due, 2000 + 6 * SECONDS_PER_DAY);
+        review.answer(Grade::Good, 3000);
+        assert_eq!((review.interval, review.repetitions), (15, 3));
+        // The interval grows by the ease factor before it is updated
+        review.answer(Grade::Easy, 4000);
+        assert_eq!((review.interval, review.repetitions), (38, 4));
+    }
+
+    #[test]
+    fn test_ease_factor() {
+        let mut review = Review::from_empty();
+        // Good keeps the ease factor, easy raises it and hard lowers it
+        review.answer(Grade::Good, 0);
+        assert_close(review.ease_factor, 2.5);
+        review.answer(Grade::Easy, 0);
+        assert_close(review.ease_factor, 2.6);
+        review.answer(Grade::Hard, 0);
+        assert_close(review.ease_factor, 2.46);
+        review.answer(Grade::Again, 0);
+        assert_close(review.ease_factor, 1.92);
+        // The ease factor never drops below the minimum
+        review.answer(Grade::Again, 0);
+        review.answer(Grade::Again, 0);
+        assert_close(review.ease_factor, Review::MINIMUM_EASE_FACTOR);
+    }
+
+    #[test]
+    fn test_again_resets() {
+        let mut review = Review::from_empty();
+        for _ in 0..4 {
+            review.answer(Grade::Good, 0);
+        }
+        assert!(review.interval > 6);
+        review.answer(Grade::Again, 5000);
+        assert_eq!((review.interval, review.repetitions), (1, 0));
+        assert_eq!(review.due, 5000 + SECONDS_PER_DAY);
+        // The progression starts again, but with the lowered ease factor
+        review.answer(Grade::Good, 0);
+        assert_eq!((review.interval, review.repetitions), (1, 1));
+        review.answer(Grade::Good, 0);
+        assert_eq!((review.interval, review.repetitions), (6, 2));
+    }
+
+    #[test]
+    fn test_grade_values() {
+        for grade in Grade::ALL.iter() {
+            assert_eq!(Grade::from_value(grade.value()), Some(*grade));
+        }
+        assert_eq!(Grade::from_value(0), None);
+    }
+}
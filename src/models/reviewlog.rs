@@ -0,0 +1,143 @@
+//! Model ReviewLog
+
+use crate::database::DatabaseError;
+use super::{Model, active_scheduler, timestamp};
+use super::review::Grade;
+
+
+/// A single answer given by the user when studying a flash card
+#[derive(Debug)]
+pub struct ReviewLog {
+    /// Identifier of the log entry
+    pub id: i64,
+    /// Card which has been answered
+    pub card_id: i64,
+    /// Language the card has been shown in
+    pub prompt_language_id: i64,
+    /// Language the card has been answered in
+    pub answer_language_id: i64,
+    /// Grade given by the user
+    pub grade: Grade,
+    /// Unix timestamp of the answer
+    pub timestamp: i64,
+    /// Milliseconds between showing the card and answering it
+    pub response_time: i64,
+}
+
+
+impl ReviewLog {
+    /// Create a new log entry for an answer given now
+    ///
+    /// # Arguments
+    ///
+    /// * `card_id` - Identifier of the card which has been answered
+    /// * `prompt_language_id` - Identifier of the language the card has been shown in
+    /// * `answer_language_id` - Identifier of the language the card has been answered in
+    /// * `grade` - Grade given by the user
+    /// * `response_time` - Milliseconds between showing the card and answering it
+    pub fn new(
+        card_id: i64,
+        prompt_language_id: i64,
+        answer_language_id: i64,
+        grade: Grade,
+        response_time: i64,
+    ) -> ReviewLog {
+        let mut log = ReviewLog::from_empty();
+        log.card_id = card_id;
+        log.prompt_language_id = prompt_language_id;
+        log.answer_language_id = answer_language_id;
+        log.grade = grade;
+        log.timestamp = timestamp();
+        log.response_time = response_time;
+        log
+    }
+
+    /// Record the answer: save it to the log and let the active scheduler reschedule the card
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    pub fn record(&mut self, conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+        self.save(conn)?;
+        active_scheduler(conn)?.answer(conn, self)?;
+        Ok(self.id)
+    }
+
+    /// Save a ReviewLog to database (insert or update)
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    pub fn save(&mut self, conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+        let mut values = vec![
+            sqlite::Value::Integer(self.card_id),
+            sqlite::Value::Integer(self.prompt_language_id),
+            sqlite::Value::Integer(self.answer_language_id),
+            sqlite::Value::Integer(self.grade.value()),
+            sqlite::Value::Integer(self.timestamp),
+            sqlite::Value::Integer(self.response_time),
+        ];
+        if self.id > 0 {
+            values.push(sqlite::Value::Integer(self.id));
+            ReviewLog::update(conn, &values)?;
+        } else {
+            self.id = ReviewLog::insert(conn, &values)?;
+        }
+        Ok(self.id)
+    }
+}
+
+
+impl Model for ReviewLog {
+    const TABLE_NAME: &'static str = "review_log";
+    const STATEMENT_INSERT: &'static str = "
+        INSERT INTO review_log (
+            card_id, prompt_language_id, answer_language_id, grade, timestamp, response_time
+        ) VALUES (?, ?, ?, ?, ?, ?)";
+    const STATEMENT_SELECT: &'static str = "
+        SELECT id, card_id, prompt_language_id, answer_language_id, grade, timestamp, response_time
+        FROM review_log WHERE id = ?";
+    const STATEMENT_SELECT_ALL: &'static str = "
+        SELECT id, card_id, prompt_language_id, answer_language_id, grade, timestamp, response_time
+        FROM review_log ORDER BY timestamp";
+    const STATEMENT_UPDATE: &'static str = "
+        UPDATE review_log SET
+            card_id = ?, prompt_language_id = ?, answer_language_id = ?, grade = ?, timestamp = ?,
+            response_time = ?
+        WHERE id = ?";
+
+    fn from_empty() -> ReviewLog {
+        ReviewLog {
+            id: 0,
+            card_id: 0,
+            prompt_language_id: 0,
+            answer_language_id: 0,
+            grade: Grade::Again,
+            timestamp: 0,
+            response_time: 0,
+        }
+    }
+
+    fn from_row(row: &[sqlite::Value]) -> Result<ReviewLog, DatabaseError> {
+        let mut values = Vec::new();
+        for value in row {
+            match value.as_integer() {
+                Some(value) => values.push(value),
+                None => { return Err(DatabaseError::ValueNotInteger); },
+            }
+        }
+        let grade = match Grade::from_value(values[4]) {
+            Some(grade) => grade,
+            None => { return Err(DatabaseError::ValueInvalid(values[4].to_string())); },
+        };
+        Ok(ReviewLog {
+            id: values[0],
+            card_id: values[1],
+            prompt_language_id: values[2],
+            answer_language_id: values[3],
+            grade,
+            timestamp: values[5],
+            response_time: values[6],
+        })
+    }
+}
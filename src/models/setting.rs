@@ -0,0 +1,131 @@
+//! Model Setting
+
+use crate::database::DatabaseError;
+use super::Model;
+
+
+/// A setting stored in the database, i.e. it applies to this database only
+#[derive(Debug)]
+pub struct Setting {
+    /// Identifier of the setting
+    pub id: i64,
+    /// Unique key of the setting
+    pub key: String,
+    /// Value of the setting
+    pub value: String,
+}
+
+
+impl Setting {
+    /// Key of the setting which holds the name of the active scheduler
+    pub const KEY_SCHEDULER: &'static str = "scheduler";
+    /// Key of the setting which holds the comma separated review intervals of the Leitner boxes
+    pub const KEY_LEITNER_INTERVALS: &'static str = "leitner_intervals";
+    /// Key of the setting which holds the comma separated ids of the categories to study
+    pub const KEY_FILTER_CATEGORIES: &'static str = "filter_categories";
+    /// Key of the setting which holds the comma separated ids of the active languages
+    pub const KEY_FILTER_LANGUAGES: &'static str = "filter_languages";
+    /// Key of the setting which holds the identifier of the deck and note type in Anki exports
+    pub const KEY_ANKI_ID: &'static str = "anki_id";
+
+    const STATEMENT_LOAD_BY_KEY: &'static str = "SELECT id, key, value FROM setting WHERE key = ?";
+
+    /// Get the value of a setting from the database, if it has been set
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `key` - Key of the setting to get
+    pub fn get(conn: &sqlite::Connection, key: &str) -> Result<Option<String>, DatabaseError> {
+        match Setting::load_by_key(conn, key) {
+            Ok(setting) => Ok(Some(setting.value)),
+            Err(DatabaseError::NotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Load a setting from database by key
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `key` - Key of the setting to load
+    pub fn load_by_key(conn: &sqlite::Connection, key: &str) -> Result<Setting, DatabaseError> {
+        let mut cursor = conn.prepare(Setting::STATEMENT_LOAD_BY_KEY)?.cursor();
+        cursor.bind(&[sqlite::Value::String(key.to_string())])?;
+        if let Some(row) = cursor.next()? {
+            return Setting::from_row(row);
+        }
+        Err(DatabaseError::NotFound)
+    }
+
+    /// Save a Setting to database (insert or update)
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    pub fn save(&mut self, conn: &sqlite::Connection) -> Result<i64, DatabaseError> {
+        let mut values = vec![
+            sqlite::Value::String(self.key.clone()),
+            sqlite::Value::String(self.value.clone()),
+        ];
+        if self.id > 0 {
+            values.push(sqlite::Value::Integer(self.id));
+            Setting::update(conn, &values)?;
+        } else {
+            self.id = Setting::insert(conn, &values)?;
+        }
+        Ok(self.id)
+    }
+
+    /// Set the value of a setting in the database
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `key` - Key of the setting to set
+    /// * `value` - New value of the setting
+    pub fn set(conn: &sqlite::Connection, key: &str, value: &str) -> Result<(), DatabaseError> {
+        let mut setting = match Setting::load_by_key(conn, key) {
+            Ok(setting) => setting,
+            Err(DatabaseError::NotFound) => {
+                let mut setting = Setting::from_empty();
+                setting.key = key.to_string();
+                setting
+            },
+            Err(err) => { return Err(err); },
+        };
+        setting.value = value.to_string();
+        setting.save(conn)?;
+        Ok(())
+    }
+}
+
+
+impl Model for Setting {
+    const TABLE_NAME: &'static str = "setting";
+    const STATEMENT_INSERT: &'static str = "INSERT INTO setting (key, value) VALUES (?, ?)";
+    const STATEMENT_SELECT: &'static str = "SELECT id, key, value FROM setting WHERE id = ?";
+    const STATEMENT_SELECT_ALL: &'static str = "SELECT id, key, value FROM setting ORDER BY key";
+    const STATEMENT_UPDATE: &'static str = "UPDATE setting SET key = ?, value = ? WHERE id = ?";
+
+    fn from_empty() -> Setting {
+        Setting { id: 0, key: "".to_string(), value: "".to_string() }
+    }
+
+    fn from_row(row: &[sqlite::Value]) -> Result<Setting, DatabaseError> {
+        let id = match row[0].as_integer() {
+            Some(id) => id,
+            None => { return Err(DatabaseError::ValueNotInteger); },
+        };
+        let key = match row[1].as_string() {
+            Some(key) => key.to_string(),
+            None => { return Err(DatabaseError::ValueNotString); },
+        };
+        let value = match row[2].as_string() {
+            Some(value) => value.to_string(),
+            None => { return Err(DatabaseError::ValueNotString); },
+        };
+        Ok(Setting { id, key, value })
+    }
+}
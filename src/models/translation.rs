@@ -1,13 +1,11 @@
 //! Model Translation
 
-use sqlite;
-
-use crate::database::DatabaseError;
+use crate::database::{DatabaseError, with_transaction};
 use super::Model;
 
 
 /// A flash card's translation
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Translation {
     /// Identifier of the translation
     pub id: i64,
@@ -22,8 +20,132 @@ pub struct Translation {
 }
 
 
+/// A translation found by a full-text search
+#[derive(Debug)]
+pub struct SearchResult {
+    /// The translation which matches the search
+    pub translation: Translation,
+    /// Part of the text or description around the match, see `SearchResult::HIGHLIGHT_START`
+    pub snippet: String,
+}
+
+
+impl SearchResult {
+    /// Marks the start of a matching term in the snippet
+    pub const HIGHLIGHT_START: &'static str = "\u{2}";
+    /// Marks the end of a matching term in the snippet
+    pub const HIGHLIGHT_END: &'static str = "\u{3}";
+}
+
+
 impl Translation {
-    /// Load all translations for a given card from the database
+    /// Maximum number of results of a full-text search
+    const SEARCH_LIMIT: i64 = 50;
+
+    /// Search translations by their text and description, the best matches first
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `query` - Words to search for; each word matches words starting with it
+    /// * `language_id` - Identifier of the language to search in; `None` for all languages
+    ///
+    /// # Notes
+    ///
+    /// * The search index is kept in sync by triggers on the translation table, so saving and
+    ///   deleting translations, also by deleting their card, updates it.
+    pub fn search(
+        conn: &sqlite::Connection,
+        query: &str,
+        language_id: Option<i64>,
+    ) -> Result<Vec<SearchResult>, DatabaseError> {
+        // Quote each word, so that characters with a meaning in the FTS5 query syntax are literal
+        let terms: Vec<String> = query.split_whitespace()
+            .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+            .collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let statement = format!("
+            SELECT translation.id, translation.card_id, translation.language_id, translation.text,
+                translation.description, snippet(translation_search, -1, ?, ?, '…', 8)
+            FROM translation_search JOIN {} AS translation ON translation.id = translation_search.rowid
+            WHERE translation_search MATCH ? AND (? IS NULL OR translation.language_id = ?)
+            ORDER BY rank LIMIT ?",
+            Translation::TABLE_NAME,
+        );
+        let language_id = match language_id {
+            Some(id) => sqlite::Value::Integer(id),
+            None => sqlite::Value::Null,
+        };
+        let mut cursor = conn.prepare(statement)?.cursor();
+        cursor.bind(&[
+            sqlite::Value::String(SearchResult::HIGHLIGHT_START.to_string()),
+            sqlite::Value::String(SearchResult::HIGHLIGHT_END.to_string()),
+            sqlite::Value::String(terms.join(" ")),
+            language_id.clone(),
+            language_id,
+            sqlite::Value::Integer(Translation::SEARCH_LIMIT),
+        ])?;
+        let mut results = Vec::new();
+        while let Some(row) = cursor.next()? {
+            let translation = Translation::from_row(row)?;
+            let snippet = match row[5].as_string() {
+                Some(snippet) => snippet.to_string(),
+                None => { return Err(DatabaseError::ValueNotString); },
+            };
+            results.push(SearchResult { translation, snippet });
+        }
+        Ok(results)
+    }
+
+    /// Load translations of other cards to be used as wrong answers in a multiple choice quiz
+    ///
+    /// The translations are picked at random and their texts differ from the given translation's
+    /// text and from each other.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `translation` - The correct translation for which to load the wrong ones
+    /// * `category_id` - Identifier of the category to pick translations from; 0 for any category
+    /// * `limit` - Maximum number of translations to load
+    pub fn load_distractors(
+        conn: &sqlite::Connection,
+        translation: &Translation,
+        category_id: i64,
+        limit: i64,
+    ) -> Result<Vec<Translation>, DatabaseError> {
+        let statement = format!("
+            SELECT MIN(translation.id) FROM {} AS translation
+            JOIN card ON card.id = translation.card_id
+            WHERE translation.card_id != ? AND translation.language_id = ?
+                AND translation.text != '' AND translation.text != ?
+                AND (card.category_id = ? OR ? = 0)
+            GROUP BY translation.text ORDER BY RANDOM() LIMIT ?",
+            Translation::TABLE_NAME,
+        );
+        let mut cursor = conn.prepare(statement)?.cursor();
+        cursor.bind(&[
+            sqlite::Value::Integer(translation.card_id),
+            sqlite::Value::Integer(translation.language_id),
+            sqlite::Value::String(translation.text.clone()),
+            sqlite::Value::Integer(category_id),
+            sqlite::Value::Integer(category_id),
+            sqlite::Value::Integer(limit),
+        ])?;
+        let mut distractors = Vec::new();
+        while let Some(row) = cursor.next()? {
+            let id = match row[0].as_integer() {
+                Some(id) => id,
+                None => { return Err(DatabaseError::ValueNotInteger); },
+            };
+            distractors.push(Translation::load(conn, id)?);
+        }
+        Ok(distractors)
+    }
+
+    /// Load all translations for a given card from the database in the display order of languages
     ///
     /// # Arguments
     ///
@@ -33,8 +155,12 @@ impl Translation {
         conn: &sqlite::Connection,
         card_id: i64,
     ) -> Result<Vec<Translation>, DatabaseError> {
-        let statement = format!(
-            "SELECT id FROM {} WHERE card_id = ? ORDER BY language_id", Translation::TABLE_NAME);
+        let statement = format!("
+            SELECT translation.id FROM {} AS translation
+            JOIN language ON language.id = translation.language_id
+            WHERE translation.card_id = ? ORDER BY language.position, language.id",
+            Translation::TABLE_NAME,
+        );
         let mut cursor = conn.prepare(statement)?.cursor();
         cursor.bind(&[sqlite::Value::Integer(card_id)])?;
         let mut translations = Vec::new();
@@ -72,9 +198,8 @@ impl Translation {
             sqlite::Value::Integer(card_id),
             sqlite::Value::Integer(language_id),
         ])?;
-        while let Some(row) = cursor.next()? {
+        if let Some(row) = cursor.next()? {
             translation = Translation::from_row(row)?;
-            break;
         }
         Ok(translation)
     }
@@ -91,13 +216,15 @@ impl Translation {
             sqlite::Value::String(self.text.clone()),
             sqlite::Value::String(self.description.clone()),
         ];
-        if self.id > 0 {
-            values.push(sqlite::Value::Integer(self.id));
-            Translation::update(conn, &values)?;
-        } else {
-            self.id = Translation::insert(conn, &values)?;
-        }
-        Ok(self.id)
+        with_transaction(conn, |tx| {
+            if self.id > 0 {
+                values.push(sqlite::Value::Integer(self.id));
+                Translation::update(tx, &values)?;
+            } else {
+                self.id = Translation::insert(tx, &values)?;
+            }
+            Ok(self.id)
+        })
     }
 }
 
@@ -0,0 +1,128 @@
+//! Command line options of the graphical application
+//!
+//! The options are parsed by GApplication, which also provides `--help`. A second invocation does
+//! not start another window, but forwards its command line to the running application, which
+//! applies the options to its window, see `UI::start`.
+//!
+//! `--category` is not limited to the session: like the study filter dialog, it changes the saved
+//! study filter, which keeps only the given category until the filter is changed again.
+//!
+//! # Example
+//!
+//! ```sh
+//! kaati_ako --db db.sqlite --category animals --mode typed
+//! kaati_ako --card 42
+//! ```
+
+use std::path::Path;
+
+use gio::prelude::{ApplicationCommandLineExt, ApplicationExt};
+
+use crate::session::StudyMode;
+use crate::VERSION;
+
+
+/// The options given on the command line of the graphical application
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Path to the database file to open, absolute or relative to the caller's directory
+    pub db_path: Option<String>,
+    /// Identifier of the card to show; 0 for the next card to study
+    pub card_id: i64,
+    /// Name of the category to study only; it replaces the categories of the saved study filter
+    pub category: Option<String>,
+    /// The study mode to switch to
+    pub mode: Option<StudyMode>,
+}
+
+
+impl Options {
+    /// Add the options to the application, so that they are parsed and listed by `--help`
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The GTK application
+    pub fn add(app: &gtk::Application) {
+        let modes: Vec<&str> = StudyMode::ALL.iter().map(|mode| mode.name()).collect();
+        let mode_description = format!("Study in the given mode: {}", modes.join(", "));
+        let options = [
+            ("db", glib::OptionArg::String, "Open the given database file", Some("PATH")),
+            ("card", glib::OptionArg::Int64, "Show the card with the given id", Some("ID")),
+            (
+                "category",
+                glib::OptionArg::String,
+                "Study the given category only, saved as the study filter",
+                Some("NAME"),
+            ),
+            ("mode", glib::OptionArg::String, mode_description.as_str(), Some("MODE")),
+            ("version", glib::OptionArg::None, "Show the version and exit", None),
+        ];
+        for (name, arg, description, arg_description) in options.iter() {
+            app.add_main_option(
+                name, glib::Char(0), glib::OptionFlags::NONE, *arg, description, *arg_description);
+        }
+        app.connect_handle_local_options(|_, options| {
+            // Answered by the invoked process, without contacting a running instance
+            if options.contains("version") {
+                println!("kaati_ako {}", VERSION);
+                return 0;
+            }
+            if let Err(err) = Options::from_dict(options, None) {
+                eprintln!("{}", err);
+                return 2;
+            }
+            -1
+        });
+    }
+
+    /// Get the options of a command line, which may come from another invocation
+    ///
+    /// # Arguments
+    ///
+    /// * `command_line` - The command line passed to the primary instance
+    pub fn from_command_line(command_line: &gio::ApplicationCommandLine) -> Result<Options, String> {
+        // The first argument is the program, any other argument is left over by the parser
+        if let Some(argument) = command_line.get_arguments().get(1) {
+            return Err(format!("Unknown argument: {}", argument.to_string_lossy()));
+        }
+        match command_line.get_options_dict() {
+            Some(dict) => Options::from_dict(&dict, command_line.get_cwd().as_deref()),
+            None => Ok(Options::default()),
+        }
+    }
+
+    /// Get the options from the dictionary of parsed options
+    ///
+    /// # Arguments
+    ///
+    /// * `dict` - The parsed options
+    /// * `cwd` - The caller's directory, which relative database paths are resolved against
+    fn from_dict(dict: &glib::VariantDict, cwd: Option<&Path>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let string = |name: &str| -> Option<String> {
+            dict.lookup_value(name, glib::VariantTy::new("s").ok())
+                .and_then(|value| value.get_str().map(|value| value.to_string()))
+        };
+        // A relative path is meant relative to the caller, not to the running instance
+        options.db_path = string("db").map(|db_path| match cwd {
+            Some(cwd) if Path::new(&db_path).is_relative() => {
+                cwd.join(&db_path).to_string_lossy().to_string()
+            },
+            _ => db_path,
+        });
+        if let Some(value) = dict.lookup_value("card", glib::VariantTy::new("x").ok()) {
+            options.card_id = value.get::<i64>().unwrap_or(0);
+            if options.card_id <= 0 {
+                return Err(format!("Invalid card id: {}", options.card_id));
+            }
+        }
+        options.category = string("category");
+        if let Some(name) = string("mode") {
+            match StudyMode::from_name(&name) {
+                Some(mode) => options.mode = Some(mode),
+                None => { return Err(format!("Unknown study mode: {}", name)); },
+            }
+        }
+        Ok(options)
+    }
+}
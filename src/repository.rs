@@ -0,0 +1,456 @@
+//! The repository of flash cards, i.e. the application's access to an opened database
+//!
+//! # Example
+//!
+//! ```rust
+//! use repository::Repository;
+//! let repository = Repository::open("kaati_ako.sqlite")?;
+//! let card = repository.next_card(0)?;
+//! let translations = repository.translations(card.id)?;
+//! ```
+
+use std::path::Path;
+
+use crate::anki::{AnkiError, PackageOptions, write_package};
+use crate::database::{DatabaseError, create, open, repair, schema_version, with_transaction};
+use crate::export::{Format, export_cards};
+use crate::import::{Column, Summary, import_rows};
+use crate::models::{Model, active_scheduler, count_references, timestamp};
+use crate::models::card::Card;
+use crate::models::category::Category;
+use crate::models::filter::Filter;
+use crate::models::language::Language;
+use crate::models::leitner::Leitner;
+use crate::models::reviewlog::ReviewLog;
+use crate::models::setting::Setting;
+use crate::models::translation::{SearchResult, Translation};
+use crate::statistics::Statistics;
+
+
+/// The repository of flash cards which keeps the database connection open
+pub struct Repository {
+    /// Connection to the database
+    conn: sqlite::Connection,
+    /// Path to the database file
+    path: String,
+}
+
+
+impl Repository {
+    /// Open the database at the given path, creating or migrating it if necessary
+    ///
+    /// # Arguments
+    ///
+    /// * `db_path` - Path to the database file
+    pub fn open(db_path: &str) -> Result<Repository, DatabaseError> {
+        let conn = open(db_path)?;
+        Ok(Repository { conn, path: db_path.to_string() })
+    }
+
+    /// Create an empty database at the given path, without the example cards of a first start
+    ///
+    /// # Arguments
+    ///
+    /// * `db_path` - Path to the database file
+    pub fn create(db_path: &str) -> Result<Repository, DatabaseError> {
+        let conn = create(db_path)?;
+        Ok(Repository { conn, path: db_path.to_string() })
+    }
+
+    /// Get the path to the database file
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Get the schema version of the database
+    pub fn schema_version(&self) -> Result<i64, DatabaseError> {
+        schema_version(&self.conn)
+    }
+
+    /// Get a card by id
+    ///
+    /// # Arguments
+    ///
+    /// * `card_id` - Identifier of the card to get. Set to 0 to get a new, empty card.
+    pub fn card(&self, card_id: i64) -> Result<Card, DatabaseError> {
+        if card_id == 0 {
+            Ok(Card::from_empty())
+        } else {
+            Card::load(&self.conn, card_id)
+        }
+    }
+
+    /// Get all cards ordered by id
+    pub fn cards(&self) -> Result<Vec<Card>, DatabaseError> {
+        Card::load_all(&self.conn)
+    }
+
+    /// Get a card by id or the next card to study
+    ///
+    /// # Arguments
+    ///
+    /// * `card_id` - Identifier of the card to get. Set to 0 to get the next card to study which
+    ///   passes the study filter.
+    pub fn next_card(&self, card_id: i64) -> Result<Card, DatabaseError> {
+        Card::get(&self.conn, card_id)
+    }
+
+    /// Get the id of a random card which passes the study filter
+    pub fn random_card_id(&self) -> Result<i64, DatabaseError> {
+        Card::random_id(&self.conn)
+    }
+
+    /// Save a card and its translations as one unit
+    ///
+    /// # Arguments
+    ///
+    /// * `card` - The card to save; its id is set if it is new
+    /// * `translations` - The translations of the card; their card id is set to the card's
+    pub fn save_card(
+        &self,
+        card: &mut Card,
+        translations: &mut [Translation],
+    ) -> Result<i64, DatabaseError> {
+        with_transaction(&self.conn, |tx| {
+            card.save(tx)?;
+            for translation in translations.iter_mut() {
+                translation.card_id = card.id;
+                translation.save(tx)?;
+            }
+            Ok(card.id)
+        })
+    }
+
+    /// Save a card and its translations as one unit in the category with the given name, which is
+    /// created if missing
+    ///
+    /// # Arguments
+    ///
+    /// * `card` - The card to save; its id is set if it is new and its category id is set
+    /// * `translations` - The translations of the card; their card id is set to the card's
+    /// * `category_name` - Name of the category
+    pub fn save_card_in_category(
+        &self,
+        card: &mut Card,
+        translations: &mut [Translation],
+        category_name: &str,
+    ) -> Result<i64, DatabaseError> {
+        // The category is only created if the card can be saved, as in the import
+        with_transaction(&self.conn, |tx| {
+            card.category_id = match Category::load_by_name(tx, category_name.to_string()) {
+                Ok(category) => category.id,
+                Err(DatabaseError::NotFound) => {
+                    let mut category = Category::from_empty();
+                    category.name = category_name.to_string();
+                    category.save(tx)?
+                },
+                Err(err) => { return Err(err); },
+            };
+            self.save_card(card, translations)
+        })
+    }
+
+    /// Delete a card with its translations and review history
+    ///
+    /// # Arguments
+    ///
+    /// * `card_id` - Identifier of the card to delete
+    pub fn delete_card(&self, card_id: i64) -> Result<bool, DatabaseError> {
+        Card::delete(&self.conn, card_id)
+    }
+
+    /// Get all translations of a card
+    ///
+    /// # Arguments
+    ///
+    /// * `card_id` - Identifier of the card
+    pub fn translations(&self, card_id: i64) -> Result<Vec<Translation>, DatabaseError> {
+        Translation::load_for_card(&self.conn, card_id)
+    }
+
+    /// Check if a card passes the study filter
+    ///
+    /// # Arguments
+    ///
+    /// * `card_id` - Identifier of the card
+    pub fn passes_filter(&self, card_id: i64) -> Result<bool, DatabaseError> {
+        Filter::load(&self.conn)?.passes(&self.conn, card_id)
+    }
+
+    /// Get the translations of a card in the languages which are active in the study filter
+    ///
+    /// # Arguments
+    ///
+    /// * `card_id` - Identifier of the card
+    pub fn study_translations(&self, card_id: i64) -> Result<Vec<Translation>, DatabaseError> {
+        let filter = Filter::load(&self.conn)?;
+        let mut translations = Translation::load_for_card(&self.conn, card_id)?;
+        translations.retain(|translation| filter.is_language_active(translation.language_id));
+        Ok(translations)
+    }
+
+    /// Get the translations of all cards
+    pub fn all_translations(&self) -> Result<Vec<Translation>, DatabaseError> {
+        Translation::load_all(&self.conn)
+    }
+
+    /// Get the translation of a card in a language; an empty one if there is none yet
+    ///
+    /// # Arguments
+    ///
+    /// * `card_id` - Identifier of the card
+    /// * `language_id` - Identifier of the language
+    pub fn translation(&self, card_id: i64, language_id: i64) -> Result<Translation, DatabaseError> {
+        Translation::load_for_card_language(&self.conn, card_id, language_id)
+    }
+
+    /// Search translations by their text and description, the best matches first
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Words to search for
+    /// * `language_id` - Identifier of the language to search in; `None` for all languages
+    pub fn search(
+        &self,
+        query: &str,
+        language_id: Option<i64>,
+    ) -> Result<Vec<SearchResult>, DatabaseError> {
+        Translation::search(&self.conn, query, language_id)
+    }
+
+    /// Get wrong answers for a translation in random order
+    ///
+    /// # Arguments
+    ///
+    /// * `translation` - The correct answer
+    /// * `category_id` - Identifier of the category to take them from; 0 for any category
+    /// * `limit` - Maximum number of wrong answers
+    pub fn distractors(
+        &self,
+        translation: &Translation,
+        category_id: i64,
+        limit: i64,
+    ) -> Result<Vec<Translation>, DatabaseError> {
+        Translation::load_distractors(&self.conn, translation, category_id, limit)
+    }
+
+    /// Get a category by id
+    ///
+    /// # Arguments
+    ///
+    /// * `category_id` - Identifier of the category
+    pub fn category(&self, category_id: i64) -> Result<Category, DatabaseError> {
+        Category::load(&self.conn, category_id)
+    }
+
+    /// Get a category by name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the category
+    pub fn category_by_name(&self, name: &str) -> Result<Category, DatabaseError> {
+        Category::load_by_name(&self.conn, name.to_string())
+    }
+
+    /// Get all categories ordered by name
+    pub fn categories(&self) -> Result<Vec<Category>, DatabaseError> {
+        Category::load_all(&self.conn)
+    }
+
+    /// Count the cards in a category
+    ///
+    /// # Arguments
+    ///
+    /// * `category_id` - Identifier of the category
+    pub fn count_cards(&self, category_id: i64) -> Result<i64, DatabaseError> {
+        Category::count_cards(&self.conn, category_id)
+    }
+
+    /// Save a category
+    ///
+    /// # Arguments
+    ///
+    /// * `category` - The category to save; its id is set if it is new
+    pub fn save_category(&self, category: &mut Category) -> Result<i64, DatabaseError> {
+        category.save(&self.conn)
+    }
+
+    /// Delete a category, refused if any card is still in it
+    ///
+    /// # Arguments
+    ///
+    /// * `category_id` - Identifier of the category to delete
+    pub fn delete_category(&self, category_id: i64) -> Result<bool, DatabaseError> {
+        Category::delete(&self.conn, category_id)
+    }
+
+    /// Merge a category into another one: move its cards and delete it
+    ///
+    /// # Arguments
+    ///
+    /// * `category_id` - Identifier of the category to merge and delete
+    /// * `into_id` - Identifier of the category to move the cards to
+    pub fn merge_categories(&self, category_id: i64, into_id: i64) -> Result<(), DatabaseError> {
+        Category::merge(&self.conn, category_id, into_id)
+    }
+
+    /// Get a language by id
+    ///
+    /// # Arguments
+    ///
+    /// * `language_id` - Identifier of the language
+    pub fn language(&self, language_id: i64) -> Result<Language, DatabaseError> {
+        Language::load(&self.conn, language_id)
+    }
+
+    /// Get all languages in display order
+    pub fn languages(&self) -> Result<Vec<Language>, DatabaseError> {
+        Language::load_all(&self.conn)
+    }
+
+    /// Count the translations in a language
+    ///
+    /// # Arguments
+    ///
+    /// * `language_id` - Identifier of the language
+    pub fn count_translations(&self, language_id: i64) -> Result<i64, DatabaseError> {
+        count_references(&self.conn, "translation", "language_id", language_id)
+    }
+
+    /// Save a language
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - The language to save; its id is set if it is new
+    pub fn save_language(&self, language: &mut Language) -> Result<i64, DatabaseError> {
+        language.save(&self.conn)
+    }
+
+    /// Delete a language, refused if any translation is still in it
+    ///
+    /// # Arguments
+    ///
+    /// * `language_id` - Identifier of the language to delete
+    pub fn delete_language(&self, language_id: i64) -> Result<bool, DatabaseError> {
+        Language::delete(&self.conn, language_id)
+    }
+
+    /// Set the display order of languages
+    ///
+    /// # Arguments
+    ///
+    /// * `language_ids` - Identifiers of the languages in their new order
+    pub fn reorder_languages(&self, language_ids: &[i64]) -> Result<(), DatabaseError> {
+        Language::reorder(&self.conn, language_ids)
+    }
+
+    /// Record an answer and reschedule its card as one unit
+    ///
+    /// # Arguments
+    ///
+    /// * `log` - The log entry of the answer; its id is set
+    pub fn record_answer(&self, log: &mut ReviewLog) -> Result<i64, DatabaseError> {
+        with_transaction(&self.conn, |tx| log.record(tx))
+    }
+
+    /// Export the cards which pass a filter with their category and translations
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The format to export to
+    /// * `filter` - Restricts the categories and languages to export; an empty filter exports all
+    pub fn export_cards(&self, format: Format, filter: &Filter) -> Result<String, DatabaseError> {
+        export_cards(&self.conn, format, filter)
+    }
+
+    /// Export cards as notes to an Anki package and return the number of notes
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the `.apkg` file
+    /// * `name` - Name of the deck in Anki
+    /// * `options` - The categories, directions and whether to show descriptions
+    pub fn export_anki(
+        &self,
+        path: &Path,
+        name: &str,
+        options: &PackageOptions,
+    ) -> Result<usize, AnkiError> {
+        write_package(&self.conn, path, name, options)
+    }
+
+    /// Import rows of a delimited text file as new cards
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - The rows to import, without a header
+    /// * `columns` - What each column holds
+    /// * `category_id` - Identifier of the category for rows without one
+    pub fn import_rows(
+        &self,
+        rows: &[Vec<String>],
+        columns: &[Column],
+        category_id: i64,
+    ) -> Result<Summary, DatabaseError> {
+        import_rows(&self.conn, rows, columns, category_id)
+    }
+
+    /// Get the study filter of the database
+    pub fn filter(&self) -> Result<Filter, DatabaseError> {
+        Filter::load(&self.conn)
+    }
+
+    /// Set the study filter of the database
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The new study filter
+    pub fn set_filter(&self, filter: &Filter) -> Result<(), DatabaseError> {
+        filter.save(&self.conn)
+    }
+
+    /// Get the name of the active scheduler
+    pub fn scheduler_name(&self) -> Result<&'static str, DatabaseError> {
+        Ok(active_scheduler(&self.conn)?.name())
+    }
+
+    /// Choose the scheduler by name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the scheduler
+    pub fn set_scheduler(&self, name: &str) -> Result<(), DatabaseError> {
+        Setting::set(&self.conn, Setting::KEY_SCHEDULER, name)
+    }
+
+    /// Get the Leitner system as configured in the database
+    pub fn leitner(&self) -> Result<Leitner, DatabaseError> {
+        Leitner::load(&self.conn)
+    }
+
+    /// Set the review intervals of the Leitner boxes
+    ///
+    /// # Arguments
+    ///
+    /// * `leitner` - The Leitner system with the new intervals
+    pub fn set_leitner(&self, leitner: &Leitner) -> Result<(), DatabaseError> {
+        leitner.save(&self.conn)
+    }
+
+    /// Get the statistics about the cards and the learning progress as of now
+    ///
+    /// # Arguments
+    ///
+    /// * `utc_offset` - Seconds the local time is ahead of UTC
+    /// * `days` - Number of days of the history and of the forecast
+    pub fn statistics(&self, utc_offset: i64, days: i64) -> Result<Statistics, DatabaseError> {
+        Statistics::load(&self.conn, timestamp(), utc_offset, days)
+    }
+
+    /// Clean up items which refer to items which do not exist (anymore)
+    ///
+    /// Returns the number of repaired items.
+    pub fn repair(&self) -> Result<i64, DatabaseError> {
+        repair(&self.conn)
+    }
+}
@@ -0,0 +1,151 @@
+//! The study session, i.e. how flash cards are studied while the application is running
+
+use crate::compare::Strictness;
+use crate::models::translation::Translation;
+
+
+/// How a flash card is studied
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StudyMode {
+    /// All translations are shown at once
+    Browse,
+    /// Only the prompt is shown, the answers are revealed on request
+    Reveal,
+    /// Only the prompt is shown, the first answer has to be typed in
+    Typed,
+    /// Only the prompt is shown, the first answer has to be chosen from several ones
+    Choice,
+}
+
+
+impl StudyMode {
+    /// All study modes
+    pub const ALL: [StudyMode; 4] = [
+        StudyMode::Browse, StudyMode::Reveal, StudyMode::Typed, StudyMode::Choice,
+    ];
+
+    /// Get the study mode by name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name as returned by `StudyMode::name`
+    pub fn from_name(name: &str) -> Option<StudyMode> {
+        StudyMode::ALL.iter().find(|mode| mode.name() == name).copied()
+    }
+
+    /// Get the human readable label of the study mode
+    pub fn label(&self) -> &'static str {
+        match self {
+            StudyMode::Browse => "Browse all translations",
+            StudyMode::Reveal => "Hide and reveal answers",
+            StudyMode::Typed => "Type the answer",
+            StudyMode::Choice => "Choose the answer",
+        }
+    }
+
+    /// Get the name of the study mode, e.g. to be used in settings
+    pub fn name(&self) -> &'static str {
+        match self {
+            StudyMode::Browse => "browse",
+            StudyMode::Reveal => "reveal",
+            StudyMode::Typed => "typed",
+            StudyMode::Choice => "choice",
+        }
+    }
+}
+
+
+/// The settings of the current study session
+#[derive(Clone, Debug)]
+pub struct Session {
+    /// How the flash cards are studied
+    pub mode: StudyMode,
+    /// Identifier of the language the card is shown in; 0 for the first available language
+    pub prompt_language_id: i64,
+    /// Identifiers of the languages the answers are shown in; empty for all other languages
+    pub answer_language_ids: Vec<i64>,
+    /// How strictly typed answers are compared
+    pub strictness: Strictness,
+}
+
+
+/// Implement the Default trait for a session showing all translations of a card
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            mode: StudyMode::Browse,
+            prompt_language_id: 0,
+            answer_language_ids: Vec::new(),
+            strictness: Strictness::new(),
+        }
+    }
+}
+
+
+impl Session {
+    /// Create a new session showing all translations of a card
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    /// Arrange a card's translations into the prompt and the answers of this session
+    ///
+    /// Translations in languages which are neither the prompt nor an answer language are dropped.
+    /// If the card has no translation in the prompt language, the first answer becomes the prompt.
+    ///
+    /// # Arguments
+    ///
+    /// * `translations` - All translations of a card
+    pub fn arrange(&self, translations: Vec<Translation>) -> (Option<Translation>, Vec<Translation>) {
+        let mut prompt = None;
+        let mut answers = Vec::new();
+        for translation in translations {
+            let is_prompt = match &prompt {
+                Some(_) => false,
+                None => {
+                    self.prompt_language_id == 0 ||
+                        translation.language_id == self.prompt_language_id
+                },
+            };
+            if is_prompt {
+                prompt = Some(translation);
+            } else if self.is_answer_language(translation.language_id) {
+                answers.push(translation);
+            }
+        }
+        if prompt.is_none() && !answers.is_empty() {
+            prompt = Some(answers.remove(0));
+        }
+        if !self.answer_language_ids.is_empty() {
+            answers.sort_by_key(|translation| {
+                self.answer_language_ids.iter().position(|id| *id == translation.language_id)
+            });
+        }
+        (prompt, answers)
+    }
+
+    /// Drop the prompt and answer languages which are not among the given ones, e.g. because they
+    /// were used with another database
+    ///
+    /// # Arguments
+    ///
+    /// * `language_ids` - Identifiers of the available languages
+    pub fn retain_languages(&mut self, language_ids: &[i64]) {
+        if !language_ids.contains(&self.prompt_language_id) {
+            self.prompt_language_id = 0;
+        }
+        self.answer_language_ids.retain(|id| language_ids.contains(id));
+    }
+
+    /// Check if the given language is an answer language in this session
+    ///
+    /// # Arguments
+    ///
+    /// * `language_id` - Identifier of the language to check
+    pub fn is_answer_language(&self, language_id: i64) -> bool {
+        if language_id == self.prompt_language_id {
+            return false;
+        }
+        self.answer_language_ids.is_empty() || self.answer_language_ids.contains(&language_id)
+    }
+}
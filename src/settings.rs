@@ -0,0 +1,176 @@
+//! The user's settings, i.e. what applies to the application regardless of the database
+//!
+//! The settings are stored in a key file in the XDG config directory, usually
+//! `~/.config/kaati_ako/settings.ini`.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::session::StudyMode;
+
+
+/// The settings of the user
+#[derive(Clone, Debug)]
+pub struct Settings {
+    /// Path to the database file; `None` for the default path
+    pub db_path: Option<String>,
+    /// Paths to the recently opened database files, the most recent first
+    pub recent_db_paths: Vec<String>,
+    /// Identifier of the last used prompt language; 0 for the first available language
+    pub prompt_language_id: i64,
+    /// Identifiers of the last used answer languages; empty for all other languages
+    pub answer_language_ids: Vec<i64>,
+    /// The study mode to start with
+    pub mode: StudyMode,
+    /// Font size of the card text in points
+    pub font_size: f64,
+    /// Width of the application window; 0 for half the screen width
+    pub window_width: i32,
+    /// Height of the application window; 0 for half the screen height
+    pub window_height: i32,
+    /// Whether the application window is maximized
+    pub window_maximized: bool,
+}
+
+
+/// Implement the Default trait for the settings used if there is no settings file
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            db_path: None,
+            recent_db_paths: Vec::new(),
+            prompt_language_id: 0,
+            answer_language_ids: Vec::new(),
+            mode: StudyMode::Browse,
+            font_size: Settings::DEFAULT_FONT_SIZE,
+            window_width: 0,
+            window_height: 0,
+            window_maximized: false,
+        }
+    }
+}
+
+
+impl Settings {
+    /// Font size of the card text if not configured otherwise
+    pub const DEFAULT_FONT_SIZE: f64 = 30.0;
+    /// Smallest font size of the card text
+    pub const MIN_FONT_SIZE: f64 = 8.0;
+    /// Largest font size of the card text
+    pub const MAX_FONT_SIZE: f64 = 96.0;
+    /// Number of recently opened database files to remember
+    pub const MAX_RECENT_DB_PATHS: usize = 5;
+
+    const GROUP_DATABASE: &'static str = "database";
+    const GROUP_SESSION: &'static str = "session";
+    const GROUP_APPEARANCE: &'static str = "appearance";
+    const GROUP_WINDOW: &'static str = "window";
+
+    /// Create the default settings
+    pub fn new() -> Settings {
+        Settings::default()
+    }
+
+    /// Get the path to the settings file
+    pub fn path() -> PathBuf {
+        let mut path = glib::get_user_config_dir().unwrap_or_else(|| PathBuf::from("."));
+        path.push("kaati_ako");
+        path.push("settings.ini");
+        path
+    }
+
+    /// Load the settings from the settings file
+    ///
+    /// Settings which are missing or invalid get their default value, so a missing file yields the
+    /// default settings.
+    pub fn load() -> Settings {
+        let mut settings = Settings::new();
+        let file = glib::KeyFile::new();
+        if file.load_from_file(Settings::path(), glib::KeyFileFlags::NONE).is_err() {
+            return settings;
+        }
+        if let Ok(path) = file.get_string(Settings::GROUP_DATABASE, "path") {
+            if !path.is_empty() {
+                settings.db_path = Some(path.to_string());
+            }
+        }
+        if let Ok(paths) = file.get_string(Settings::GROUP_DATABASE, "recent") {
+            settings.recent_db_paths = paths.lines()
+                .filter(|path| !path.is_empty())
+                .map(|path| path.to_string())
+                .take(Settings::MAX_RECENT_DB_PATHS)
+                .collect();
+        }
+        if let Ok(id) = file.get_int64(Settings::GROUP_SESSION, "prompt_language") {
+            settings.prompt_language_id = id;
+        }
+        if let Ok(ids) = file.get_string(Settings::GROUP_SESSION, "answer_languages") {
+            settings.answer_language_ids = ids.split(',')
+                .filter_map(|id| id.trim().parse::<i64>().ok())
+                .collect();
+        }
+        if let Some(mode) = file.get_string(Settings::GROUP_SESSION, "mode").ok().and_then(
+            |name| StudyMode::from_name(&name)) {
+            settings.mode = mode;
+        }
+        if let Ok(size) = file.get_double(Settings::GROUP_APPEARANCE, "font_size") {
+            settings.font_size = size.clamp(Settings::MIN_FONT_SIZE, Settings::MAX_FONT_SIZE);
+        }
+        if let Ok(width) = file.get_integer(Settings::GROUP_WINDOW, "width") {
+            settings.window_width = width.max(0);
+        }
+        if let Ok(height) = file.get_integer(Settings::GROUP_WINDOW, "height") {
+            settings.window_height = height.max(0);
+        }
+        if let Ok(maximized) = file.get_boolean(Settings::GROUP_WINDOW, "maximized") {
+            settings.window_maximized = maximized;
+        }
+        settings
+    }
+
+    /// Use a database file from now on and put it first in the recently opened files
+    ///
+    /// # Arguments
+    ///
+    /// * `db_path` - Path to the database file
+    pub fn use_db_path(&mut self, db_path: &str) {
+        self.db_path = Some(db_path.to_string());
+        self.add_recent_db_path(db_path);
+    }
+
+    /// Put a database file first in the recently opened files
+    ///
+    /// # Arguments
+    ///
+    /// * `db_path` - Path to the database file
+    pub fn add_recent_db_path(&mut self, db_path: &str) {
+        self.recent_db_paths.retain(|path| path != db_path);
+        self.recent_db_paths.insert(0, db_path.to_string());
+        self.recent_db_paths.truncate(Settings::MAX_RECENT_DB_PATHS);
+    }
+
+    /// Save the settings to the settings file, creating its directory if necessary
+    pub fn save(&self) -> Result<(), glib::Error> {
+        let file = glib::KeyFile::new();
+        file.set_string(
+            Settings::GROUP_DATABASE, "path", self.db_path.as_deref().unwrap_or(""));
+        // Paths may contain the list separator of key files, so they are separated by line breaks
+        file.set_string(Settings::GROUP_DATABASE, "recent", &self.recent_db_paths.join("\n"));
+        file.set_int64(Settings::GROUP_SESSION, "prompt_language", self.prompt_language_id);
+        let answer_languages: Vec<String> = self.answer_language_ids.iter()
+            .map(|id| id.to_string())
+            .collect();
+        file.set_string(Settings::GROUP_SESSION, "answer_languages", &answer_languages.join(","));
+        file.set_string(Settings::GROUP_SESSION, "mode", self.mode.name());
+        file.set_double(Settings::GROUP_APPEARANCE, "font_size", self.font_size);
+        file.set_integer(Settings::GROUP_WINDOW, "width", self.window_width);
+        file.set_integer(Settings::GROUP_WINDOW, "height", self.window_height);
+        file.set_boolean(Settings::GROUP_WINDOW, "maximized", self.window_maximized);
+        let path = Settings::path();
+        if let Some(dir) = path.parent() {
+            // A failure shows up when saving the file
+            let _ = fs::create_dir_all(dir);
+        }
+        file.save_to_file(path)
+    }
+}
@@ -0,0 +1,508 @@
+//! Statistics about the flash cards and the learning progress, computed from the database
+//!
+//! Days are counted in local time: a timestamp belongs to the day `(timestamp + utc_offset) /
+//! SECONDS_PER_DAY`. The current time is passed in instead of being read from the clock, so each
+//! query returns the same result for the same database.
+//!
+//! # Example
+//!
+//! ```rust
+//! use statistics::Statistics;
+//! let statistics = Statistics::load(&conn, timestamp(), utc_offset, 30)?;
+//! println!("{} days in a row", statistics.streak);
+//! ```
+
+use crate::database::DatabaseError;
+use crate::models::{Model, SECONDS_PER_DAY, active_scheduler};
+use crate::models::leitner::{Leitner, LeitnerCard};
+use crate::models::review::{Grade, Review};
+
+
+/// A number of items which belong to a named item, e.g. the cards of a category
+#[derive(Clone, Debug, PartialEq)]
+pub struct Total {
+    /// Name of the item, e.g. of the category
+    pub name: String,
+    /// Number of items which belong to it
+    pub count: i64,
+}
+
+
+/// The answers given in a period and how many of them have been recalled
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Retention {
+    /// Number of answers
+    pub answers: i64,
+    /// Number of answers which have not been graded as again
+    pub recalled: i64,
+}
+
+
+impl Retention {
+    /// Get the share of recalled answers from 0 to 1; `None` if there are no answers
+    pub fn rate(&self) -> Option<f64> {
+        if self.answers > 0 {
+            Some(self.recalled as f64 / self.answers as f64)
+        } else {
+            None
+        }
+    }
+}
+
+
+/// All statistics shown by the statistics window
+#[derive(Clone, Debug)]
+pub struct Statistics {
+    /// Number of cards per category, ordered by category name
+    pub categories: Vec<Total>,
+    /// Number of translations per language, in the order of the languages
+    pub languages: Vec<Total>,
+    /// Number of answers per day of the last days, the oldest first and today last
+    pub reviews_per_day: Vec<i64>,
+    /// The answers of the last days
+    pub retention: Retention,
+    /// Number of cards which become due per day of the next days, today first with overdue cards
+    pub due_forecast: Vec<i64>,
+    /// Number of cards which have never been scheduled
+    pub new_cards: i64,
+    /// Number of consecutive days with answers until today, or yesterday if not answered today
+    pub streak: i64,
+}
+
+
+impl Statistics {
+    /// Load all statistics from the database
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `now` - Unix timestamp of the current time
+    /// * `utc_offset` - Seconds the local time is ahead of UTC
+    /// * `days` - Number of days of the history and of the forecast
+    pub fn load(
+        conn: &sqlite::Connection,
+        now: i64,
+        utc_offset: i64,
+        days: i64,
+    ) -> Result<Statistics, DatabaseError> {
+        let today = day(now, utc_offset);
+        let table_name = schedule_table_name(conn)?;
+        Ok(Statistics {
+            categories: cards_per_category(conn)?,
+            languages: translations_per_language(conn)?,
+            reviews_per_day: reviews_per_day(conn, today, utc_offset, days)?,
+            retention: retention(conn, start_of_day(today - days + 1, utc_offset))?,
+            due_forecast: due_forecast(conn, table_name, today, utc_offset, days)?,
+            new_cards: count_new_cards(conn, table_name)?,
+            streak: streak(conn, today, utc_offset)?,
+        })
+    }
+}
+
+
+/// Get the local day of a timestamp as number of days since the Unix epoch
+///
+/// # Arguments
+///
+/// * `timestamp` - Unix timestamp
+/// * `utc_offset` - Seconds the local time is ahead of UTC
+pub fn day(timestamp: i64, utc_offset: i64) -> i64 {
+    (timestamp + utc_offset).div_euclid(SECONDS_PER_DAY)
+}
+
+
+/// Get the Unix timestamp when a local day starts
+///
+/// # Arguments
+///
+/// * `day` - Number of days since the Unix epoch
+/// * `utc_offset` - Seconds the local time is ahead of UTC
+pub fn start_of_day(day: i64, utc_offset: i64) -> i64 {
+    day * SECONDS_PER_DAY - utc_offset
+}
+
+
+/// Get the name of the table which holds the due dates of the active scheduler
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+pub fn schedule_table_name(conn: &sqlite::Connection) -> Result<&'static str, DatabaseError> {
+    match active_scheduler(conn)?.name() {
+        Leitner::NAME => Ok(LeitnerCard::TABLE_NAME),
+        _ => Ok(Review::TABLE_NAME),
+    }
+}
+
+
+/// Run a query which returns a name and a count per row
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `statement` - The query
+fn query_totals(conn: &sqlite::Connection, statement: &str) -> Result<Vec<Total>, DatabaseError> {
+    let mut cursor = conn.prepare(statement)?.cursor();
+    let mut totals = Vec::new();
+    while let Some(row) = cursor.next()? {
+        totals.push(Total {
+            name: row[0].as_string().ok_or(DatabaseError::ValueNotString)?.to_string(),
+            count: row[1].as_integer().ok_or(DatabaseError::ValueNotInteger)?,
+        });
+    }
+    Ok(totals)
+}
+
+
+/// Run a query which returns a day and a count per row, and sum the counts per day of a period
+///
+/// Days before the period are added to its first day and days after it are left out.
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `statement` - The query
+/// * `values` - The values to bind to the query
+/// * `first_day` - The first day of the period
+/// * `days` - Number of days of the period
+fn query_days(
+    conn: &sqlite::Connection,
+    statement: &str,
+    values: &[sqlite::Value],
+    first_day: i64,
+    days: i64,
+) -> Result<Vec<i64>, DatabaseError> {
+    let mut counts = vec![0; days.max(0) as usize];
+    let mut cursor = conn.prepare(statement)?.cursor();
+    cursor.bind(values)?;
+    while let Some(row) = cursor.next()? {
+        let day = row[0].as_integer().ok_or(DatabaseError::ValueNotInteger)?;
+        let count = row[1].as_integer().ok_or(DatabaseError::ValueNotInteger)?;
+        let idx = (day - first_day).max(0) as usize;
+        if let Some(total) = counts.get_mut(idx) {
+            *total += count;
+        }
+    }
+    Ok(counts)
+}
+
+
+/// Count the cards per category, including categories without cards
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+pub fn cards_per_category(conn: &sqlite::Connection) -> Result<Vec<Total>, DatabaseError> {
+    query_totals(conn, "
+        SELECT category.name, COUNT(card.id) FROM category
+        LEFT JOIN card ON card.category_id = category.id
+        GROUP BY category.id ORDER BY category.name")
+}
+
+
+/// Count the translations per language, including languages without translations
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+pub fn translations_per_language(conn: &sqlite::Connection) -> Result<Vec<Total>, DatabaseError> {
+    query_totals(conn, "
+        SELECT language.name, COUNT(translation.id) FROM language
+        LEFT JOIN translation ON translation.language_id = language.id
+        GROUP BY language.id ORDER BY language.position, language.id")
+}
+
+
+/// Count the answers per day of the last days
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `today` - The current local day
+/// * `utc_offset` - Seconds the local time is ahead of UTC
+/// * `days` - Number of days, including today
+pub fn reviews_per_day(
+    conn: &sqlite::Connection,
+    today: i64,
+    utc_offset: i64,
+    days: i64,
+) -> Result<Vec<i64>, DatabaseError> {
+    let first_day = today - days + 1;
+    let statement = format!("
+        SELECT (timestamp + ?) / {} AS day, COUNT(*) FROM review_log
+        WHERE timestamp >= ? GROUP BY day", SECONDS_PER_DAY);
+    let values = [
+        sqlite::Value::Integer(utc_offset),
+        sqlite::Value::Integer(start_of_day(first_day, utc_offset)),
+    ];
+    query_days(conn, &statement, &values, first_day, days)
+}
+
+
+/// Count the answers since a point in time and how many of them have been recalled
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `since` - Unix timestamp of the first answer to count
+pub fn retention(conn: &sqlite::Connection, since: i64) -> Result<Retention, DatabaseError> {
+    let mut cursor = conn.prepare("
+        SELECT COUNT(*), COALESCE(SUM(grade != ?), 0) FROM review_log WHERE timestamp >= ?")?
+        .cursor();
+    cursor.bind(&[sqlite::Value::Integer(Grade::Again.value()), sqlite::Value::Integer(since)])?;
+    match cursor.next()? {
+        Some(row) => Ok(Retention {
+            answers: row[0].as_integer().ok_or(DatabaseError::ValueNotInteger)?,
+            recalled: row[1].as_integer().ok_or(DatabaseError::ValueNotInteger)?,
+        }),
+        None => Ok(Retention::default()),
+    }
+}
+
+
+/// Count the cards which become due per day of the next days
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `table_name` - Name of the table which holds the due dates, see `schedule_table_name`
+/// * `today` - The current local day, which also counts the overdue cards
+/// * `utc_offset` - Seconds the local time is ahead of UTC
+/// * `days` - Number of days, including today
+pub fn due_forecast(
+    conn: &sqlite::Connection,
+    table_name: &str,
+    today: i64,
+    utc_offset: i64,
+    days: i64,
+) -> Result<Vec<i64>, DatabaseError> {
+    let statement = format!("
+        SELECT (due + ?) / {} AS day, COUNT(*) FROM {} WHERE due < ? GROUP BY day",
+        SECONDS_PER_DAY, table_name);
+    let values = [
+        sqlite::Value::Integer(utc_offset),
+        sqlite::Value::Integer(start_of_day(today + days, utc_offset)),
+    ];
+    query_days(conn, &statement, &values, today, days)
+}
+
+
+/// Count the cards which have never been scheduled, i.e. never been answered
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `table_name` - Name of the table which holds the due dates, see `schedule_table_name`
+pub fn count_new_cards(conn: &sqlite::Connection, table_name: &str) -> Result<i64, DatabaseError> {
+    let statement = format!(
+        "SELECT COUNT(*) FROM card WHERE id NOT IN (SELECT card_id FROM {})", table_name);
+    let mut cursor = conn.prepare(statement)?.cursor();
+    match cursor.next()? {
+        Some(row) => row[0].as_integer().ok_or(DatabaseError::ValueNotInteger),
+        None => Ok(0),
+    }
+}
+
+
+/// Count the consecutive days with at least one answer
+///
+/// The streak is not broken until today is over, so it counts until yesterday if there is no
+/// answer today yet.
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database
+/// * `today` - The current local day
+/// * `utc_offset` - Seconds the local time is ahead of UTC
+pub fn streak(
+    conn: &sqlite::Connection,
+    today: i64,
+    utc_offset: i64,
+) -> Result<i64, DatabaseError> {
+    let statement = format!("
+        SELECT DISTINCT (timestamp + ?) / {} AS day FROM review_log
+        WHERE timestamp < ? ORDER BY day DESC", SECONDS_PER_DAY);
+    let mut cursor = conn.prepare(statement)?.cursor();
+    cursor.bind(&[
+        sqlite::Value::Integer(utc_offset),
+        sqlite::Value::Integer(start_of_day(today + 1, utc_offset)),
+    ])?;
+    let mut expected = today;
+    let mut streak = 0;
+    while let Some(row) = cursor.next()? {
+        let day = row[0].as_integer().ok_or(DatabaseError::ValueNotInteger)?;
+        if streak == 0 && day == today - 1 {
+            expected = day;
+        }
+        if day != expected {
+            break;
+        }
+        streak += 1;
+        expected -= 1;
+    }
+    Ok(streak)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrate;
+
+    /// Local time of the tests: 13 hours ahead of UTC, as in Tonga
+    const OFFSET: i64 = 13 * 3600;
+
+    /// Open an empty database with one category and four cards
+    fn open_database() -> sqlite::Connection {
+        let conn = sqlite::open(":memory:").unwrap();
+        migrate(&conn).unwrap();
+        conn.execute("
+            INSERT INTO category (name) VALUES ('default');
+            INSERT INTO card (category_id) VALUES (1);
+            INSERT INTO card (category_id) VALUES (1);
+            INSERT INTO card (category_id) VALUES (1);
+            INSERT INTO card (category_id) VALUES (1);
+            ").unwrap();
+        conn
+    }
+
+    /// Log an answer to card 1
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `grade` - The grade of the answer
+    /// * `timestamp` - Unix timestamp of the answer
+    fn log_answer(conn: &sqlite::Connection, grade: Grade, timestamp: i64) {
+        conn.execute(format!("
+            INSERT INTO review_log (
+                card_id, prompt_language_id, answer_language_id, grade, timestamp, response_time
+            ) VALUES (1, 1, 2, {}, {}, 1000)", grade.value(), timestamp)).unwrap();
+    }
+
+    /// Schedule a card with SM-2
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - Connection to the database
+    /// * `card_id` - Identifier of the card
+    /// * `due` - Unix timestamp when the card is due
+    fn schedule(conn: &sqlite::Connection, card_id: i64, due: i64) {
+        conn.execute(format!("
+            INSERT INTO review (card_id, ease_factor, interval, repetitions, due)
+            VALUES ({}, 2.5, 1, 1, {})", card_id, due)).unwrap();
+    }
+
+    #[test]
+    fn test_day_with_offset() {
+        // 11:00 UTC is already the next day in local time
+        assert_eq!(day(10 * SECONDS_PER_DAY + 11 * 3600, OFFSET), 11);
+        assert_eq!(day(10 * SECONDS_PER_DAY + 10 * 3600, OFFSET), 10);
+        assert_eq!(day(10 * SECONDS_PER_DAY + 10 * 3600, 0), 10);
+        assert_eq!(start_of_day(11, OFFSET), 10 * SECONDS_PER_DAY + 11 * 3600);
+        assert_eq!(day(-1, 0), -1);
+    }
+
+    #[test]
+    fn test_streak_includes_today() {
+        let conn = open_database();
+        let today = 100;
+        let noon = start_of_day(today, OFFSET) + SECONDS_PER_DAY / 2;
+        for days_ago in [0, 0, 1, 2, 4].iter() {
+            log_answer(&conn, Grade::Good, noon - days_ago * SECONDS_PER_DAY);
+        }
+        assert_eq!(streak(&conn, today, OFFSET).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_streak_counts_from_yesterday() {
+        let conn = open_database();
+        let today = 100;
+        let noon = start_of_day(today, OFFSET) + SECONDS_PER_DAY / 2;
+        assert_eq!(streak(&conn, today, OFFSET).unwrap(), 0);
+        log_answer(&conn, Grade::Good, noon - 2 * SECONDS_PER_DAY);
+        assert_eq!(streak(&conn, today, OFFSET).unwrap(), 0);
+        log_answer(&conn, Grade::Again, noon - SECONDS_PER_DAY);
+        assert_eq!(streak(&conn, today, OFFSET).unwrap(), 2);
+        // Answers after today, e.g. by a wrong clock, do not count
+        log_answer(&conn, Grade::Good, noon + SECONDS_PER_DAY);
+        assert_eq!(streak(&conn, today, OFFSET).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_reviews_per_day_buckets_local_days() {
+        let conn = open_database();
+        // Both answers are on the same UTC day, but on two local days
+        log_answer(&conn, Grade::Good, 10 * SECONDS_PER_DAY + 10 * 3600);
+        log_answer(&conn, Grade::Good, 10 * SECONDS_PER_DAY + 11 * 3600);
+        log_answer(&conn, Grade::Good, 10 * SECONDS_PER_DAY + 12 * 3600);
+        // Before the period
+        log_answer(&conn, Grade::Good, 5 * SECONDS_PER_DAY);
+        assert_eq!(reviews_per_day(&conn, 11, OFFSET, 3).unwrap(), vec![0, 1, 2]);
+        assert_eq!(reviews_per_day(&conn, 10, 0, 2).unwrap(), vec![0, 3]);
+        assert_eq!(reviews_per_day(&conn, 11, OFFSET, 0).unwrap(), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn test_due_forecast_folds_overdue_cards_into_today() {
+        let conn = open_database();
+        let today = 100;
+        let start = start_of_day(today, OFFSET);
+        schedule(&conn, 1, start - 5 * SECONDS_PER_DAY);
+        schedule(&conn, 2, start + 3600);
+        schedule(&conn, 3, start + SECONDS_PER_DAY + 3600);
+        schedule(&conn, 4, start + 3 * SECONDS_PER_DAY);
+        let forecast = due_forecast(&conn, Review::TABLE_NAME, today, OFFSET, 3).unwrap();
+        assert_eq!(forecast, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_count_new_cards() {
+        let conn = open_database();
+        assert_eq!(count_new_cards(&conn, Review::TABLE_NAME).unwrap(), 4);
+        schedule(&conn, 1, 0);
+        assert_eq!(count_new_cards(&conn, Review::TABLE_NAME).unwrap(), 3);
+        assert_eq!(count_new_cards(&conn, LeitnerCard::TABLE_NAME).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_retention() {
+        let conn = open_database();
+        let none = retention(&conn, 0).unwrap();
+        assert_eq!(none, Retention { answers: 0, recalled: 0 });
+        assert_eq!(none.rate(), None);
+        assert_eq!(Retention::default().rate(), None);
+
+        log_answer(&conn, Grade::Again, 100);
+        log_answer(&conn, Grade::Again, 200);
+        log_answer(&conn, Grade::Hard, 300);
+        log_answer(&conn, Grade::Easy, 400);
+        let all = retention(&conn, 0).unwrap();
+        assert_eq!(all, Retention { answers: 4, recalled: 2 });
+        assert_eq!(all.rate(), Some(0.5));
+        assert_eq!(retention(&conn, 200).unwrap().rate(), Some(2.0 / 3.0));
+        assert_eq!(retention(&conn, 500).unwrap().rate(), None);
+    }
+
+    #[test]
+    fn test_totals() {
+        let conn = open_database();
+        conn.execute("
+            INSERT INTO category (name) VALUES ('animals');
+            INSERT INTO language (code, name, position) VALUES ('en', 'English', 2);
+            INSERT INTO language (code, name, position) VALUES ('to', 'Tongan', 1);
+            INSERT INTO translation (card_id, language_id, text, description)
+                VALUES (1, 1, 'card', '');
+            ").unwrap();
+        let categories = cards_per_category(&conn).unwrap();
+        assert_eq!(categories, vec![
+            Total { name: "animals".to_string(), count: 0 },
+            Total { name: "default".to_string(), count: 4 },
+        ]);
+        let languages = translations_per_language(&conn).unwrap();
+        assert_eq!(languages, vec![
+            Total { name: "Tongan".to_string(), count: 0 },
+            Total { name: "English".to_string(), count: 1 },
+        ]);
+    }
+}
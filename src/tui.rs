@@ -0,0 +1,288 @@
+//! Terminal user interface to study flash cards without a display, e.g. over SSH
+//!
+//! It mirrors the main window: the prompt of the next card is shown, the answers are revealed by
+//! `space`, `n` shows the next card and `a`, `h`, `g` or `e` grade the answer. Cards are selected
+//! and answers are recorded as in the main window, so both share the progress of a database.
+//!
+//! # Example
+//!
+//! ```rust
+//! use tui::Tui;
+//! let reviewed = Tui::run(Repository::open("kaati_ako.sqlite")?, session)?;
+//! ```
+
+use std::error::Error;
+use std::io::{self, Write};
+use std::time::Instant;
+
+use crossterm::{cursor, event, execute, queue, style, terminal};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+use crate::database::DatabaseError;
+use crate::models::card::Card;
+use crate::models::language::Language;
+use crate::models::review::Grade;
+use crate::models::reviewlog::ReviewLog;
+use crate::models::translation::Translation;
+use crate::repository::Repository;
+use crate::session::{Session, StudyMode};
+
+
+/// The keys to grade an answer, as the accelerators of the main window
+const GRADE_KEYS: [(char, Grade); 4] =
+    [('a', Grade::Again), ('h', Grade::Hard), ('g', Grade::Good), ('e', Grade::Easy)];
+
+
+/// The state of the terminal user interface
+pub struct Tui {
+    /// The repository of flash cards
+    repository: Repository,
+    /// The study session which arranges the translations into prompt and answers
+    session: Session,
+    /// All languages of the database
+    languages: Vec<Language>,
+    /// The shown card; `None` if there is no card to study
+    card: Option<Card>,
+    /// Name of the shown card's category
+    category: String,
+    /// The translation shown as prompt
+    prompt: Option<Translation>,
+    /// The translations shown as answers once revealed
+    answers: Vec<Translation>,
+    /// Whether the answers are shown
+    revealed: bool,
+    /// When the card has been shown, to measure the response time
+    shown_at: Instant,
+    /// Number of answers recorded in this session
+    reviewed: usize,
+    /// Message shown below the card, e.g. the last grade
+    message: String,
+}
+
+
+impl Tui {
+    /// Create the user interface and load the next card
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `session` - The study session; typed and chosen answers are revealed instead
+    fn new(repository: Repository, session: Session) -> Result<Tui, DatabaseError> {
+        let languages = repository.languages()?;
+        let mut tui = Tui {
+            repository,
+            session,
+            languages,
+            card: None,
+            category: String::new(),
+            prompt: None,
+            answers: Vec::new(),
+            revealed: false,
+            shown_at: Instant::now(),
+            reviewed: 0,
+            message: String::new(),
+        };
+        tui.load_card(0)?;
+        Ok(tui)
+    }
+
+    /// Load a card and arrange its translations into the prompt and the answers
+    ///
+    /// # Arguments
+    ///
+    /// * `card_id` - Identifier of the card to load. Set to 0 to load the next card to study.
+    fn load_card(&mut self, card_id: i64) -> Result<(), DatabaseError> {
+        let card = match self.repository.next_card(card_id) {
+            Ok(card) => card,
+            Err(DatabaseError::NotFound) => {
+                self.card = None;
+                self.prompt = None;
+                self.answers.clear();
+                self.message = "There is no card to study, check the study filter.".to_string();
+                return Ok(());
+            },
+            Err(err) => { return Err(err); },
+        };
+        let translations = self.repository.study_translations(card.id)?;
+        let (prompt, answers) = self.session.arrange(translations);
+        self.category = self.repository.category(card.category_id)?.name;
+        self.card = Some(card);
+        self.prompt = prompt;
+        self.answers = answers;
+        self.revealed = self.session.mode == StudyMode::Browse;
+        self.shown_at = Instant::now();
+        Ok(())
+    }
+
+    /// Record the answer to the shown card and load the next card
+    ///
+    /// # Arguments
+    ///
+    /// * `grade` - The grade given by the user
+    fn grade(&mut self, grade: Grade) -> Result<(), DatabaseError> {
+        let (card_id, prompt) = match (&self.card, &self.prompt) {
+            (Some(card), Some(prompt)) => (card.id, prompt.language_id),
+            _ => { return Ok(()); },
+        };
+        // As in the main window, the first answer is the one which has been studied
+        let answer = self.answers.first().map_or(prompt, |answer| answer.language_id);
+        let response_time = self.shown_at.elapsed().as_millis() as i64;
+        let mut log = ReviewLog::new(card_id, prompt, answer, grade, response_time);
+        self.repository.record_answer(&mut log)?;
+        self.reviewed += 1;
+        self.message = format!(
+            "Graded card {} as {}, {} reviewed.", card_id, grade.name(), self.reviewed);
+        self.load_card(0)
+    }
+
+    /// Get the name of a language
+    ///
+    /// # Arguments
+    ///
+    /// * `language_id` - Identifier of the language
+    fn language_name(&self, language_id: i64) -> &str {
+        self.languages.iter()
+            .find(|language| language.id == language_id)
+            .map_or("", |language| language.name.as_str())
+    }
+
+    /// Get the lines to show for the current state, each with whether it is emphasised
+    fn lines(&self) -> Vec<(String, bool)> {
+        let mut lines = vec![(format!("Kaati Ako - {}", self.repository.path()), true)];
+        if let Some(card) = &self.card {
+            lines.push((format!("Card {} in category {}", card.id, self.category), false));
+        }
+        lines.push((String::new(), false));
+        let translations = self.prompt.iter()
+            .chain(self.answers.iter().filter(|_| self.revealed));
+        for translation in translations {
+            lines.push((
+                format!("{}: {}", self.language_name(translation.language_id), translation.text),
+                true,
+            ));
+            // The description may give the answer away, so it is hidden until revealed
+            if self.revealed && !translation.description.is_empty() {
+                for line in translation.description.lines() {
+                    lines.push((format!("    {}", line), false));
+                }
+            }
+            lines.push((String::new(), false));
+        }
+        if self.card.is_some() && !self.revealed {
+            lines.push(("Press space to reveal the answers.".to_string(), false));
+            lines.push((String::new(), false));
+        }
+        lines.push((self.message.clone(), false));
+        lines
+    }
+
+    /// Draw the current state to the terminal
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The terminal's output
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let width = width.max(1) as usize;
+        queue!(out, terminal::Clear(terminal::ClearType::All))?;
+        let mut row: u16 = 0;
+        for (line, is_emphasised) in self.lines() {
+            // Wrap long lines by hand, as each line is placed at the start of its row
+            let chars: Vec<char> = line.chars().collect();
+            let chunks: Vec<String> = if chars.is_empty() {
+                vec![String::new()]
+            } else {
+                chars.chunks(width).map(|chunk| chunk.iter().collect()).collect()
+            };
+            for chunk in chunks {
+                if row + 1 >= height {
+                    break;
+                }
+                queue!(out, cursor::MoveTo(0, row))?;
+                if is_emphasised {
+                    queue!(out, style::SetAttribute(style::Attribute::Bold))?;
+                }
+                queue!(out, style::Print(chunk), style::SetAttribute(style::Attribute::Reset))?;
+                row += 1;
+            }
+        }
+        let help = "space reveal  n next  a again  h hard  g good  e easy  q quit";
+        queue!(
+            out,
+            cursor::MoveTo(0, height.saturating_sub(1)),
+            style::SetAttribute(style::Attribute::Reverse),
+            style::Print(help.chars().take(width).collect::<String>()),
+            style::SetAttribute(style::Attribute::Reset),
+        )?;
+        out.flush()
+    }
+
+    /// Handle a pressed key; returns whether to quit
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The pressed key
+    fn handle_key(&mut self, key: KeyEvent) -> Result<bool, DatabaseError> {
+        let is_control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('c') if is_control => { return Ok(true); },
+            KeyCode::Char('q') | KeyCode::Esc => { return Ok(true); },
+            KeyCode::Char(' ') | KeyCode::Enter => self.revealed = true,
+            KeyCode::Char('n') => {
+                self.message.clear();
+                self.load_card(0)?;
+            },
+            KeyCode::Char(character) => {
+                if let Some((_, grade)) = GRADE_KEYS.iter().find(|(key, _)| *key == character) {
+                    // A card can only be graded once its answers have been seen
+                    if self.revealed {
+                        self.grade(*grade)?;
+                    } else if self.card.is_some() {
+                        self.message = "Press space to reveal the answers first.".to_string();
+                    }
+                }
+            },
+            _ => {},
+        }
+        Ok(false)
+    }
+
+    /// Read keys and draw the state until the user quits
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The terminal's output
+    fn event_loop(&mut self, out: &mut impl Write) -> Result<(), Box<dyn Error>> {
+        loop {
+            self.draw(out)?;
+            if let Event::Key(key) = event::read()? {
+                let result = self.handle_key(key);
+                match result {
+                    Ok(true) => { return Ok(()); },
+                    Ok(false) => {},
+                    // Show the error instead of leaving, e.g. a failed grade can be given again
+                    Err(err) => self.message = err.to_string(),
+                }
+            }
+        }
+    }
+
+    /// Run the user interface in the terminal until the user quits and return the number of
+    /// recorded answers
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `session` - The study session; typed and chosen answers are revealed instead
+    pub fn run(repository: Repository, session: Session) -> Result<usize, Box<dyn Error>> {
+        let mut tui = Tui::new(repository, session)?;
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+        let result = tui.event_loop(&mut out);
+        // Restore the terminal in any case, otherwise the shell would be unusable
+        execute!(out, cursor::Show, terminal::LeaveAlternateScreen).ok();
+        terminal::disable_raw_mode().ok();
+        result.map(|_| tui.reviewed)
+    }
+}
@@ -3,21 +3,62 @@
 mod dialogs;
 mod widgets;
 
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
 use gdk::Screen;
-use gio::ActionMapExt;
-use gtk::{ContainerExt, GtkApplicationExt, GtkWindowExt, WidgetExt};
+use gio::{ActionExt, ActionMapExt};
+use glib::{Cast, ObjectExt, ToVariant};
+use gtk::prelude::GtkWindowExtManual;
+use gtk::{
+    BinExt,
+    ContainerExt,
+    GtkApplicationExt,
+    GtkWindowExt,
+    HeaderBarExt,
+    Inhibit,
+    WidgetExt,
+};
 
+use crate::database::{get_path, DatabaseError};
+use crate::models::leitner::Leitner;
+use crate::models::review::{Grade, Sm2};
+use crate::options::Options;
+use crate::repository::Repository;
+use crate::session::{Session, StudyMode};
+use crate::settings::Settings;
+use dialogs::error::Error as ErrorDialog;
 use dialogs::about::About;
+use dialogs::ankiexport::AnkiExport;
+use dialogs::ankiimport::AnkiImport;
+use dialogs::cardbrowser::CardBrowser;
 use dialogs::cardeditor::CardEditor;
+use dialogs::categorymanager::CategoryManager;
+use dialogs::deckfile::DeckFile;
 use dialogs::deletecard::DeleteCard;
+use dialogs::exportcards::ExportCards;
+use dialogs::importwizard::ImportWizard;
+use dialogs::info::Info as InfoDialog;
+use dialogs::languagemanager::LanguageManager;
+use dialogs::preferences::Preferences;
+use dialogs::statistics::Statistics;
+use dialogs::studyfilter::StudyFilter;
+use dialogs::studysession::StudySession;
 use widgets::cardnotebook::CardNotebook;
+use widgets::choice::Choice;
 use widgets::content::Content;
+use widgets::search::Search;
 
 
 /// The name of content widget which contains the flash card
 const WIDGET_NAME_CONTENT: &str = "content";
 /// The name of the flash card's widget
 const WIDGET_NAME_CARD: &str = "card";
+/// The name of the search entry in the header bar
+const WIDGET_NAME_SEARCH: &str = "search";
+/// The name of the label in the action bar which shows the study filter
+const WIDGET_NAME_FILTER: &str = "filter";
 
 
 /// The application's user interface
@@ -30,28 +71,101 @@ impl UI {
     /// # Arguments
     ///
     /// * `app` - The GTK application
+    /// * `mode` - The study mode of the session
     ///
     /// # Supported accelerators
     ///
     /// * `F1` - Show the about dialog
     /// * `n` - Show the next card
+    /// * `space` - Show the answers of the current card
+    /// * `a`, `h`, `g`, `e` - Grade the answer to the current card as again, hard, good or easy
+    /// * `1` to `6` - Choose the answer with that number in a multiple choice quiz
+    ///
+    /// The single key accelerators need the `Alt` modifier when answers are typed.
     /// * `Ctrl-A` - Show the dialog to add a new card
+    /// * `Ctrl-B` - Show the dialog to browse all cards
     /// * `Ctrl-D` - Show the confirm dialog to delete the current card
     /// * `Ctrl-E` - Show the dialog to edit the current card
+    /// * `Ctrl-F` - Search cards by their translations
+    /// * `Ctrl-I` - Show the wizard to import cards
+    /// * `Ctrl-N` - Show the dialog to create a new deck
+    /// * `Ctrl-O` - Show the dialog to open a deck
+    /// * `Ctrl-P` - Show the preferences dialog
     /// * `Ctrl-Q` - Quit the application
-    fn add_accelerators(app: &gtk::Application) {
+    /// * `Ctrl-S` - Show the dialog to configure the study session
+    fn add_accelerators(app: &gtk::Application, mode: StudyMode) {
         app.set_accels_for_action("app.about", &["F1"]);
-        app.set_accels_for_action("app.next_card", &["n"]);
+        UI::add_study_accelerators(app, mode);
         // `Primary` is a platform-agnostic accelerator modifier.
         // On Windows and Linux, `Primary` maps to the `Ctrl` key,
         // and on macOS it maps to the `command` key.
         app.set_accels_for_action(
             "app.add_card", &["<Primary>A"]);
+        app.set_accels_for_action("app.browse_cards", &["<Primary>B"]);
         app.set_accels_for_action(
             "app.delete_card", &["<Primary>D"]);
         app.set_accels_for_action(
             "app.edit_card", &["<Primary>E"]);
+        app.set_accels_for_action("app.import_cards", &["<Primary>I"]);
+        app.set_accels_for_action("app.new_deck", &["<Primary>N"]);
+        app.set_accels_for_action("app.open_deck", &["<Primary>O"]);
+        app.set_accels_for_action("app.preferences", &["<Primary>P"]);
         app.set_accels_for_action("app.quit", &["<Primary>Q"]);
+        app.set_accels_for_action("app.search", &["<Primary>F"]);
+        app.set_accels_for_action("app.study_session", &["<Primary>S"]);
+    }
+
+    /// Add the single key accelerators for studying
+    ///
+    /// When answers are typed, the keys are needed for typing, so the accelerators require the
+    /// `Alt` modifier in that study mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The GTK application
+    /// * `mode` - The study mode of the session
+    pub fn add_study_accelerators(app: &gtk::Application, mode: StudyMode) {
+        let modifier = if mode == StudyMode::Typed { "<Alt>" } else { "" };
+        let accelerators = [
+            ("app.next_card", "n"),
+            ("app.show_answer", "space"),
+            ("app.grade_again", "a"),
+            ("app.grade_hard", "h"),
+            ("app.grade_good", "g"),
+            ("app.grade_easy", "e"),
+        ];
+        for (action, key) in accelerators.iter() {
+            app.set_accels_for_action(action, &[&format!("{}{}", modifier, key)]);
+        }
+        for number in 1..=Choice::MAX_CHOICES {
+            app.set_accels_for_action(
+                &format!("app.choose({})", number),
+                &[&format!("{}{}", modifier, number), &format!("{}KP_{}", modifier, number)],
+            );
+        }
+    }
+
+    /// Enable the actions to grade the answer, unless the study mode grades the answers itself
+    ///
+    /// A multiple choice quiz records the grade when an answer is chosen, so grading it by the
+    /// actions would record a second answer to the same card.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    pub fn update_grade_actions(window: &gtk::ApplicationWindow) {
+        let app = match window.get_application() {
+            Some(app) => app,
+            None => { return; },
+        };
+        let is_enabled = UI::get_session(window).mode != StudyMode::Choice;
+        for grade in Grade::ALL.iter() {
+            let action = app.lookup_action(&format!("grade_{}", grade.name()))
+                .and_then(|action| action.downcast::<gio::SimpleAction>().ok());
+            if let Some(action) = action {
+                action.set_enabled(is_enabled);
+            }
+        }
     }
 
     /// Add actions for the application
@@ -65,10 +179,31 @@ impl UI {
     ///
     /// * `about` - Show the about dialog
     /// * `add_card` - Show the dialog to add a new card
+    /// * `browse_cards` - Show the dialog to browse all cards
+    /// * `choose` - Choose the answer with the given number in a multiple choice quiz
     /// * `delete_card` - Show the confirm dialog to delete the current card
     /// * `edit_card` - Show the dialog to edit the current card
+    /// * `export_anki` - Show the dialog to export cards to an Anki package
+    /// * `export_cards` - Show the dialog to export cards to a CSV, TSV or JSON file
+    /// * `grade_again`, `grade_hard`, `grade_good`, `grade_easy` - Grade the answer to the current
+    ///   card and show the next card
+    /// * `import_anki` - Show the dialog to import cards from an Anki package
+    /// * `import_cards` - Show the wizard to import cards from a CSV, TSV or JSON file
+    /// * `manage_categories` - Show the dialog to manage the categories
+    /// * `manage_languages` - Show the dialog to manage the languages
+    /// * `new_deck` - Show the dialog to create a new deck
     /// * `next_card` - Show the next card
+    /// * `open_deck` - Show the dialog to open a deck
+    /// * `open_recent` - Open the deck with the given path
+    /// * `preferences` - Show the preferences dialog
     /// * `quit` - Quit the application
+    /// * `repair_database` - Clean up items which refer to deleted items
+    /// * `scheduler` - Choose the scheduler of the database by name
+    /// * `search` - Focus the search entry
+    /// * `show_answer` - Show the answers of the current card
+    /// * `statistics` - Show the dialog with statistics about the cards and the learning progress
+    /// * `study_filter` - Show the dialog to choose the categories and languages to study
+    /// * `study_session` - Show the dialog to configure the study session
     fn add_actions(app: &gtk::Application, window: &gtk::ApplicationWindow) {
         let quit = gio::SimpleAction::new("quit", None);
         quit.connect_activate(glib::clone!(@weak window => move |_, _| {
@@ -88,6 +223,20 @@ impl UI {
         }));
         app.add_action(&add_card);
 
+        let browse_cards = gio::SimpleAction::new("browse_cards", None);
+        browse_cards.connect_activate(glib::clone!(@weak window => move |_, _| {
+            CardBrowser::show(&window);
+        }));
+        app.add_action(&browse_cards);
+
+        let choose = gio::SimpleAction::new("choose", glib::VariantTy::new("i").ok());
+        choose.connect_activate(glib::clone!(@weak window => move |_, parameter| {
+            if let Some(number) = parameter.and_then(|parameter| parameter.get::<i32>()) {
+                Choice::choose(&window, number);
+            }
+        }));
+        app.add_action(&choose);
+
         let delete_card = gio::SimpleAction::new(
             "delete_card", None);
         delete_card.connect_activate(glib::clone!(@weak window => move |_, _| {
@@ -101,11 +250,123 @@ impl UI {
         }));
         app.add_action(&edit_card);
 
+        let export_cards = gio::SimpleAction::new("export_cards", None);
+        export_cards.connect_activate(glib::clone!(@weak window => move |_, _| {
+            ExportCards::show(&window);
+        }));
+        app.add_action(&export_cards);
+
+        for grade in Grade::ALL.iter() {
+            let grade = *grade;
+            let action = gio::SimpleAction::new(&format!("grade_{}", grade.name()), None);
+            action.connect_activate(glib::clone!(@weak window => move |_, _| {
+                CardNotebook::grade(&window, grade);
+            }));
+            app.add_action(&action);
+        }
+
         let next_card = gio::SimpleAction::new("next_card", None);
         next_card.connect_activate(glib::clone!(@weak window => move |_, _| {
             CardNotebook::replace(&window, 0);
         }));
         app.add_action(&next_card);
+
+        let export_anki = gio::SimpleAction::new("export_anki", None);
+        export_anki.connect_activate(glib::clone!(@weak window => move |_, _| {
+            AnkiExport::show(&window);
+        }));
+        app.add_action(&export_anki);
+
+        let import_anki = gio::SimpleAction::new("import_anki", None);
+        import_anki.connect_activate(glib::clone!(@weak window => move |_, _| {
+            AnkiImport::show(&window);
+        }));
+        app.add_action(&import_anki);
+
+        let import_cards = gio::SimpleAction::new("import_cards", None);
+        import_cards.connect_activate(glib::clone!(@weak window => move |_, _| {
+            ImportWizard::show(&window);
+        }));
+        app.add_action(&import_cards);
+
+        let manage_categories = gio::SimpleAction::new("manage_categories", None);
+        manage_categories.connect_activate(glib::clone!(@weak window => move |_, _| {
+            CategoryManager::show(&window);
+        }));
+        app.add_action(&manage_categories);
+
+        let manage_languages = gio::SimpleAction::new("manage_languages", None);
+        manage_languages.connect_activate(glib::clone!(@weak window => move |_, _| {
+            LanguageManager::show(&window);
+        }));
+        app.add_action(&manage_languages);
+
+        let new_deck = gio::SimpleAction::new("new_deck", None);
+        new_deck.connect_activate(glib::clone!(@weak window => move |_, _| {
+            DeckFile::show_new(&window);
+        }));
+        app.add_action(&new_deck);
+
+        let open_deck = gio::SimpleAction::new("open_deck", None);
+        open_deck.connect_activate(glib::clone!(@weak window => move |_, _| {
+            DeckFile::show_open(&window);
+        }));
+        app.add_action(&open_deck);
+
+        let open_recent = gio::SimpleAction::new("open_recent", glib::VariantTy::new("s").ok());
+        open_recent.connect_activate(glib::clone!(@weak window => move |_, parameter| {
+            if let Some(path) = parameter.and_then(|parameter| parameter.get_str()) {
+                UI::open_deck(&window, path);
+            }
+        }));
+        app.add_action(&open_recent);
+
+        let preferences = gio::SimpleAction::new("preferences", None);
+        preferences.connect_activate(glib::clone!(@weak window => move |_, _| {
+            Preferences::show(&window);
+        }));
+        app.add_action(&preferences);
+
+        let repair_database = gio::SimpleAction::new("repair_database", None);
+        repair_database.connect_activate(glib::clone!(@weak window => move |_, _| {
+            UI::repair_database(&window);
+        }));
+        app.add_action(&repair_database);
+
+        let scheduler = UI::build_scheduler_action(window);
+        app.add_action(&scheduler);
+
+        let search = gio::SimpleAction::new("search", None);
+        search.connect_activate(glib::clone!(@weak window => move |_, _| {
+            if let Some(entry) = Search::find(&window) {
+                entry.grab_focus();
+            }
+        }));
+        app.add_action(&search);
+
+        let show_answer = gio::SimpleAction::new("show_answer", None);
+        show_answer.connect_activate(glib::clone!(@weak window => move |_, _| {
+            CardNotebook::reveal(&window);
+        }));
+        app.add_action(&show_answer);
+
+        let statistics = gio::SimpleAction::new("statistics", None);
+        statistics.connect_activate(glib::clone!(@weak window => move |_, _| {
+            Statistics::show(&window);
+        }));
+        app.add_action(&statistics);
+
+        let study_filter = gio::SimpleAction::new("study_filter", None);
+        study_filter.connect_activate(glib::clone!(@weak window => move |_, _| {
+            StudyFilter::show(&window);
+        }));
+        app.add_action(&study_filter);
+
+        let study_session = gio::SimpleAction::new("study_session", None);
+        study_session.connect_activate(glib::clone!(@weak window => move |_, _| {
+            StudySession::show(&window);
+        }));
+        app.add_action(&study_session);
     }
 
     /// Build the application's user interface with window parametrisation and widgets
@@ -113,47 +374,459 @@ impl UI {
     /// # Arguments
     ///
     /// * `app` - The GTK application
-    pub fn build(app: &gtk::Application) {
+    /// * `repository` - The repository of flash cards to study
+    /// * `settings` - The settings of the user
+    pub fn build(app: &gtk::Application, repository: Repository, mut settings: Settings) {
         let window = gtk::ApplicationWindow::new(app);
         window.set_title("Kaati Ako");
         window.set_border_width(10);
         window.set_position(gtk::WindowPosition::Center);
-        match Screen::get_default() {
-            Some(screen) => {
-                let width = screen.get_width() / 2;
-                let height = screen.get_height() / 2;
-                window.set_default_size(width, height);
-            },
-            None => { window.set_default_size(350, 70); }
+        if settings.window_width > 0 && settings.window_height > 0 {
+            window.set_default_size(settings.window_width, settings.window_height);
+        } else {
+            match Screen::get_default() {
+                Some(screen) => {
+                    let width = screen.get_width() / 2;
+                    let height = screen.get_height() / 2;
+                    window.set_default_size(width, height);
+                },
+                None => { window.set_default_size(350, 70); }
+            }
+        }
+        if settings.window_maximized {
+            window.maximize();
+        }
+        let mut session = Session::new();
+        session.mode = settings.mode;
+        session.prompt_language_id = settings.prompt_language_id;
+        session.answer_language_ids = settings.answer_language_ids.clone();
+        if let Ok(languages) = repository.languages() {
+            let language_ids: Vec<i64> = languages.iter().map(|language| language.id).collect();
+            session.retain_languages(&language_ids);
         }
+        if let Ok(path) = fs::canonicalize(repository.path()) {
+            settings.add_recent_db_path(&path.to_string_lossy());
+        }
+        UI::add_accelerators(app, session.mode);
+        UI::set_repository(&window, repository);
+        UI::set_session(&window, session);
+        UI::set_settings(&window, settings);
+        window.connect_delete_event(|window, _| {
+            UI::save_settings(window);
+            Inhibit(false)
+        });
+        let header = gtk::HeaderBar::new();
+        header.set_title(Some("Kaati Ako"));
+        header.set_show_close_button(true);
+        header.pack_end(&Search::build(&window));
+        window.set_titlebar(Some(&header));
+        unsafe {
+            window.set_data("header", header);
+        }
+        UI::update_title(&window);
         window.add(&Content::build(&window));
-        UI::build_menu(app);
-        UI::add_accelerators(app);
+        UI::build_menu(app, &window);
         UI::add_actions(app, &window);
+        UI::update_grade_actions(&window);
         window.show_all();
     }
 
+    /// Start the application with the given command line options
+    ///
+    /// The first invocation builds the window, later ones apply their options to it and raise it.
+    /// The database given by the options takes precedence over `DB_PATH` and the settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The GTK application
+    /// * `options` - The command line options
+    pub fn start(app: &gtk::Application, options: &Options) {
+        let window = app.get_active_window()
+            .and_then(|window| window.downcast::<gtk::ApplicationWindow>().ok());
+        let window = match window {
+            Some(window) => {
+                if let Some(db_path) = &options.db_path {
+                    let open_path = UI::get_repository(&window).ok()
+                        .and_then(|repository| fs::canonicalize(repository.path()).ok());
+                    if open_path.is_none() || fs::canonicalize(db_path).ok() != open_path {
+                        UI::open_deck(&window, db_path);
+                    }
+                }
+                window
+            },
+            None => {
+                let settings = Settings::load();
+                let db_path = options.db_path.clone()
+                    .unwrap_or_else(|| get_path(settings.db_path.as_deref()));
+                match Repository::open(&db_path) {
+                    Ok(repository) => UI::build(app, repository, settings),
+                    Err(err) => {
+                        ErrorDialog::show_without_parent(
+                            app, &format!("Could not open the database {}: {}", db_path, err));
+                        return;
+                    },
+                }
+                match app.get_active_window()
+                    .and_then(|window| window.downcast::<gtk::ApplicationWindow>().ok()) {
+                    Some(window) => window,
+                    None => { return; },
+                }
+            },
+        };
+        UI::apply_options(&window, options);
+        window.present();
+    }
+
+    /// Apply the study mode, the category and the card of the command line options to the window
+    ///
+    /// The category replaces the categories of the saved study filter, the languages of the filter
+    /// are kept. So it is still the only category studied on the next start.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `options` - The command line options
+    fn apply_options(window: &gtk::ApplicationWindow, options: &Options) {
+        if options.mode.is_none() && options.category.is_none() && options.card_id == 0 {
+            return;
+        }
+        let repository = match UI::get_repository(window) {
+            Ok(repository) => repository,
+            Err(err) => {
+                ErrorDialog::show(window, &err.to_string());
+                return;
+            },
+        };
+        if let Some(mode) = options.mode {
+            let mut session = UI::get_session(window);
+            session.mode = mode;
+            if let Some(app) = window.get_application() {
+                UI::add_study_accelerators(&app, mode);
+            }
+            UI::set_session(window, session);
+        }
+        if let Some(name) = &options.category {
+            let result = repository.category_by_name(name).and_then(|category| {
+                let mut filter = repository.filter()?;
+                filter.category_ids = vec![category.id];
+                repository.set_filter(&filter)
+            });
+            match result {
+                Ok(_) => Content::update_filter(window),
+                Err(DatabaseError::NotFound) => ErrorDialog::show(
+                    window, &format!("There is no category called {}.", name)),
+                Err(err) => ErrorDialog::show(window, &err.to_string()),
+            }
+        }
+        let card_id = match repository.card(options.card_id) {
+            Ok(card) => card.id,
+            Err(_) if options.card_id == 0 => 0,
+            Err(_) => {
+                ErrorDialog::show(window, &format!("There is no card with id {}.", options.card_id));
+                0
+            },
+        };
+        CardNotebook::replace(window, card_id);
+    }
+
+    /// Get the repository of flash cards of the application window
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    pub fn get_repository(window: &gtk::ApplicationWindow) -> Result<Rc<Repository>, DatabaseError> {
+        unsafe {
+            match window.get_data::<Rc<Repository>>("repository") {
+                Some(repository) => Ok(repository.clone()),
+                None => Err(DatabaseError::NotFound),
+            }
+        }
+    }
+
+    /// Set the repository of flash cards of the application window, e.g. to switch the database
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `repository` - The new repository
+    pub fn set_repository(window: &gtk::ApplicationWindow, repository: Repository) {
+        unsafe {
+            window.set_data("repository", Rc::new(repository));
+        }
+    }
+
+    /// Open a deck, i.e. a database file, instead of the current one
+    ///
+    /// A missing file is created with the latest schema. The languages of the study session are
+    /// kept if the deck has them, and the deck is opened on the next start, too.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `db_path` - Path to the database file
+    pub fn open_deck(window: &gtk::ApplicationWindow, db_path: &str) {
+        let repository = match Repository::open(db_path) {
+            Ok(repository) => repository,
+            Err(err) => {
+                ErrorDialog::show(window, &err.to_string());
+                return;
+            },
+        };
+        let mut session = UI::get_session(window);
+        match repository.languages() {
+            Ok(languages) => {
+                let language_ids: Vec<i64> = languages.iter().map(|language| language.id).collect();
+                session.retain_languages(&language_ids);
+            },
+            Err(err) => {
+                ErrorDialog::show(window, &err.to_string());
+                return;
+            },
+        }
+        let scheduler_name = repository.scheduler_name().unwrap_or(Sm2::NAME);
+        UI::set_repository(window, repository);
+        UI::set_session(window, session);
+
+        let mut settings = UI::get_settings(window);
+        settings.use_db_path(db_path);
+        if let Err(err) = settings.save() {
+            ErrorDialog::show(window, &err.to_string());
+        }
+        UI::set_settings(window, settings);
+
+        if let Some(app) = window.get_application() {
+            if let Some(action) = app.lookup_action("scheduler") {
+                action.change_state(&scheduler_name.to_variant());
+            }
+        }
+        UI::update_recent_menu(window);
+        UI::update_title(window);
+        if let Some(content) = window.get_child() {
+            window.remove(&content);
+        }
+        window.add(&Content::build(window));
+        window.show_all();
+    }
+
+    /// Show the file name of the open deck as subtitle of the header bar
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    fn update_title(window: &gtk::ApplicationWindow) {
+        let header = match unsafe { window.get_data::<gtk::HeaderBar>("header") } {
+            Some(header) => header,
+            None => { return; },
+        };
+        let name = UI::get_repository(window).ok().and_then(|repository| {
+            Path::new(repository.path()).file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        });
+        header.set_subtitle(name.as_deref());
+    }
+
+    /// Get the study session of the application window
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    pub fn get_session(window: &gtk::ApplicationWindow) -> Session {
+        unsafe {
+            match window.get_data::<Session>("session") {
+                Some(session) => session.clone(),
+                None => Session::new(),
+            }
+        }
+    }
+
+    /// Set the study session of the application window
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `session` - The new study session
+    pub fn set_session(window: &gtk::ApplicationWindow, session: Session) {
+        unsafe {
+            window.set_data("session", session);
+        }
+    }
+
+    /// Get the settings of the user
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    pub fn get_settings(window: &gtk::ApplicationWindow) -> Settings {
+        unsafe {
+            match window.get_data::<Settings>("settings") {
+                Some(settings) => settings.clone(),
+                None => Settings::new(),
+            }
+        }
+    }
+
+    /// Set the settings of the user
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `settings` - The new settings
+    pub fn set_settings(window: &gtk::ApplicationWindow, settings: Settings) {
+        unsafe {
+            window.set_data("settings", settings);
+        }
+    }
+
+    /// Save the settings of the user together with the languages of the study session and the
+    /// geometry of the application window
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    fn save_settings(window: &gtk::ApplicationWindow) {
+        let mut settings = UI::get_settings(window);
+        let session = UI::get_session(window);
+        settings.prompt_language_id = session.prompt_language_id;
+        settings.answer_language_ids = session.answer_language_ids;
+        settings.window_maximized = window.is_maximized();
+        if !settings.window_maximized {
+            let (width, height) = window.get_size();
+            settings.window_width = width;
+            settings.window_height = height;
+        }
+        // The window is about to close, so there is nobody left to show an error dialog to
+        if let Err(err) = settings.save() {
+            eprintln!("Cannot save settings to {}: {}", Settings::path().display(), err);
+        }
+        UI::set_settings(window, settings);
+    }
+
+    /// Build the stateful action to choose the scheduler of the database
+    ///
+    /// The state of the action is the name of the active scheduler.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    fn build_scheduler_action(window: &gtk::ApplicationWindow) -> gio::SimpleAction {
+        let name = UI::get_repository(window)
+            .and_then(|repository| repository.scheduler_name())
+            .unwrap_or(Sm2::NAME);
+        let action = gio::SimpleAction::new_stateful(
+            "scheduler", glib::VariantTy::new("s").ok(), &name.to_variant());
+        action.connect_activate(glib::clone!(@weak window => move |action, parameter| {
+            let name = match parameter.and_then(|parameter| parameter.get_str()) {
+                Some(name) => name,
+                None => { return; },
+            };
+            let result = UI::get_repository(&window).and_then(
+                |repository| repository.set_scheduler(name));
+            match result {
+                Ok(_) => {
+                    action.set_state(&name.to_variant());
+                    CardNotebook::replace(&window, 0);
+                },
+                Err(err) => ErrorDialog::show(&window, &err.to_string()),
+            }
+        }));
+        action
+    }
+
+    /// Repair the database and show how many items have been repaired
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    fn repair_database(window: &gtk::ApplicationWindow) {
+        match UI::get_repository(window).and_then(|repository| repository.repair()) {
+            Ok(0) => InfoDialog::show(window, "The database is consistent, nothing to repair."),
+            Ok(count) => InfoDialog::show(window, &format!("Repaired {} items.", count)),
+            Err(err) => ErrorDialog::show(window, &err.to_string()),
+        }
+    }
+
+    /// List the recently opened decks in the menu, except the open one
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    fn update_recent_menu(window: &gtk::ApplicationWindow) {
+        let menu = match unsafe { window.get_data::<gio::Menu>("recent_menu") } {
+            Some(menu) => menu.clone(),
+            None => { return; },
+        };
+        let open_path = UI::get_repository(window).ok()
+            .and_then(|repository| fs::canonicalize(repository.path()).ok());
+        menu.remove_all();
+        for db_path in UI::get_settings(window).recent_db_paths {
+            let path = Path::new(&db_path);
+            if Some(path) == open_path.as_deref() {
+                continue;
+            }
+            let label = path.file_name().map_or(
+                db_path.clone(), |name| name.to_string_lossy().to_string());
+            let item = gio::MenuItem::new(Some(&label), None);
+            item.set_action_and_target_value(Some("app.open_recent"), Some(&db_path.to_variant()));
+            menu.append_item(&item);
+        }
+    }
+
     /// Build the application's menu
     ///
     /// # Arguments
     ///
     /// * `app` - The GTK application
-    fn build_menu(app: &gtk::Application) {
+    /// * `window` - The GTK application window
+    fn build_menu(app: &gtk::Application, window: &gtk::ApplicationWindow) {
         let menu = gio::Menu::new();
+        menu.append(Some("Preferences"), Some("app.preferences"));
         menu.append(Some("Quit"), Some("app.quit"));
         app.set_app_menu(Some(&menu));
 
+        let recent_menu = gio::Menu::new();
+        unsafe {
+            window.set_data("recent_menu", recent_menu.clone());
+        }
+        UI::update_recent_menu(window);
+        let file_menu = gio::Menu::new();
+        file_menu.append(Some("New deck"), Some("app.new_deck"));
+        file_menu.append(Some("Open deck"), Some("app.open_deck"));
+        file_menu.append_submenu(Some("Recent decks"), &recent_menu);
+        let exchange_menu = gio::Menu::new();
+        exchange_menu.append(Some("Import cards"), Some("app.import_cards"));
+        exchange_menu.append(Some("Import Anki package"), Some("app.import_anki"));
+        exchange_menu.append(Some("Export cards"), Some("app.export_cards"));
+        exchange_menu.append(Some("Export Anki package"), Some("app.export_anki"));
+        file_menu.append_section(None, &exchange_menu);
+
         let card_menu = gio::Menu::new();
         card_menu.append(Some("Add card"), Some("app.add_card"));
+        card_menu.append(Some("Browse cards"), Some("app.browse_cards"));
         card_menu.append(Some("Edit current card"), Some("app.edit_card"));
         card_menu.append(
             Some("Delete current card"), Some("app.delete_card"));
+        let database_menu = gio::Menu::new();
+        database_menu.append(Some("Manage categories"), Some("app.manage_categories"));
+        database_menu.append(Some("Manage languages"), Some("app.manage_languages"));
+        database_menu.append(Some("Repair database"), Some("app.repair_database"));
+        card_menu.append_section(None, &database_menu);
+
+        let scheduler_menu = gio::Menu::new();
+        scheduler_menu.append(Some("SM-2"), Some(&format!("app.scheduler::{}", Sm2::NAME)));
+        scheduler_menu.append(Some("Leitner"), Some(&format!("app.scheduler::{}", Leitner::NAME)));
+        let study_menu = gio::Menu::new();
+        study_menu.append(Some("Study session"), Some("app.study_session"));
+        study_menu.append(Some("Study filter"), Some("app.study_filter"));
+        study_menu.append(Some("Statistics"), Some("app.statistics"));
+        study_menu.append_section(Some("Scheduler"), &scheduler_menu);
 
         let about_menu = gio::Menu::new();
         about_menu.append(Some("About"), Some("app.about"));
 
         let menu_bar = gio::Menu::new();
+        menu_bar.append_submenu(Some("File"), &file_menu);
         menu_bar.append_submenu(Some("Card"), &card_menu);
+        menu_bar.append_submenu(Some("Study"), &study_menu);
         menu_bar.append_submenu(Some("?"), &about_menu);
         app.set_menubar(Some(&menu_bar));
     }
@@ -1,6 +1,19 @@
 //! Dialogs of the application
 
 pub mod about;
+pub mod ankiexport;
+pub mod ankiimport;
+pub mod cardbrowser;
 pub mod cardeditor;
+pub mod categorymanager;
+pub mod deckfile;
 pub mod deletecard;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod exportcards;
+pub mod importwizard;
+pub mod info;
+pub mod languagemanager;
+pub mod preferences;
+pub mod statistics;
+pub mod studyfilter;
+pub mod studysession;
\ No newline at end of file
@@ -0,0 +1,250 @@
+//! Module for the dialog to export flash cards to Anki packages
+
+use std::path::{Path, PathBuf};
+
+use gtk::{
+    BoxExt,
+    ContainerExt,
+    DialogExt,
+    EntryExt,
+    FileChooserExt,
+    GtkWindowExt,
+    ScrolledWindowExt,
+    ToggleButtonExt,
+    WidgetExt,
+};
+
+use crate::anki::PackageOptions;
+use crate::database::DatabaseError;
+use crate::models::language::Language;
+use crate::repository::Repository;
+use crate::ui::UI;
+use super::error::Error as ErrorDialog;
+use super::info::Info as InfoDialog;
+use super::studyfilter::StudyFilter;
+
+
+/// The widgets of the dialog which hold the user's choices
+struct Widgets {
+    /// The entry which holds the name of the deck in Anki
+    name: gtk::Entry,
+    /// The check buttons which hold the categories by category id
+    categories: Vec<(i64, gtk::CheckButton)>,
+    /// The check buttons which hold the directions by prompt and answer language id
+    directions: Vec<((i64, i64), gtk::CheckButton)>,
+    /// The check button which holds whether to show the descriptions
+    descriptions: gtk::CheckButton,
+}
+
+
+/// A dialog to export flash cards as notes to an Anki package
+pub struct AnkiExport;
+
+/// Implementation of the dialog to export Anki packages
+impl AnkiExport {
+    /// Get the name of the database file without extension, which names the deck and the package
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    fn stem(repository: &Repository) -> String {
+        Path::new(repository.path()).file_stem()
+            .map_or("kaati_ako".to_string(), |stem| stem.to_string_lossy().to_string())
+    }
+
+    /// Build a check button per direction, i.e. per ordered pair of languages, in a container
+    ///
+    /// The directions between the first two languages are checked initially.
+    ///
+    /// # Arguments
+    ///
+    /// * `container` - The container of the check buttons
+    /// * `languages` - All languages of the database
+    fn build_directions(
+        container: &gtk::Box,
+        languages: &[Language],
+    ) -> Vec<((i64, i64), gtk::CheckButton)> {
+        let mut buttons = Vec::new();
+        for (prompt_idx, prompt) in languages.iter().enumerate() {
+            for (answer_idx, answer) in languages.iter().enumerate() {
+                if prompt_idx == answer_idx {
+                    continue;
+                }
+                let button = gtk::CheckButton::with_label(
+                    &format!("{} → {}", prompt.name, answer.name));
+                button.set_active(prompt_idx < 2 && answer_idx < 2);
+                container.pack_start(&button, false, false, 0);
+                buttons.push(((prompt.id, answer.id), button));
+            }
+        }
+        buttons
+    }
+
+    /// Build the dialog
+    ///
+    /// The categories of the study filter are checked initially.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    fn build(parent: &gtk::ApplicationWindow) -> Result<gtk::Dialog, DatabaseError> {
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Export Anki Package"),
+            Some(parent),
+            gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+            &[
+                ("_Export", gtk::ResponseType::Accept),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        let repository = UI::get_repository(parent)?;
+        let filter = repository.filter()?;
+        let spacing = 10;
+        dialog.set_default_size(300, 500);
+
+        let content = dialog.get_content_area();
+        content.set_margin_start(spacing as i32);
+        content.set_margin_end(spacing as i32);
+
+        let label = gtk::Label::new(Some("Name of the deck"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let name = gtk::Entry::new();
+        name.set_text(&AnkiExport::stem(&repository));
+        content.pack_start(&name, false, false, spacing);
+
+        let label = gtk::Label::new(Some("Categories (none checked for all)"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let categories = repository.categories()?.into_iter()
+            .map(|category| (category.id, category.name))
+            .collect();
+        let (container, category_buttons) = StudyFilter::build_check_buttons(
+            categories, &filter.category_ids, gtk::Orientation::Vertical);
+        let scrolled = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.add(&container);
+        content.pack_start(&scrolled, true, true, spacing);
+
+        let label = gtk::Label::new(Some("Card templates"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let container = gtk::Box::new(gtk::Orientation::Vertical, spacing as i32);
+        let direction_buttons = AnkiExport::build_directions(&container, &repository.languages()?);
+        let scrolled = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.add(&container);
+        content.pack_start(&scrolled, true, true, spacing);
+
+        let descriptions = gtk::CheckButton::with_label("Show the descriptions on the back");
+        descriptions.set_active(true);
+        content.pack_start(&descriptions, false, false, spacing);
+
+        let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
+        content.pack_end(&separator, false, false, spacing);
+
+        let widgets = Widgets {
+            name,
+            categories: category_buttons,
+            directions: direction_buttons,
+            descriptions,
+        };
+        dialog.connect_response(glib::clone!(@weak parent => move |dialog, response_type| {
+            if response_type == gtk::ResponseType::Accept {
+                // Hide this dialog, so that it does not cover the file chooser
+                dialog.hide();
+                AnkiExport::response_accept(&parent, &repository, &widgets);
+            }
+        }));
+        Ok(dialog)
+    }
+
+    /// Show the dialog to choose the package to export to
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `repository` - The repository of flash cards
+    fn choose_file(parent: &gtk::ApplicationWindow, repository: &Repository) -> Option<PathBuf> {
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some("Export Anki Package"),
+            Some(parent),
+            gtk::FileChooserAction::Save,
+            &[
+                ("_Save", gtk::ResponseType::Accept),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        dialog.set_modal(true);
+        dialog.set_do_overwrite_confirmation(true);
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("Anki packages"));
+        filter.add_pattern("*.apkg");
+        dialog.add_filter(&filter);
+        dialog.set_current_name(format!("{}.apkg", AnkiExport::stem(repository)));
+        let path = match dialog.run() {
+            gtk::ResponseType::Accept => dialog.get_filename(),
+            _ => None,
+        };
+        dialog.close();
+        path
+    }
+
+    /// When the dialog is accepted, choose a file and export the cards to it
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `repository` - The repository of flash cards
+    /// * `widgets` - The widgets which hold the user's choices
+    fn response_accept(
+        parent: &gtk::ApplicationWindow,
+        repository: &Repository,
+        widgets: &Widgets,
+    ) {
+        let options = PackageOptions {
+            category_ids: widgets.categories.iter()
+                .filter(|(_, button)| button.get_active())
+                .map(|(id, _)| *id)
+                .collect(),
+            directions: widgets.directions.iter()
+                .filter(|(_, button)| button.get_active())
+                .map(|(direction, _)| *direction)
+                .collect(),
+            descriptions: widgets.descriptions.get_active(),
+        };
+        if options.directions.is_empty() {
+            ErrorDialog::show(parent, "Nothing exported: No card template is checked.");
+            return;
+        }
+        let name = widgets.name.get_text().trim().to_string();
+        let name = if name.is_empty() { AnkiExport::stem(repository) } else { name };
+        let path = match AnkiExport::choose_file(parent, repository) {
+            Some(path) => path,
+            None => { return; },
+        };
+        match repository.export_anki(&path, &name, &options) {
+            Ok(count) => InfoDialog::show(parent, &format!(
+                "Exported {} notes to {}.", count, path.to_string_lossy())),
+            Err(err) => ErrorDialog::show(parent, &err.to_string()),
+        }
+    }
+
+    /// Show the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show(parent: &gtk::ApplicationWindow) {
+        let dialog = match AnkiExport::build(parent) {
+            Ok(dialog) => dialog,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        dialog.show_all();
+        dialog.run();
+        dialog.close();
+    }
+}
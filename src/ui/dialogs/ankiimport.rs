@@ -0,0 +1,309 @@
+//! Module for the dialog to import flash cards from Anki packages
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use gtk::{
+    BoxExt,
+    ComboBoxExt,
+    ComboBoxTextExt,
+    ContainerExt,
+    DialogExt,
+    FileChooserExt,
+    GridExt,
+    GtkWindowExt,
+    LabelExt,
+    ScrolledWindowExt,
+    ToggleButtonExt,
+    WidgetExt,
+};
+
+use crate::anki::{AnkiPackage, CategorySource, html_to_text};
+use crate::database::DatabaseError;
+use crate::import::Column;
+use crate::models::language::Language;
+use crate::repository::Repository;
+use crate::ui::UI;
+use crate::ui::widgets::cardnotebook::CardNotebook;
+use super::error::Error as ErrorDialog;
+use super::importwizard::ImportWizard;
+use super::info::Info as InfoDialog;
+
+
+/// The widgets of the dialog which hold the user's choices
+struct Widgets {
+    /// The combo boxes which hold the mapping of the fields by note type id
+    note_types: Vec<(i64, Vec<gtk::ComboBoxText>)>,
+    /// The combo box which holds where the category comes from
+    category_source: gtk::ComboBoxText,
+    /// The combo box which holds the category of notes without one
+    category: gtk::ComboBoxText,
+    /// The check button which holds whether to copy the media files
+    media: gtk::CheckButton,
+}
+
+
+/// A dialog to map the note fields of an Anki package to languages and import its notes as cards
+pub struct AnkiImport;
+
+/// Implementation of the dialog to import Anki packages
+impl AnkiImport {
+    /// Show the dialog to choose the package to import
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    fn choose_file(parent: &gtk::ApplicationWindow) -> Option<PathBuf> {
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some("Import Anki Package"),
+            Some(parent),
+            gtk::FileChooserAction::Open,
+            &[
+                ("_Next", gtk::ResponseType::Accept),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        dialog.set_modal(true);
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("Anki packages"));
+        filter.add_pattern("*.apkg");
+        dialog.add_filter(&filter);
+        let path = match dialog.run() {
+            gtk::ResponseType::Accept => dialog.get_filename(),
+            _ => None,
+        };
+        dialog.close();
+        path
+    }
+
+    /// Build a frame per note type with a combo box per field to map it
+    ///
+    /// The combo boxes are preset by the names of the fields, see `Column::guess`.
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The Anki package
+    /// * `languages` - All languages of the database
+    fn build_note_types(
+        package: &AnkiPackage,
+        languages: &[Language],
+    ) -> (gtk::Box, Vec<(i64, Vec<gtk::ComboBoxText>)>) {
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 10);
+        let mut note_type_widgets = Vec::new();
+        for note_type in package.note_types.iter() {
+            let count = package.notes.iter()
+                .filter(|note| note.note_type_id == note_type.id)
+                .count();
+            let frame = gtk::Frame::new(Some(&format!("{} ({} notes)", note_type.name, count)));
+            let grid = gtk::Grid::new();
+            grid.set_column_spacing(10);
+            grid.set_row_spacing(5);
+            grid.set_margin_start(10);
+            grid.set_margin_end(10);
+            let example = package.notes.iter().find(|note| note.note_type_id == note_type.id);
+            let mut combos = Vec::new();
+            for (idx, field) in note_type.fields.iter().enumerate() {
+                let top = idx as i32;
+                let label = gtk::Label::new(Some(field));
+                label.set_halign(gtk::Align::Start);
+                grid.attach(&label, 0, top, 1, 1);
+                let combo = ImportWizard::build_column(languages, Column::guess(field, languages));
+                grid.attach(&combo, 1, top, 1, 1);
+                let value = example
+                    .and_then(|note| note.fields.get(idx))
+                    .map_or(String::new(), |value| html_to_text(value, false));
+                let label = gtk::Label::new(Some(&value));
+                label.set_halign(gtk::Align::Start);
+                label.set_line_wrap(true);
+                label.set_max_width_chars(30);
+                grid.attach(&label, 2, top, 1, 1);
+                combos.push(combo);
+            }
+            frame.add(&grid);
+            container.pack_start(&frame, false, false, 0);
+            note_type_widgets.push((note_type.id, combos));
+        }
+        (container, note_type_widgets)
+    }
+
+    /// Build the combo box to choose where the category of a note comes from
+    fn build_category_source() -> gtk::ComboBoxText {
+        let combo = gtk::ComboBoxText::new();
+        combo.append(Some("deck"), "Deck of the note");
+        combo.append(Some("tag"), "First tag of the note");
+        combo.append(Some("none"), "Category below");
+        combo.set_active_id(Some("deck"));
+        combo
+    }
+
+    /// Build the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `path` - Path to the package
+    /// * `package` - The content of the package
+    fn build(
+        parent: &gtk::ApplicationWindow,
+        path: PathBuf,
+        package: AnkiPackage,
+    ) -> Result<gtk::Dialog, DatabaseError> {
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Import Anki Package"),
+            Some(parent),
+            gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+            &[
+                ("_Import", gtk::ResponseType::Accept),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        let repository = UI::get_repository(parent)?;
+        let languages = repository.languages()?;
+        let spacing = 10;
+        dialog.set_default_size(600, 500);
+
+        let content = dialog.get_content_area();
+        content.set_margin_start(spacing as i32);
+        content.set_margin_end(spacing as i32);
+
+        let label = gtk::Label::new(Some("Map the fields of the notes to languages or the category."));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let (container, note_type_widgets) = AnkiImport::build_note_types(&package, &languages);
+        let scrolled = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.add(&container);
+        content.pack_start(&scrolled, true, true, spacing);
+
+        let label = gtk::Label::new(Some("Category of the cards, unless a field is mapped to it"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let category_source = AnkiImport::build_category_source();
+        content.pack_start(&category_source, false, false, spacing);
+        let category = ImportWizard::build_category(&repository)?;
+        content.pack_start(&category, false, false, spacing);
+
+        let media = gtk::CheckButton::with_label(&format!(
+            "Copy the media files to {}", AnkiImport::media_dir(&repository).to_string_lossy()));
+        media.set_sensitive(package.has_media());
+        content.pack_start(&media, false, false, spacing);
+
+        let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
+        content.pack_end(&separator, false, false, spacing);
+
+        let widgets = Widgets { note_types: note_type_widgets, category_source, category, media };
+        dialog.connect_response(glib::clone!(@weak parent => move |_, response_type| {
+            if response_type == gtk::ResponseType::Accept {
+                AnkiImport::response_accept(&parent, &repository, &path, &package, &widgets);
+            }
+        }));
+        Ok(dialog)
+    }
+
+    /// Get the directory for media files, next to the database file
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    fn media_dir(repository: &Repository) -> PathBuf {
+        let path = Path::new(repository.path());
+        let stem = path.file_stem().map_or(
+            "kaati_ako".to_string(), |stem| stem.to_string_lossy().to_string());
+        path.with_file_name(format!("{}.media", stem))
+    }
+
+    /// When the dialog is accepted, import the notes and show a summary
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `repository` - The repository of flash cards
+    /// * `path` - Path to the package
+    /// * `package` - The content of the package
+    /// * `widgets` - The widgets which hold the user's choices
+    fn response_accept(
+        parent: &gtk::ApplicationWindow,
+        repository: &Repository,
+        path: &Path,
+        package: &AnkiPackage,
+        widgets: &Widgets,
+    ) {
+        let mapping: HashMap<i64, Vec<Column>> = widgets.note_types.iter()
+            .map(|(id, combos)| {
+                let columns = combos.iter()
+                    .map(|combo| combo.get_active_id().map_or(
+                        Column::Ignore, |id| ImportWizard::column_from_id(&id)))
+                    .collect();
+                (*id, columns)
+            })
+            .collect();
+        let is_mapped = mapping.values().flatten().any(|column| matches!(column, Column::Text(_)));
+        if !is_mapped {
+            ErrorDialog::show(parent, "Nothing imported: No field is mapped to a language.");
+            return;
+        }
+        let category_source = match widgets.category_source.get_active_id().as_deref() {
+            Some("deck") => CategorySource::Deck,
+            Some("tag") => CategorySource::Tag,
+            _ => CategorySource::None,
+        };
+        let category_id = widgets.category.get_active_id()
+            .and_then(|id| id.parse::<i64>().ok())
+            .unwrap_or(0);
+        let keep_media = widgets.media.get_active();
+        let (rows, columns) = package.to_rows(&mapping, category_source, keep_media);
+        let summary = match repository.import_rows(&rows, &columns, category_id) {
+            Ok(summary) => summary,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        let mut message = format!(
+            "Imported {} cards, skipped {} duplicates, {} notes failed.",
+            summary.imported, summary.skipped, summary.failed,
+        );
+        if keep_media {
+            match package.extract_media(path, &AnkiImport::media_dir(repository)) {
+                Ok(count) => message.push_str(&format!(" Copied {} media files.", count)),
+                Err(err) => ErrorDialog::show(parent, &err.to_string()),
+            }
+        }
+        InfoDialog::show(parent, &message);
+        let card_id = CardNotebook::get_card_id(parent).unwrap_or(0);
+        CardNotebook::replace(parent, card_id);
+    }
+
+    /// Show the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show(parent: &gtk::ApplicationWindow) {
+        let path = match AnkiImport::choose_file(parent) {
+            Some(path) => path,
+            None => { return; },
+        };
+        let package = match AnkiPackage::read(&path) {
+            Ok(package) if package.notes.is_empty() => {
+                InfoDialog::show(parent, "The package has no notes to import.");
+                return;
+            },
+            Ok(package) => package,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        let dialog = match AnkiImport::build(parent, path, package) {
+            Ok(dialog) => dialog,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        dialog.show_all();
+        dialog.run();
+        dialog.close();
+    }
+}
@@ -0,0 +1,229 @@
+//! Module for the dialog to browse all flash cards
+
+use std::collections::HashMap;
+
+use gtk::{
+    BoxExt,
+    CellLayoutExt,
+    ContainerExt,
+    DialogExt,
+    EntryExt,
+    GtkListStoreExt,
+    GtkWindowExt,
+    ScrolledWindowExt,
+    SearchEntryExt,
+    TreeModelExt,
+    TreeModelFilterExt,
+    TreeSelectionExt,
+    TreeViewColumnExt,
+    TreeViewExt,
+    WidgetExt,
+    prelude::GtkListStoreExtManual,
+};
+
+use crate::database::DatabaseError;
+use crate::models::language::Language;
+use crate::repository::Repository;
+use crate::ui::UI;
+use crate::ui::widgets::cardnotebook::CardNotebook;
+use super::cardeditor::CardEditor;
+use super::error::Error as ErrorDialog;
+
+
+/// A dialog to browse, sort and filter all cards
+pub struct CardBrowser;
+
+/// Implementation of the dialog to browse all cards
+impl CardBrowser {
+    /// Column of the card id in the list store
+    const COLUMN_ID: i32 = 0;
+    /// Column of the category name in the list store, followed by one column per language
+    const COLUMN_CATEGORY: i32 = 1;
+
+    /// Build a sortable text column of the tree view
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - Title of the column
+    /// * `column_id` - Column in the list store which holds the text
+    fn build_column(title: &str, column_id: i32) -> gtk::TreeViewColumn {
+        let renderer = gtk::CellRendererText::new();
+        let column = gtk::TreeViewColumn::new();
+        column.set_title(title);
+        column.pack_start(&renderer, true);
+        column.add_attribute(&renderer, "text", column_id);
+        column.set_sort_column_id(column_id);
+        column.set_resizable(true);
+        column
+    }
+
+    /// Build the list store with the card id, the category and one column per language
+    ///
+    /// # Arguments
+    ///
+    /// * `languages` - All available languages
+    fn build_store(languages: &[Language]) -> gtk::ListStore {
+        let mut types = vec![glib::Type::I64, glib::Type::String];
+        types.extend(languages.iter().map(|_| glib::Type::String));
+        gtk::ListStore::new(&types)
+    }
+
+    /// Fill the list store with all cards
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `store` - The list store to fill
+    /// * `languages` - All available languages in order of the store's columns
+    fn fill_store(
+        repository: &Repository,
+        store: &gtk::ListStore,
+        languages: &[Language],
+    ) -> Result<(), DatabaseError> {
+        let categories: HashMap<i64, String> = repository.categories()?.into_iter()
+            .map(|category| (category.id, category.name))
+            .collect();
+        let texts: HashMap<(i64, i64), String> = repository.all_translations()?.into_iter()
+            .map(|translation| ((translation.card_id, translation.language_id), translation.text))
+            .collect();
+        let columns: Vec<u32> = (0..languages.len() as u32 + 2).collect();
+        let empty = String::new();
+        store.clear();
+        for card in repository.cards()? {
+            let category = categories.get(&card.category_id).unwrap_or(&empty);
+            let mut values: Vec<&dyn glib::ToValue> = vec![&card.id, category];
+            for language in languages {
+                values.push(texts.get(&(card.id, language.id)).unwrap_or(&empty));
+            }
+            store.insert_with_values(None, &columns, &values);
+        }
+        Ok(())
+    }
+
+    /// Check if a row of the store matches the search text in its category or any translation
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - The model of the row
+    /// * `iter` - The row to check
+    /// * `search` - The search text in lower case
+    fn matches(model: &gtk::TreeModel, iter: &gtk::TreeIter, search: &str) -> bool {
+        if search.is_empty() {
+            return true;
+        }
+        (CardBrowser::COLUMN_CATEGORY..model.get_n_columns()).any(|column_id| {
+            match model.get_value(iter, column_id).get::<String>() {
+                Ok(Some(text)) => text.to_lowercase().contains(search),
+                _ => false,
+            }
+        })
+    }
+
+    /// Get the id of the card in the selected row
+    ///
+    /// # Arguments
+    ///
+    /// * `tree_view` - The tree view which shows the cards
+    fn get_selected_card_id(tree_view: &gtk::TreeView) -> Option<i64> {
+        let (model, iter) = tree_view.get_selection().get_selected()?;
+        model.get_value(&iter, CardBrowser::COLUMN_ID).get_some::<i64>().ok()
+    }
+
+    /// Build the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    fn build(parent: &gtk::ApplicationWindow) -> Result<gtk::Dialog, DatabaseError> {
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Browse Cards"),
+            Some(parent),
+            gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+            &[
+                ("_Show", gtk::ResponseType::Accept),
+                ("_Edit", gtk::ResponseType::Apply),
+                ("_Close", gtk::ResponseType::Close),
+            ],
+        );
+        let repository = UI::get_repository(parent)?;
+        let languages = repository.languages()?;
+        let spacing = 10;
+        let width = parent.get_allocated_width();
+        let height = parent.get_allocated_height();
+        dialog.set_default_size(width, height);
+
+        let content = dialog.get_content_area();
+        content.set_margin_start(spacing as i32);
+        content.set_margin_end(spacing as i32);
+
+        let search = gtk::SearchEntry::new();
+        search.set_placeholder_text(Some("Filter by category or translation ..."));
+        content.pack_start(&search, false, false, spacing);
+
+        let store = CardBrowser::build_store(&languages);
+        CardBrowser::fill_store(&repository, &store, &languages)?;
+        let filter = gtk::TreeModelFilter::new(&store, None);
+        filter.set_visible_func(glib::clone!(@weak search => @default-return true,
+            move |model, iter| {
+                CardBrowser::matches(model, iter, &search.get_text().to_lowercase())
+            }
+        ));
+        search.connect_search_changed(glib::clone!(@weak filter => move |_| {
+            filter.refilter();
+        }));
+        let sort = gtk::TreeModelSort::new(&filter);
+
+        let tree_view = gtk::TreeView::with_model(&sort);
+        tree_view.append_column(&CardBrowser::build_column("Id", CardBrowser::COLUMN_ID));
+        tree_view.append_column(
+            &CardBrowser::build_column("Category", CardBrowser::COLUMN_CATEGORY));
+        for (idx, language) in languages.iter().enumerate() {
+            let column_id = CardBrowser::COLUMN_CATEGORY + 1 + idx as i32;
+            tree_view.append_column(&CardBrowser::build_column(&language.name, column_id));
+        }
+        tree_view.connect_row_activated(glib::clone!(@weak dialog => move |_, _, _| {
+            dialog.response(gtk::ResponseType::Accept);
+        }));
+        let scrolled = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+        scrolled.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+        scrolled.add(&tree_view);
+        content.pack_start(&scrolled, true, true, spacing);
+
+        dialog.connect_response(glib::clone!(@weak parent => move |_, response_type| {
+            let card_id = match CardBrowser::get_selected_card_id(&tree_view) {
+                Some(card_id) => card_id,
+                None => { return; },
+            };
+            match response_type {
+                gtk::ResponseType::Accept => CardNotebook::replace(&parent, card_id),
+                gtk::ResponseType::Apply => {
+                    CardEditor::show(&parent, card_id);
+                    if let Err(err) = CardBrowser::fill_store(&repository, &store, &languages) {
+                        ErrorDialog::show(&parent, &err.to_string());
+                    }
+                },
+                _ => {},
+            }
+        }));
+        Ok(dialog)
+    }
+
+    /// Show the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show(parent: &gtk::ApplicationWindow) {
+        let dialog = match CardBrowser::build(parent) {
+            Ok(dialog) => dialog,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        dialog.show_all();
+        // Editing a card keeps the dialog open
+        while dialog.run() == gtk::ResponseType::Apply {}
+        dialog.close();
+    }
+}
@@ -15,12 +15,12 @@ use gtk::{
     prelude::ComboBoxExtManual,
 };
 
-use crate::database::{DatabaseError, get_connection};
-use crate::models::Model;
+use crate::database::DatabaseError;
 use crate::models::card::Card;
-use crate::models::category::Category;
 use crate::models::language::Language;
 use crate::models::translation::Translation;
+use crate::repository::Repository;
+use crate::ui::UI;
 use crate::ui::widgets::cardnotebook::CardNotebook;
 use super::error::Error as ErrorDialog;
 
@@ -42,21 +42,19 @@ impl CardEditor {
     ///
     /// # Arguments
     ///
-    /// * `conn` - Connection to the database
+    /// * `repository` - The repository of flash cards
     /// * `selected` - The identifier of the selected category. Set to 0 to have the `default`
-    ///                category selected
+    ///   category selected
     fn build_category(
-        conn: &sqlite::Connection,
+        repository: &Repository,
         selected: i64,
     ) -> Result<gtk::ComboBoxText, DatabaseError> {
         let combo = gtk::ComboBoxText::new();
-        let categories = Category::load_all(&conn)?;
+        let categories = repository.categories()?;
         let mut active_idx = 0;
         for (idx, category) in categories.iter().enumerate() {
             combo.append_text(&category.name);
-            if category.id == selected {
-                active_idx = idx;
-            } else if selected == 0 && category.name == "default" {
+            if category.id == selected || (selected == 0 && category.name == "default") {
                 active_idx = idx;
             }
         }
@@ -68,24 +66,23 @@ impl CardEditor {
     ///
     /// # Arguments
     ///
-    /// * `conn` - Connection to the database
+    /// * `repository` - The repository of flash cards
     /// * `card_id` - Identifier of the card for which to get translations
-    /// * `languages` - Vector of languages for which to get translations
+    /// * `languages` - Vector of languages for which to get translations, in display order
     fn build_translations(
-        conn: &sqlite::Connection,
+        repository: &Repository,
         card_id: i64,
-        languages: &Vec<Language>,
+        languages: &[Language],
     ) -> Result<gtk::Grid, DatabaseError> {
         let grid = gtk::Grid::new();
         grid.set_column_spacing(5);
         grid.set_row_spacing(10);
-        for language in languages {
-            let translation = Translation::load_for_card_language(
-                &conn, card_id, language.id)?;
+        for (idx, language) in languages.iter().enumerate() {
+            let translation = repository.translation(card_id, language.id)?;
             let label = gtk::Label::new(Some(&language.name));
             label.set_halign(gtk::Align::Start);
-            // Map language id to top: 1 -> 0, 1 ; 2 -> 2, 3 ; 3 -> 4, 5
-            let mut top = language.id as i32 * 2 - 2 ;
+            // Each language takes two rows, the label above the text and the description
+            let mut top = idx as i32 * 2;
             grid.attach(&label, 0, top, 1, 1);
             let text = gtk::Entry::new();
             text.set_text(&translation.text);
@@ -120,10 +117,10 @@ impl CardEditor {
                 ("_Cancel", gtk::ResponseType::Cancel),
             ],
         );
-        let conn = get_connection()?;
-        let card = CardEditor::get_card(&conn, card_id)?;
+        let repository = UI::get_repository(parent)?;
+        let card = repository.card(card_id)?;
         let spacing = 10;
-        let languages = Language::load_all(&conn)?;
+        let languages = repository.languages()?;
 
         let content = dialog.get_content_area();
         content.set_margin_start(spacing as i32);
@@ -133,10 +130,10 @@ impl CardEditor {
         label.set_halign(gtk::Align::Start);
         content.pack_start(&label, false, false, spacing);
 
-        let category = CardEditor::build_category(&conn, card.category_id)?;
+        let category = CardEditor::build_category(&repository, card.category_id)?;
         content.pack_start(&category, false, false, spacing);
 
-        let translations = CardEditor::build_translations(&conn, card.id, &languages)?;
+        let translations = CardEditor::build_translations(&repository, card.id, &languages)?;
         content.pack_start(&translations, false, false, spacing);
 
         let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
@@ -145,7 +142,7 @@ impl CardEditor {
         dialog.connect_response(glib::clone!(@weak parent => move |_, response_type| {
             if response_type == gtk::ResponseType::Accept {
                 CardEditor::response_accept(
-                    &parent, &conn, card.id, &languages, &category, &translations);
+                    &parent, &repository, card.id, &languages, &category, &translations);
             }
         }));
         Ok(dialog)
@@ -155,26 +152,19 @@ impl CardEditor {
     ///
     /// # Arguments
     ///
-    /// * `conn` - Connection to the database
+    /// * `repository` - The repository of flash cards
     /// * `category_widget` - Widget which holds the category
     /// * `card_id` - Identifier of the card which was accepted
     fn accept_category(
-        conn: &sqlite::Connection,
+        repository: &Repository,
         category_widget: &gtk::ComboBoxText,
         card_id: i64,
     ) -> Result<Card, Box<dyn Error>> {
-        let mut card = CardEditor::get_card(&conn, card_id)?;
-        let category_id = match category_widget.get_active_text() {
-            Some(name)  => {
-                let category = Category::load_by_name(&conn, name.to_string())?;
-                category.id
-            },
+        let mut card = repository.card(card_id)?;
+        card.category_id = match category_widget.get_active_text() {
+            Some(name)  => repository.category_by_name(&name)?.id,
             None => Err("No category selected!")?,
         };
-        if category_id != card.category_id {
-            card.category_id = category_id;
-            card.save(&conn)?;
-        }
         Ok(card)
     }
 
@@ -182,12 +172,12 @@ impl CardEditor {
     ///
     /// # Arguments
     ///
-    /// * `conn` - Connection to the database
+    /// * `repository` - The repository of flash cards
     /// * `card_id` - Identifier of the card which was accepted
     /// * `translations_widget` - Widget which holds all translation texts & descriptions
     /// * `language` - Language to process
     fn accept_translation(
-        conn: &sqlite::Connection,
+        repository: &Repository,
         card_id: i64,
         translations_widget: &gtk::Grid,
         language: &Language,
@@ -212,66 +202,47 @@ impl CardEditor {
                 };
             }
         }
-        let mut translation = Translation::load_for_card_language(
-            &conn, card_id, language.id)?;
+        let mut translation = repository.translation(card_id, language.id)?;
         translation.text = text;
         translation.description = description;
-        translation.save(&conn)?;
         Ok(translation)
     }
 
-    /// Get Card by given card id
-    ///
-    /// # Arguments
-    ///
-    /// * `conn` - Connection to the database
-    /// * `card_id` - Identifier of the card to get. If 0, an empty card will be retrieved.
-    fn get_card(conn: &sqlite::Connection, card_id: i64) -> Result<Card, DatabaseError> {
-        let card = if card_id == 0 {
-            Card::from_empty()
-        } else {
-            Card::load(&conn, card_id)?
-        };
-        Ok(card)
-    }
-
     /// When the dialog is accepted, respond by saving the provided data into a new card and replace
     /// the currently shown card
     ///
     /// # Arguments
     ///
     /// * `parent` - The parent widget of the dialog aka application window
-    /// * `conn`- Connection to the database
+    /// * `repository` - The repository of flash cards
     /// * `card_id` - Identifier of the card to handle, cannot be a `Card` because of:
-    ///               ```cannot borrow `card` as mutable, as it is a captured variable in a `Fn` closure```
+    ///   ```cannot borrow `card` as mutable, as it is a captured variable in a `Fn` closure```
     /// * `languages` - A vector with all supported languages
     /// * `category_widget` - The widget which holds the accepted category
     /// * `translations_widget` - The widget which holds the accepted translations
     fn response_accept(
         parent: &gtk::ApplicationWindow,
-        conn: &sqlite::Connection,
+        repository: &Repository,
         card_id: i64,
         languages: &Vec<Language>,
         category_widget: &gtk::ComboBoxText,
         translations_widget: &gtk::Grid,
     ) {
-        let card = match CardEditor::accept_category(&conn, &category_widget, card_id) {
-            Ok(card) => card,
-            Err(err) => {
-                ErrorDialog::show(&parent, &err.to_string());
-                return;
-            }
-        };
-        for language in languages {
-            match CardEditor::accept_translation(&conn, card.id, &translations_widget, &language) {
-                Err(err) => {
-                    ErrorDialog::show(&parent, &err.to_string());
-                    return;
-                },
-                _ => {},
-            }
+        let result = CardEditor::accept_category(repository, category_widget, card_id).and_then(
+            |mut card| -> Result<i64, Box<dyn Error>> {
+                let mut translations = Vec::new();
+                for language in languages {
+                    translations.push(CardEditor::accept_translation(
+                        repository, card.id, translations_widget, language)?);
+                }
+                // The whole card is saved or nothing, so a failing translation leaves no
+                // half-edited card
+                Ok(repository.save_card(&mut card, &mut translations)?)
+            });
+        match result {
+            Ok(card_id) => CardNotebook::replace(parent, card_id),
+            Err(err) => ErrorDialog::show(parent, &err.to_string()),
         }
-        CardNotebook::replace(&parent, card.id);
     }
 
     /// Show the dialog for card given by id
@@ -280,11 +251,11 @@ impl CardEditor {
     ///
     /// * `parent` - The GTK application window
     /// * `card_id` - Identifier of the card for which to show the dialog
-    fn show(parent: &gtk::ApplicationWindow, card_id: i64) {
+    pub fn show(parent: &gtk::ApplicationWindow, card_id: i64) {
         let dialog = match CardEditor::build(parent, card_id) {
             Ok(dialog) => dialog,
             Err(err) => {
-                ErrorDialog::show(&parent, &err.to_string());
+                ErrorDialog::show(parent, &err.to_string());
                 return;
             },
         };
@@ -308,10 +279,10 @@ impl CardEditor {
     ///
     /// * `parent` - The GTK application window
     pub fn show_edit(parent: &gtk::ApplicationWindow) {
-        let card_id = match CardNotebook::get_card_id(&parent) {
+        let card_id = match CardNotebook::get_card_id(parent) {
             Ok(id) => id,
             Err(err) => {
-                ErrorDialog::show(&parent, &err.to_string());
+                ErrorDialog::show(parent, &err.to_string());
                 return;
             }
         };
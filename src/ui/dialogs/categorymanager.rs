@@ -0,0 +1,341 @@
+//! Module for the dialog to manage the categories of flash cards
+
+use std::error::Error;
+use std::rc::Rc;
+
+use gtk::{
+    BoxExt,
+    ButtonExt,
+    CellLayoutExt,
+    CellRendererTextExt,
+    ComboBoxExt,
+    ComboBoxTextExt,
+    ContainerExt,
+    DialogExt,
+    EntryExt,
+    GtkListStoreExt,
+    GtkWindowExt,
+    ScrolledWindowExt,
+    TreeModelExt,
+    TreeSelectionExt,
+    TreeViewColumnExt,
+    TreeViewExt,
+    WidgetExt,
+    prelude::GtkListStoreExtManual,
+};
+
+use crate::database::DatabaseError;
+use crate::models::category::Category;
+use crate::repository::Repository;
+use crate::ui::UI;
+use crate::ui::widgets::cardnotebook::CardNotebook;
+use super::error::Error as ErrorDialog;
+
+
+/// A dialog to create, rename, merge and delete categories
+pub struct CategoryManager;
+
+/// Implementation of the dialog to manage categories
+impl CategoryManager {
+    /// Column of the category id in the list store
+    const COLUMN_ID: i32 = 0;
+    /// Column of the category name in the list store
+    const COLUMN_NAME: i32 = 1;
+    /// Column of the number of cards in the list store
+    const COLUMN_CARDS: i32 = 2;
+
+    /// Fill the list store and the combo box to merge into with all categories
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `store` - The list store to fill
+    /// * `merge_into` - The combo box to choose the category to merge into
+    fn fill(
+        repository: &Repository,
+        store: &gtk::ListStore,
+        merge_into: &gtk::ComboBoxText,
+    ) -> Result<(), DatabaseError> {
+        store.clear();
+        merge_into.remove_all();
+        for category in repository.categories()? {
+            let cards = repository.count_cards(category.id)?;
+            store.insert_with_values(
+                None,
+                &[
+                    CategoryManager::COLUMN_ID as u32,
+                    CategoryManager::COLUMN_NAME as u32,
+                    CategoryManager::COLUMN_CARDS as u32,
+                ],
+                &[&category.id, &category.name, &cards],
+            );
+            merge_into.append(Some(&category.id.to_string()), &category.name);
+        }
+        Ok(())
+    }
+
+    /// Fill the dialog again and show the current card again, as its category may have changed
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `repository` - The repository of flash cards
+    /// * `store` - The list store to fill
+    /// * `merge_into` - The combo box to choose the category to merge into
+    fn refresh(
+        parent: &gtk::ApplicationWindow,
+        repository: &Repository,
+        store: &gtk::ListStore,
+        merge_into: &gtk::ComboBoxText,
+    ) {
+        if let Err(err) = CategoryManager::fill(repository, store, merge_into) {
+            ErrorDialog::show(parent, &err.to_string());
+        }
+        if let Ok(card_id) = CardNotebook::get_card_id(parent) {
+            CardNotebook::replace(parent, card_id);
+        }
+    }
+
+    /// Get the id of the selected category
+    ///
+    /// # Arguments
+    ///
+    /// * `tree_view` - The tree view which shows the categories
+    fn get_selected_id(tree_view: &gtk::TreeView) -> Result<i64, Box<dyn Error>> {
+        let selected = tree_view.get_selection().get_selected().and_then(|(model, iter)| {
+            model.get_value(&iter, CategoryManager::COLUMN_ID).get_some::<i64>().ok()
+        });
+        match selected {
+            Some(id) => Ok(id),
+            None => Err("No category selected!")?,
+        }
+    }
+
+    /// Check a category's name: it must not be empty and no other category may have it
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `name` - The name to check
+    /// * `category_id` - Identifier of the category to name; 0 for a new category
+    fn check_name(
+        repository: &Repository,
+        name: &str,
+        category_id: i64,
+    ) -> Result<String, Box<dyn Error>> {
+        let name = name.trim();
+        if name.is_empty() {
+            Err("The name of a category must not be empty!")?;
+        }
+        match repository.category_by_name(name) {
+            Ok(category) if category.id != category_id => {
+                Err(format!("A category named '{}' exists already!", name))?
+            },
+            Ok(_) | Err(DatabaseError::NotFound) => Ok(name.to_string()),
+            Err(err) => Err(err)?,
+        }
+    }
+
+    /// Create a new category
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `name` - Name of the new category
+    fn add(repository: &Repository, name: &str) -> Result<(), Box<dyn Error>> {
+        let mut category = Category {
+            id: 0,
+            name: CategoryManager::check_name(repository, name, 0)?,
+        };
+        repository.save_category(&mut category)?;
+        Ok(())
+    }
+
+    /// Rename a category
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `category_id` - Identifier of the category to rename
+    /// * `name` - New name of the category
+    fn rename(repository: &Repository, category_id: i64, name: &str) -> Result<(), Box<dyn Error>> {
+        let mut category = repository.category(category_id)?;
+        category.name = CategoryManager::check_name(repository, name, category_id)?;
+        repository.save_category(&mut category)?;
+        Ok(())
+    }
+
+    /// Merge the selected category into the chosen one
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `tree_view` - The tree view which shows the categories
+    /// * `merge_into` - The combo box with the category to merge into
+    fn merge(
+        repository: &Repository,
+        tree_view: &gtk::TreeView,
+        merge_into: &gtk::ComboBoxText,
+    ) -> Result<(), Box<dyn Error>> {
+        let category_id = CategoryManager::get_selected_id(tree_view)?;
+        let into_id = match merge_into.get_active_id().and_then(|id| id.parse::<i64>().ok()) {
+            Some(id) => id,
+            None => Err("No category to merge into selected!")?,
+        };
+        if category_id == into_id {
+            Err("A category cannot be merged into itself!")?;
+        }
+        repository.merge_categories(category_id, into_id)?;
+        Ok(())
+    }
+
+    /// Build the tree view which lists the categories with their number of cards
+    ///
+    /// Names can be edited in place to rename the categories.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `repository` - The repository of flash cards
+    /// * `store` - The list store of the categories
+    /// * `merge_into` - The combo box to choose the category to merge into
+    fn build_tree_view(
+        parent: &gtk::ApplicationWindow,
+        repository: &Rc<Repository>,
+        store: &gtk::ListStore,
+        merge_into: &gtk::ComboBoxText,
+    ) -> gtk::TreeView {
+        let tree_view = gtk::TreeView::with_model(store);
+
+        let renderer = gtk::CellRendererText::new();
+        renderer.set_property_editable(true);
+        renderer.connect_edited(glib::clone!(
+            @weak parent, @weak store, @weak merge_into, @strong repository => move |_, path, name| {
+                let category_id = store.get_iter(&path).and_then(|iter| {
+                    store.get_value(&iter, CategoryManager::COLUMN_ID).get_some::<i64>().ok()
+                });
+                if let Some(category_id) = category_id {
+                    match CategoryManager::rename(&repository, category_id, name) {
+                        Ok(_) => CategoryManager::refresh(&parent, &repository, &store, &merge_into),
+                        Err(err) => ErrorDialog::show(&parent, &err.to_string()),
+                    }
+                }
+            }
+        ));
+        let column = gtk::TreeViewColumn::new();
+        column.set_title("Name (double-click to rename)");
+        column.pack_start(&renderer, true);
+        column.add_attribute(&renderer, "text", CategoryManager::COLUMN_NAME);
+        column.set_expand(true);
+        tree_view.append_column(&column);
+
+        let renderer = gtk::CellRendererText::new();
+        let column = gtk::TreeViewColumn::new();
+        column.set_title("Cards");
+        column.pack_start(&renderer, true);
+        column.add_attribute(&renderer, "text", CategoryManager::COLUMN_CARDS);
+        tree_view.append_column(&column);
+        tree_view
+    }
+
+    /// Build the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    fn build(parent: &gtk::ApplicationWindow) -> Result<gtk::Dialog, DatabaseError> {
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Categories"),
+            Some(parent),
+            gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+            &[("_Close", gtk::ResponseType::Close)],
+        );
+        let repository = UI::get_repository(parent)?;
+        let spacing = 10;
+        dialog.set_default_size(400, 400);
+
+        let content = dialog.get_content_area();
+        content.set_margin_start(spacing as i32);
+        content.set_margin_end(spacing as i32);
+
+        let store = gtk::ListStore::new(&[glib::Type::I64, glib::Type::String, glib::Type::I64]);
+        let merge_into = gtk::ComboBoxText::new();
+        CategoryManager::fill(&repository, &store, &merge_into)?;
+
+        let tree_view = CategoryManager::build_tree_view(parent, &repository, &store, &merge_into);
+        let scrolled = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.add(&tree_view);
+        content.pack_start(&scrolled, true, true, spacing);
+
+        let add_box = gtk::Box::new(gtk::Orientation::Horizontal, spacing as i32);
+        let name = gtk::Entry::new();
+        name.set_placeholder_text(Some("Name of a new category ..."));
+        add_box.pack_start(&name, true, true, 0);
+        let add = gtk::Button::with_label("Add");
+        add_box.pack_start(&add, false, false, 0);
+        content.pack_start(&add_box, false, false, 0);
+
+        let merge_box = gtk::Box::new(gtk::Orientation::Horizontal, spacing as i32);
+        let label = gtk::Label::new(Some("Selected category:"));
+        merge_box.pack_start(&label, false, false, 0);
+        let merge = gtk::Button::with_label("Merge into");
+        merge_box.pack_start(&merge, false, false, 0);
+        merge_box.pack_start(&merge_into, true, true, 0);
+        let delete = gtk::Button::with_label("Delete");
+        merge_box.pack_end(&delete, false, false, 0);
+        content.pack_start(&merge_box, false, false, spacing);
+
+        let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
+        content.pack_end(&separator, false, false, spacing);
+
+        add.connect_clicked(glib::clone!(
+            @weak parent, @weak store, @weak merge_into, @weak name, @strong repository => move |_| {
+                match CategoryManager::add(&repository, &name.get_text()) {
+                    Ok(_) => {
+                        name.set_text("");
+                        CategoryManager::refresh(&parent, &repository, &store, &merge_into);
+                    },
+                    Err(err) => ErrorDialog::show(&parent, &err.to_string()),
+                }
+            }
+        ));
+        merge.connect_clicked(glib::clone!(
+            @weak parent, @weak store, @weak merge_into, @weak tree_view, @strong repository => move |_| {
+                match CategoryManager::merge(&repository, &tree_view, &merge_into) {
+                    Ok(_) => CategoryManager::refresh(&parent, &repository, &store, &merge_into),
+                    Err(err) => ErrorDialog::show(&parent, &err.to_string()),
+                }
+            }
+        ));
+        delete.connect_clicked(glib::clone!(
+            @weak parent, @weak store, @weak merge_into, @weak tree_view, @strong repository => move |_| {
+                let result = CategoryManager::get_selected_id(&tree_view).and_then(
+                    |category_id| Ok(repository.delete_category(category_id)?));
+                match result {
+                    Ok(_) => CategoryManager::refresh(&parent, &repository, &store, &merge_into),
+                    Err(err) => ErrorDialog::show(&parent, &err.to_string()),
+                }
+            }
+        ));
+        Ok(dialog)
+    }
+
+    /// Show the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show(parent: &gtk::ApplicationWindow) {
+        let dialog = match CategoryManager::build(parent) {
+            Ok(dialog) => dialog,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        dialog.show_all();
+        dialog.run();
+        dialog.close();
+    }
+}
@@ -0,0 +1,115 @@
+//! Module for the dialogs to create and open database files, i.e. decks of flash cards
+
+use std::fs;
+use std::path::PathBuf;
+
+use gtk::{DialogExt, FileChooserExt, GtkWindowExt};
+
+use crate::repository::Repository;
+use crate::ui::UI;
+use super::error::Error as ErrorDialog;
+
+
+/// Dialogs to choose a database file to create or to open
+pub struct DeckFile;
+
+/// Implementation of the dialogs to choose a database file
+impl DeckFile {
+    /// Build the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `title` - The title of the dialog
+    /// * `action` - Whether to choose a new or an existing file
+    fn build(
+        parent: &gtk::ApplicationWindow,
+        title: &str,
+        action: gtk::FileChooserAction,
+    ) -> gtk::FileChooserDialog {
+        let accept = match action {
+            gtk::FileChooserAction::Save => "_Create",
+            _ => "_Open",
+        };
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some(title),
+            Some(parent),
+            action,
+            &[
+                (accept, gtk::ResponseType::Accept),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        dialog.set_modal(true);
+        dialog.set_do_overwrite_confirmation(true);
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("SQLite databases"));
+        filter.add_pattern("*.sqlite");
+        filter.add_pattern("*.db");
+        dialog.add_filter(&filter);
+        dialog
+    }
+
+    /// Run the dialog and get the chosen file, if any
+    ///
+    /// # Arguments
+    ///
+    /// * `dialog` - The dialog to run
+    fn run(dialog: &gtk::FileChooserDialog) -> Option<PathBuf> {
+        let path = match dialog.run() {
+            gtk::ResponseType::Accept => dialog.get_filename(),
+            _ => None,
+        };
+        dialog.close();
+        path
+    }
+
+    /// Show the dialog to create a new database file and open it
+    ///
+    /// An existing file is replaced after confirmation, unless it is the open database. The new
+    /// database has the schema only, without the example cards.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show_new(parent: &gtk::ApplicationWindow) {
+        let dialog = DeckFile::build(parent, "New Deck", gtk::FileChooserAction::Save);
+        dialog.set_current_name("deck.sqlite");
+        let path = match DeckFile::run(&dialog) {
+            Some(path) => path,
+            None => { return; },
+        };
+        if path.exists() {
+            let is_open = UI::get_repository(parent).ok()
+                .and_then(|repository| fs::canonicalize(repository.path()).ok())
+                .as_ref() == Some(&path);
+            if is_open {
+                ErrorDialog::show(parent, "The open deck cannot be replaced by a new one.");
+                return;
+            }
+            if let Err(err) = fs::remove_file(&path) {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            }
+        }
+        let db_path = path.to_string_lossy();
+        // Only the schema, a new deck starts without the example cards
+        if let Err(err) = Repository::create(&db_path) {
+            ErrorDialog::show(parent, &err.to_string());
+            return;
+        }
+        UI::open_deck(parent, &db_path);
+    }
+
+    /// Show the dialog to choose an existing database file and open it
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show_open(parent: &gtk::ApplicationWindow) {
+        let dialog = DeckFile::build(parent, "Open Deck", gtk::FileChooserAction::Open);
+        if let Some(path) = DeckFile::run(&dialog) {
+            UI::open_deck(parent, &path.to_string_lossy());
+        }
+    }
+}
@@ -2,9 +2,7 @@
 
 use gtk::{BoxExt, DialogExt, GtkWindowExt, WidgetExt};
 
-use crate::database::get_connection;
-use crate::models::Model;
-use crate::models::card::Card;
+use crate::ui::UI;
 use crate::ui::widgets::cardnotebook::CardNotebook;
 use super::error::Error as ErrorDialog;
 
@@ -34,8 +32,8 @@ impl DeleteCard {
         let content = dialog.get_content_area();
         content.set_margin_start(spacing as i32);
         content.set_margin_end(spacing as i32);
-        let label = gtk::Label::new(
-            Some("Are you sure you want to delete the current card?"));
+        let label = gtk::Label::new(Some(
+            "Are you sure you want to delete the current card with its translations and review history?"));
         content.pack_start(&label, true, true, spacing);
         let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
         content.pack_end(&separator, false, false, spacing);
@@ -55,8 +53,8 @@ impl DeleteCard {
     ///
     /// * `parent` - The GTK application window
     fn response_accept(parent: &gtk::ApplicationWindow) {
-        let conn = match get_connection() {
-            Ok(conn) => conn,
+        let repository = match UI::get_repository(parent) {
+            Ok(repository) => repository,
             Err(err) => {
                 ErrorDialog::show(parent, &err.to_string());
                 return;
@@ -69,15 +67,12 @@ impl DeleteCard {
                 return;
             }
         };
-        match Card::delete(&conn, card_id) {
-            Err(err) => {
-                ErrorDialog::show(parent, &err.to_string());
-                return;
-            },
-            _ => {},
+        if let Err(err) = repository.delete_card(card_id) {
+            ErrorDialog::show(parent, &err.to_string());
+            return;
         }
-        match Card::random_id(&conn) {
-            Ok(id) => CardNotebook::replace(&parent, id),
+        match repository.random_card_id() {
+            Ok(id) => CardNotebook::replace(parent, id),
             Err(err) => ErrorDialog::show(parent, &err.to_string()),
         }
     }
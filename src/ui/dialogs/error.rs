@@ -26,4 +26,23 @@ impl Error {
         dialog.run();
         dialog.close();
     }
+
+    /// Show the dialog without a parent, e.g. if the application window could not be built
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The GTK application, which keeps running while the dialog is shown
+    /// * `message` - String slice holding the error message
+    pub fn show_without_parent(app: &gtk::Application, message: &str) {
+        let dialog = gtk::MessageDialog::new(
+           None::<&gtk::Window>,
+           gtk::DialogFlags::MODAL,
+           gtk::MessageType::Error,
+           gtk::ButtonsType::Close,
+           message,
+        );
+        dialog.set_application(Some(app));
+        dialog.run();
+        dialog.close();
+    }
 }
\ No newline at end of file
@@ -0,0 +1,207 @@
+//! Module for the dialog to export flash cards to CSV, TSV or JSON files
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use gtk::{
+    BoxExt,
+    ComboBoxExt,
+    ComboBoxTextExt,
+    ContainerExt,
+    DialogExt,
+    FileChooserExt,
+    GtkWindowExt,
+    ScrolledWindowExt,
+    ToggleButtonExt,
+    WidgetExt,
+};
+
+use crate::database::DatabaseError;
+use crate::export::Format;
+use crate::models::filter::Filter;
+use crate::repository::Repository;
+use crate::ui::UI;
+use super::error::Error as ErrorDialog;
+use super::info::Info as InfoDialog;
+use super::studyfilter::StudyFilter;
+
+
+/// A dialog to export flash cards, restricted to chosen categories and languages
+pub struct ExportCards;
+
+/// Implementation of the dialog to export flash cards
+impl ExportCards {
+    /// Build the combo box to choose the format
+    fn build_format() -> gtk::ComboBoxText {
+        let combo = gtk::ComboBoxText::new();
+        for format in Format::ALL.iter() {
+            combo.append(Some(format.name()), format.label());
+        }
+        combo.set_active_id(Some(Format::Csv.name()));
+        combo
+    }
+
+    /// Build the dialog
+    ///
+    /// The categories and languages of the study filter are checked initially.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    fn build(parent: &gtk::ApplicationWindow) -> Result<gtk::Dialog, DatabaseError> {
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Export Cards"),
+            Some(parent),
+            gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+            &[
+                ("_Export", gtk::ResponseType::Accept),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        let repository = UI::get_repository(parent)?;
+        let filter = repository.filter()?;
+        let spacing = 10;
+        dialog.set_default_size(300, 400);
+
+        let content = dialog.get_content_area();
+        content.set_margin_start(spacing as i32);
+        content.set_margin_end(spacing as i32);
+
+        let label = gtk::Label::new(Some("Format"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let format = ExportCards::build_format();
+        content.pack_start(&format, false, false, spacing);
+
+        let label = gtk::Label::new(Some("Categories (none checked for all)"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let categories = repository.categories()?.into_iter()
+            .map(|category| (category.id, category.name))
+            .collect();
+        let (container, category_buttons) = StudyFilter::build_check_buttons(
+            categories, &filter.category_ids, gtk::Orientation::Vertical);
+        let scrolled = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.add(&container);
+        content.pack_start(&scrolled, true, true, spacing);
+
+        let label = gtk::Label::new(Some("Languages (none checked for all)"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let languages = repository.languages()?.into_iter()
+            .map(|language| (language.id, language.name))
+            .collect();
+        let (container, language_buttons) = StudyFilter::build_check_buttons(
+            languages, &filter.language_ids, gtk::Orientation::Horizontal);
+        content.pack_start(&container, false, false, spacing);
+
+        let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
+        content.pack_end(&separator, false, false, spacing);
+
+        dialog.connect_response(glib::clone!(@weak parent => move |dialog, response_type| {
+            if response_type == gtk::ResponseType::Accept {
+                // Hide this dialog, so that it does not cover the file chooser
+                dialog.hide();
+                ExportCards::response_accept(
+                    &parent, &repository, &format, &category_buttons, &language_buttons);
+            }
+        }));
+        Ok(dialog)
+    }
+
+    /// Show the dialog to choose the file to export to
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `repository` - The repository of flash cards
+    /// * `format` - The format to export to
+    fn choose_file(
+        parent: &gtk::ApplicationWindow,
+        repository: &Repository,
+        format: Format,
+    ) -> Option<PathBuf> {
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some("Export Cards"),
+            Some(parent),
+            gtk::FileChooserAction::Save,
+            &[
+                ("_Save", gtk::ResponseType::Accept),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        dialog.set_modal(true);
+        dialog.set_do_overwrite_confirmation(true);
+        let stem = Path::new(repository.path()).file_stem()
+            .map_or("cards".to_string(), |stem| stem.to_string_lossy().to_string());
+        dialog.set_current_name(format!("{}.{}", stem, format.name()));
+        let path = match dialog.run() {
+            gtk::ResponseType::Accept => dialog.get_filename(),
+            _ => None,
+        };
+        dialog.close();
+        path
+    }
+
+    /// When the dialog is accepted, choose a file and export the cards to it
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `repository` - The repository of flash cards
+    /// * `format_widget` - The widget which holds the format
+    /// * `category_widgets` - The widgets which hold the categories by category id
+    /// * `language_widgets` - The widgets which hold the languages by language id
+    fn response_accept(
+        parent: &gtk::ApplicationWindow,
+        repository: &Repository,
+        format_widget: &gtk::ComboBoxText,
+        category_widgets: &[(i64, gtk::CheckButton)],
+        language_widgets: &[(i64, gtk::CheckButton)],
+    ) {
+        let checked = |widgets: &[(i64, gtk::CheckButton)]| -> Vec<i64> {
+            widgets.iter()
+                .filter(|(_, button)| button.get_active())
+                .map(|(id, _)| *id)
+                .collect()
+        };
+        let filter = Filter {
+            category_ids: checked(category_widgets),
+            language_ids: checked(language_widgets),
+        };
+        let format = format_widget.get_active_id()
+            .and_then(|name| Format::from_name(&name))
+            .unwrap_or(Format::Csv);
+        let path = match ExportCards::choose_file(parent, repository, format) {
+            Some(path) => path,
+            None => { return; },
+        };
+        let result = repository.export_cards(format, &filter)
+            .map_err(|err| err.to_string())
+            .and_then(|text| fs::write(&path, text).map_err(|err| err.to_string()));
+        match result {
+            Ok(_) => InfoDialog::show(
+                parent, &format!("Exported the cards to {}.", path.to_string_lossy())),
+            Err(err) => ErrorDialog::show(parent, &err),
+        }
+    }
+
+    /// Show the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show(parent: &gtk::ApplicationWindow) {
+        let dialog = match ExportCards::build(parent) {
+            Ok(dialog) => dialog,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        dialog.show_all();
+        dialog.run();
+        dialog.close();
+    }
+}
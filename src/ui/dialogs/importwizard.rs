@@ -0,0 +1,333 @@
+//! Module for the wizard to import flash cards from CSV, TSV or JSON files
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use gtk::{
+    BoxExt,
+    ComboBoxExt,
+    ComboBoxTextExt,
+    ContainerExt,
+    DialogExt,
+    FileChooserExt,
+    GridExt,
+    GtkWindowExt,
+    LabelExt,
+    ScrolledWindowExt,
+    ToggleButtonExt,
+    WidgetExt,
+};
+
+use crate::database::DatabaseError;
+use crate::import::{Column, parse_file};
+use crate::models::language::Language;
+use crate::repository::Repository;
+use crate::ui::UI;
+use crate::ui::widgets::cardnotebook::CardNotebook;
+use super::error::Error as ErrorDialog;
+use super::info::Info as InfoDialog;
+
+
+/// A wizard to import flash cards: choose a file, map its columns and see a summary
+pub struct ImportWizard;
+
+/// Implementation of the wizard to import flash cards
+impl ImportWizard {
+    /// Number of rows shown in the preview
+    const PREVIEW_ROWS: usize = 5;
+
+    /// Show the dialog to choose the file to import
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    fn choose_file(parent: &gtk::ApplicationWindow) -> Option<PathBuf> {
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some("Import Cards"),
+            Some(parent),
+            gtk::FileChooserAction::Open,
+            &[
+                ("_Next", gtk::ResponseType::Accept),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        dialog.set_modal(true);
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("CSV, TSV and JSON files"));
+        filter.add_pattern("*.csv");
+        filter.add_pattern("*.tsv");
+        filter.add_pattern("*.txt");
+        filter.add_pattern("*.json");
+        dialog.add_filter(&filter);
+        let path = match dialog.run() {
+            gtk::ResponseType::Accept => dialog.get_filename(),
+            _ => None,
+        };
+        dialog.close();
+        path
+    }
+
+    /// Read and parse the file to import
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the file
+    fn read_rows(path: &Path) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        let rows = parse_file(path, &text)?;
+        Ok(rows)
+    }
+
+    /// Get the identifier of a column mapping in a combo box
+    ///
+    /// # Arguments
+    ///
+    /// * `column` - The column mapping
+    fn column_id(column: Column) -> String {
+        match column {
+            Column::Ignore => "ignore".to_string(),
+            Column::Text(language_id) => format!("text_{}", language_id),
+            Column::Description(language_id) => format!("description_{}", language_id),
+            Column::Category => "category".to_string(),
+        }
+    }
+
+    /// Get the column mapping from its identifier in a combo box
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The identifier, see `ImportWizard::column_id`
+    pub fn column_from_id(id: &str) -> Column {
+        let parse_id = |id: &str| id.parse::<i64>().ok();
+        if id == "category" {
+            Column::Category
+        } else if let Some(language_id) = id.strip_prefix("text_").and_then(parse_id) {
+            Column::Text(language_id)
+        } else if let Some(language_id) = id.strip_prefix("description_").and_then(parse_id) {
+            Column::Description(language_id)
+        } else {
+            Column::Ignore
+        }
+    }
+
+    /// Build the combo box to map a column
+    ///
+    /// # Arguments
+    ///
+    /// * `languages` - All languages of the database
+    /// * `selected` - The initially selected mapping
+    pub fn build_column(languages: &[Language], selected: Column) -> gtk::ComboBoxText {
+        let combo = gtk::ComboBoxText::new();
+        combo.append(Some(&ImportWizard::column_id(Column::Ignore)), "Ignore");
+        for language in languages {
+            combo.append(
+                Some(&ImportWizard::column_id(Column::Text(language.id))), &language.name);
+            combo.append(
+                Some(&ImportWizard::column_id(Column::Description(language.id))),
+                &format!("{} description", language.name),
+            );
+        }
+        combo.append(Some(&ImportWizard::column_id(Column::Category)), "Category");
+        combo.set_active_id(Some(&ImportWizard::column_id(selected)));
+        combo
+    }
+
+    /// Build the grid with a combo box per column above a preview of the first rows
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - All rows of the file
+    /// * `columns` - The initial mapping of the columns
+    /// * `languages` - All languages of the database
+    fn build_preview(
+        rows: &[Vec<String>],
+        columns: &[Column],
+        languages: &[Language],
+    ) -> (gtk::Grid, Vec<gtk::ComboBoxText>) {
+        let grid = gtk::Grid::new();
+        grid.set_column_spacing(10);
+        grid.set_row_spacing(5);
+        let mut combos = Vec::new();
+        for (idx, column) in columns.iter().enumerate() {
+            let combo = ImportWizard::build_column(languages, *column);
+            grid.attach(&combo, idx as i32, 0, 1, 1);
+            combos.push(combo);
+        }
+        for (top, row) in rows.iter().take(ImportWizard::PREVIEW_ROWS).enumerate() {
+            for (left, field) in row.iter().enumerate() {
+                let label = gtk::Label::new(Some(field));
+                label.set_halign(gtk::Align::Start);
+                label.set_line_wrap(true);
+                label.set_max_width_chars(20);
+                grid.attach(&label, left as i32, top as i32 + 1, 1, 1);
+            }
+        }
+        (grid, combos)
+    }
+
+    /// Build the combo box to choose the category of rows without one
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    pub fn build_category(repository: &Repository) -> Result<gtk::ComboBoxText, DatabaseError> {
+        let combo = gtk::ComboBoxText::new();
+        for (idx, category) in repository.categories()?.iter().enumerate() {
+            combo.append(Some(&category.id.to_string()), &category.name);
+            if idx == 0 || category.name == "default" {
+                combo.set_active_id(Some(&category.id.to_string()));
+            }
+        }
+        Ok(combo)
+    }
+
+    /// Build the dialog to map the columns of the file
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `path` - Path to the file to import
+    /// * `rows` - All rows of the file
+    fn build(
+        parent: &gtk::ApplicationWindow,
+        path: &Path,
+        rows: Vec<Vec<String>>,
+    ) -> Result<gtk::Dialog, DatabaseError> {
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Import Cards"),
+            Some(parent),
+            gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+            &[
+                ("_Import", gtk::ResponseType::Accept),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        let repository = UI::get_repository(parent)?;
+        let languages = repository.languages()?;
+        let spacing = 10;
+        dialog.set_default_size(600, 400);
+
+        let content = dialog.get_content_area();
+        content.set_margin_start(spacing as i32);
+        content.set_margin_end(spacing as i32);
+
+        let file_name = path.file_name().map_or(
+            path.to_string_lossy(), |name| name.to_string_lossy());
+        let label = gtk::Label::new(Some(&format!(
+            "Map the columns of {} ({} rows) to languages or the category.",
+            file_name, rows.len(),
+        )));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut columns: Vec<Column> = rows[0].iter()
+            .map(|header| Column::guess(header, &languages))
+            .collect();
+        columns.resize(width, Column::Ignore);
+        let has_header = columns.iter().any(|column| *column != Column::Ignore);
+        let header = gtk::CheckButton::with_label("The first row is a header");
+        header.set_active(has_header);
+        content.pack_start(&header, false, false, spacing);
+
+        let (grid, column_widgets) = ImportWizard::build_preview(&rows, &columns, &languages);
+        let scrolled = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+        scrolled.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Automatic);
+        scrolled.add(&grid);
+        content.pack_start(&scrolled, true, true, spacing);
+
+        let label = gtk::Label::new(Some("Category of rows without one"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let category = ImportWizard::build_category(&repository)?;
+        content.pack_start(&category, false, false, spacing);
+
+        let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
+        content.pack_end(&separator, false, false, spacing);
+
+        dialog.connect_response(glib::clone!(@weak parent => move |_, response_type| {
+            if response_type == gtk::ResponseType::Accept {
+                ImportWizard::response_accept(
+                    &parent, &repository, &rows, &header, &column_widgets, &category);
+            }
+        }));
+        Ok(dialog)
+    }
+
+    /// When the dialog is accepted, import the rows and show a summary
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `repository` - The repository of flash cards
+    /// * `rows` - All rows of the file
+    /// * `header_widget` - The widget which holds whether the first row is a header
+    /// * `column_widgets` - The widgets which hold the mapping of each column
+    /// * `category_widget` - The widget which holds the category of rows without one
+    fn response_accept(
+        parent: &gtk::ApplicationWindow,
+        repository: &Repository,
+        rows: &[Vec<String>],
+        header_widget: &gtk::CheckButton,
+        column_widgets: &[gtk::ComboBoxText],
+        category_widget: &gtk::ComboBoxText,
+    ) {
+        let columns: Vec<Column> = column_widgets.iter()
+            .map(|combo| combo.get_active_id().map_or(
+                Column::Ignore, |id| ImportWizard::column_from_id(&id)))
+            .collect();
+        if !columns.iter().any(|column| matches!(column, Column::Text(_))) {
+            ErrorDialog::show(parent, "Nothing imported: No column is mapped to a language.");
+            return;
+        }
+        let category_id = category_widget.get_active_id()
+            .and_then(|id| id.parse::<i64>().ok())
+            .unwrap_or(0);
+        let rows = if header_widget.get_active() { &rows[1..] } else { rows };
+        match repository.import_rows(rows, &columns, category_id) {
+            Ok(summary) => {
+                InfoDialog::show(parent, &format!(
+                    "Imported {} cards, skipped {} duplicates, {} rows failed.",
+                    summary.imported, summary.skipped, summary.failed,
+                ));
+                let card_id = CardNotebook::get_card_id(parent).unwrap_or(0);
+                CardNotebook::replace(parent, card_id);
+            },
+            Err(err) => ErrorDialog::show(parent, &err.to_string()),
+        }
+    }
+
+    /// Show the wizard
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show(parent: &gtk::ApplicationWindow) {
+        let path = match ImportWizard::choose_file(parent) {
+            Some(path) => path,
+            None => { return; },
+        };
+        let rows = match ImportWizard::read_rows(&path) {
+            Ok(rows) if rows.is_empty() => {
+                InfoDialog::show(parent, "The file has no rows to import.");
+                return;
+            },
+            Ok(rows) => rows,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        let dialog = match ImportWizard::build(parent, &path, rows) {
+            Ok(dialog) => dialog,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        dialog.show_all();
+        dialog.run();
+        dialog.close();
+    }
+}
@@ -0,0 +1,29 @@
+//! Module for the info dialog
+
+use gtk::{DialogExt, GtkWindowExt};
+
+
+/// A dialog to show an info window
+pub struct Info;
+
+/// Implementation of the dialog to show an info
+impl Info {
+
+    /// Show the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `message` - String slice holding the info message
+    pub fn show(parent: &gtk::ApplicationWindow, message: &str) {
+        let dialog = gtk::MessageDialog::new(
+           Some(parent),
+           gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+           gtk::MessageType::Info,
+           gtk::ButtonsType::Close,
+           message,
+        );
+        dialog.run();
+        dialog.close();
+    }
+}
\ No newline at end of file
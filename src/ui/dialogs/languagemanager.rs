@@ -0,0 +1,414 @@
+//! Module for the dialog to manage the languages of flash cards
+
+use std::error::Error;
+use std::rc::Rc;
+
+use gtk::{
+    BoxExt,
+    ButtonExt,
+    CellLayoutExt,
+    CellRendererTextExt,
+    ContainerExt,
+    DialogExt,
+    EntryExt,
+    GtkListStoreExt,
+    GtkWindowExt,
+    ScrolledWindowExt,
+    TreeModelExt,
+    TreeSelectionExt,
+    TreeViewColumnExt,
+    TreeViewExt,
+    WidgetExt,
+    prelude::GtkListStoreExtManual,
+};
+
+use crate::database::DatabaseError;
+use crate::models::Model;
+use crate::models::language::Language;
+use crate::repository::Repository;
+use crate::ui::UI;
+use crate::ui::widgets::cardnotebook::CardNotebook;
+use super::error::Error as ErrorDialog;
+
+
+/// A dialog to add, rename, reorder and remove languages
+pub struct LanguageManager;
+
+/// Implementation of the dialog to manage languages
+impl LanguageManager {
+    /// Column of the language id in the list store
+    const COLUMN_ID: i32 = 0;
+    /// Column of the language code in the list store
+    const COLUMN_CODE: i32 = 1;
+    /// Column of the language name in the list store
+    const COLUMN_NAME: i32 = 2;
+    /// Column of the number of translations in the list store
+    const COLUMN_TRANSLATIONS: i32 = 3;
+
+    /// Fill the list store with all languages in display order
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `store` - The list store to fill
+    fn fill(repository: &Repository, store: &gtk::ListStore) -> Result<(), DatabaseError> {
+        store.clear();
+        for language in repository.languages()? {
+            let translations = repository.count_translations(language.id)?;
+            store.insert_with_values(
+                None,
+                &[
+                    LanguageManager::COLUMN_ID as u32,
+                    LanguageManager::COLUMN_CODE as u32,
+                    LanguageManager::COLUMN_NAME as u32,
+                    LanguageManager::COLUMN_TRANSLATIONS as u32,
+                ],
+                &[&language.id, &language.code, &language.name, &translations],
+            );
+        }
+        Ok(())
+    }
+
+    /// Fill the dialog again, select the language with given id and show the current card again
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `repository` - The repository of flash cards
+    /// * `tree_view` - The tree view which shows the languages
+    /// * `store` - The list store to fill
+    /// * `selected_id` - Identifier of the language to select; 0 to select none
+    fn refresh(
+        parent: &gtk::ApplicationWindow,
+        repository: &Repository,
+        tree_view: &gtk::TreeView,
+        store: &gtk::ListStore,
+        selected_id: i64,
+    ) {
+        if let Err(err) = LanguageManager::fill(repository, store) {
+            ErrorDialog::show(parent, &err.to_string());
+        }
+        if let Some(iter) = store.get_iter_first() {
+            loop {
+                let id = store.get_value(&iter, LanguageManager::COLUMN_ID).get_some::<i64>();
+                if id == Ok(selected_id) {
+                    tree_view.get_selection().select_iter(&iter);
+                    break;
+                }
+                if !store.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+        if let Ok(card_id) = CardNotebook::get_card_id(parent) {
+            CardNotebook::replace(parent, card_id);
+        }
+    }
+
+    /// Get the id of the selected language
+    ///
+    /// # Arguments
+    ///
+    /// * `tree_view` - The tree view which shows the languages
+    fn get_selected_id(tree_view: &gtk::TreeView) -> Result<i64, Box<dyn Error>> {
+        let selected = tree_view.get_selection().get_selected().and_then(|(model, iter)| {
+            model.get_value(&iter, LanguageManager::COLUMN_ID).get_some::<i64>().ok()
+        });
+        match selected {
+            Some(id) => Ok(id),
+            None => Err("No language selected!")?,
+        }
+    }
+
+    /// Check a language's code and name and put them into the language
+    ///
+    /// The code must be an ISO 639-1 code which no other language has, the name must not be empty
+    /// and no other language may have it.
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `language` - The language to set code and name of
+    /// * `code` - The code to check
+    /// * `name` - The name to check
+    fn check(
+        repository: &Repository,
+        language: &mut Language,
+        code: &str,
+        name: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let code = code.trim().to_lowercase();
+        let name = name.trim();
+        if !Language::is_valid_code(&code) {
+            Err(format!("'{}' is not a two-letter ISO 639-1 language code!", code))?;
+        }
+        if name.is_empty() {
+            Err("The name of a language must not be empty!")?;
+        }
+        for other in repository.languages()? {
+            if other.id == language.id {
+                continue;
+            }
+            if other.code == code {
+                Err(format!("The language '{}' has the code '{}' already!", other.name, code))?;
+            }
+            if other.name == name {
+                Err(format!("A language named '{}' exists already!", name))?;
+            }
+        }
+        language.code = code;
+        language.name = name.to_string();
+        Ok(())
+    }
+
+    /// Add a new language after the last one
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `code` - Code of the new language
+    /// * `name` - Name of the new language
+    fn add(repository: &Repository, code: &str, name: &str) -> Result<i64, Box<dyn Error>> {
+        let mut language = Language::from_empty();
+        LanguageManager::check(repository, &mut language, code, name)?;
+        Ok(repository.save_language(&mut language)?)
+    }
+
+    /// Change the code or the name of a language
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `language_id` - Identifier of the language to change
+    /// * `column_id` - Column of the edited value, either the code or the name column
+    /// * `value` - The new code or name
+    fn edit(
+        repository: &Repository,
+        language_id: i64,
+        column_id: i32,
+        value: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut language = repository.language(language_id)?;
+        let (code, name) = if column_id == LanguageManager::COLUMN_CODE {
+            (value.to_string(), language.name.clone())
+        } else {
+            (language.code.clone(), value.to_string())
+        };
+        LanguageManager::check(repository, &mut language, &code, &name)?;
+        repository.save_language(&mut language)?;
+        Ok(())
+    }
+
+    /// Move the selected language up or down in the display order
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `tree_view` - The tree view which shows the languages
+    /// * `up` - Whether to move the language up, otherwise down
+    fn move_selected(
+        repository: &Repository,
+        tree_view: &gtk::TreeView,
+        up: bool,
+    ) -> Result<i64, Box<dyn Error>> {
+        let language_id = LanguageManager::get_selected_id(tree_view)?;
+        let mut ids: Vec<i64> = repository.languages()?.iter().map(|language| language.id).collect();
+        let idx = match ids.iter().position(|id| *id == language_id) {
+            Some(idx) => idx,
+            None => Err(DatabaseError::NotFound)?,
+        };
+        match (up, idx) {
+            (true, 0) => {},
+            (true, _) => ids.swap(idx, idx - 1),
+            (false, _) if idx + 1 < ids.len() => ids.swap(idx, idx + 1),
+            (false, _) => {},
+        }
+        repository.reorder_languages(&ids)?;
+        Ok(language_id)
+    }
+
+    /// Build an editable text column of the tree view
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `repository` - The repository of flash cards
+    /// * `tree_view` - The tree view to add the column to
+    /// * `store` - The list store of the languages
+    /// * `title` - Title of the column
+    /// * `column_id` - Column in the list store which holds the text
+    fn build_column(
+        parent: &gtk::ApplicationWindow,
+        repository: &Rc<Repository>,
+        tree_view: &gtk::TreeView,
+        store: &gtk::ListStore,
+        title: &str,
+        column_id: i32,
+    ) {
+        let renderer = gtk::CellRendererText::new();
+        renderer.set_property_editable(true);
+        renderer.connect_edited(glib::clone!(
+            @weak parent, @weak tree_view, @weak store, @strong repository => move |_, path, value| {
+                let language_id = store.get_iter(&path).and_then(|iter| {
+                    store.get_value(&iter, LanguageManager::COLUMN_ID).get_some::<i64>().ok()
+                });
+                if let Some(language_id) = language_id {
+                    match LanguageManager::edit(&repository, language_id, column_id, value) {
+                        Ok(_) => LanguageManager::refresh(
+                            &parent, &repository, &tree_view, &store, language_id),
+                        Err(err) => ErrorDialog::show(&parent, &err.to_string()),
+                    }
+                }
+            }
+        ));
+        let column = gtk::TreeViewColumn::new();
+        column.set_title(title);
+        column.pack_start(&renderer, true);
+        column.add_attribute(&renderer, "text", column_id);
+        column.set_expand(column_id == LanguageManager::COLUMN_NAME);
+        tree_view.append_column(&column);
+    }
+
+    /// Build the tree view which lists the languages with their number of translations
+    ///
+    /// Codes and names can be edited in place.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `repository` - The repository of flash cards
+    /// * `store` - The list store of the languages
+    fn build_tree_view(
+        parent: &gtk::ApplicationWindow,
+        repository: &Rc<Repository>,
+        store: &gtk::ListStore,
+    ) -> gtk::TreeView {
+        let tree_view = gtk::TreeView::with_model(store);
+        LanguageManager::build_column(
+            parent, repository, &tree_view, store, "Code", LanguageManager::COLUMN_CODE);
+        LanguageManager::build_column(
+            parent, repository, &tree_view, store, "Name", LanguageManager::COLUMN_NAME);
+
+        let renderer = gtk::CellRendererText::new();
+        let column = gtk::TreeViewColumn::new();
+        column.set_title("Translations");
+        column.pack_start(&renderer, true);
+        column.add_attribute(&renderer, "text", LanguageManager::COLUMN_TRANSLATIONS);
+        tree_view.append_column(&column);
+        tree_view.set_tooltip_text(Some("Double-click a code or a name to change it"));
+        tree_view
+    }
+
+    /// Build the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    fn build(parent: &gtk::ApplicationWindow) -> Result<gtk::Dialog, DatabaseError> {
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Languages"),
+            Some(parent),
+            gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+            &[("_Close", gtk::ResponseType::Close)],
+        );
+        let repository = UI::get_repository(parent)?;
+        let spacing = 10;
+        dialog.set_default_size(400, 400);
+
+        let content = dialog.get_content_area();
+        content.set_margin_start(spacing as i32);
+        content.set_margin_end(spacing as i32);
+
+        let store = gtk::ListStore::new(
+            &[glib::Type::I64, glib::Type::String, glib::Type::String, glib::Type::I64]);
+        LanguageManager::fill(&repository, &store)?;
+
+        let tree_view = LanguageManager::build_tree_view(parent, &repository, &store);
+        let scrolled = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.add(&tree_view);
+        content.pack_start(&scrolled, true, true, spacing);
+
+        let add_box = gtk::Box::new(gtk::Orientation::Horizontal, spacing as i32);
+        let code = gtk::Entry::new();
+        code.set_placeholder_text(Some("Code"));
+        code.set_max_length(2);
+        code.set_width_chars(4);
+        add_box.pack_start(&code, false, false, 0);
+        let name = gtk::Entry::new();
+        name.set_placeholder_text(Some("Name of a new language ..."));
+        add_box.pack_start(&name, true, true, 0);
+        let add = gtk::Button::with_label("Add");
+        add_box.pack_start(&add, false, false, 0);
+        content.pack_start(&add_box, false, false, 0);
+
+        let edit_box = gtk::Box::new(gtk::Orientation::Horizontal, spacing as i32);
+        let label = gtk::Label::new(Some("Selected language:"));
+        edit_box.pack_start(&label, false, false, 0);
+        let up = gtk::Button::with_label("Move up");
+        edit_box.pack_start(&up, false, false, 0);
+        let down = gtk::Button::with_label("Move down");
+        edit_box.pack_start(&down, false, false, 0);
+        let delete = gtk::Button::with_label("Delete");
+        edit_box.pack_end(&delete, false, false, 0);
+        content.pack_start(&edit_box, false, false, spacing);
+
+        let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
+        content.pack_end(&separator, false, false, spacing);
+
+        add.connect_clicked(glib::clone!(
+            @weak parent, @weak tree_view, @weak store, @weak code, @weak name,
+            @strong repository => move |_| {
+                match LanguageManager::add(&repository, &code.get_text(), &name.get_text()) {
+                    Ok(language_id) => {
+                        code.set_text("");
+                        name.set_text("");
+                        LanguageManager::refresh(&parent, &repository, &tree_view, &store, language_id);
+                    },
+                    Err(err) => ErrorDialog::show(&parent, &err.to_string()),
+                }
+            }
+        ));
+        for (button, is_up) in &[(up, true), (down, false)] {
+            let is_up = *is_up;
+            button.connect_clicked(glib::clone!(
+                @weak parent, @weak tree_view, @weak store, @strong repository => move |_| {
+                    match LanguageManager::move_selected(&repository, &tree_view, is_up) {
+                        Ok(language_id) => LanguageManager::refresh(
+                            &parent, &repository, &tree_view, &store, language_id),
+                        Err(err) => ErrorDialog::show(&parent, &err.to_string()),
+                    }
+                }
+            ));
+        }
+        delete.connect_clicked(glib::clone!(
+            @weak parent, @weak tree_view, @weak store, @strong repository => move |_| {
+                let result = LanguageManager::get_selected_id(&tree_view).and_then(
+                    |language_id| Ok(repository.delete_language(language_id)?));
+                match result {
+                    Ok(_) => LanguageManager::refresh(&parent, &repository, &tree_view, &store, 0),
+                    Err(err) => ErrorDialog::show(&parent, &err.to_string()),
+                }
+            }
+        ));
+        Ok(dialog)
+    }
+
+    /// Show the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show(parent: &gtk::ApplicationWindow) {
+        let dialog = match LanguageManager::build(parent) {
+            Ok(dialog) => dialog,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        dialog.show_all();
+        dialog.run();
+        dialog.close();
+    }
+}
@@ -0,0 +1,200 @@
+//! Module for the dialog to edit the user's preferences
+
+use std::fs;
+
+use gtk::{
+    BoxExt,
+    ComboBoxExt,
+    ComboBoxTextExt,
+    DialogExt,
+    EntryExt,
+    FileChooserExt,
+    GtkWindowExt,
+    SpinButtonExt,
+    WidgetExt,
+};
+
+use crate::database::get_path;
+use crate::models::leitner::Leitner;
+use crate::session::StudyMode;
+use crate::settings::Settings;
+use crate::ui::UI;
+use crate::ui::widgets::cardnotebook::CardNotebook;
+use super::error::Error as ErrorDialog;
+
+
+/// A dialog to edit the user's preferences
+pub struct Preferences;
+
+/// Implementation of the dialog to edit the user's preferences
+impl Preferences {
+    /// Build the button to choose the database file
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The current settings
+    fn build_db_path(settings: &Settings) -> gtk::FileChooserButton {
+        let button = gtk::FileChooserButton::new(
+            "Choose Database", gtk::FileChooserAction::Open);
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("SQLite databases"));
+        filter.add_pattern("*.sqlite");
+        filter.add_pattern("*.db");
+        button.add_filter(&filter);
+        if let Ok(path) = fs::canonicalize(get_path(settings.db_path.as_deref())) {
+            button.set_filename(path);
+        }
+        button
+    }
+
+    /// Build the combo box to choose the study mode to start with
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The current settings
+    fn build_mode(settings: &Settings) -> gtk::ComboBoxText {
+        let combo = gtk::ComboBoxText::new();
+        for mode in StudyMode::ALL.iter() {
+            combo.append(Some(mode.name()), mode.label());
+        }
+        combo.set_active_id(Some(settings.mode.name()));
+        combo
+    }
+
+    /// Build the entry for the review intervals of the Leitner boxes of the open deck
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    fn build_leitner_intervals(parent: &gtk::ApplicationWindow) -> gtk::Entry {
+        let entry = gtk::Entry::new();
+        entry.set_placeholder_text(Some("e.g. 1, 2, 4, 8, 16"));
+        let leitner = UI::get_repository(parent).and_then(|repository| repository.leitner());
+        if let Ok(leitner) = leitner {
+            entry.set_text(&leitner.intervals_text());
+        }
+        entry
+    }
+
+    /// Build the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    fn build(parent: &gtk::ApplicationWindow) -> gtk::Dialog {
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Preferences"),
+            Some(parent),
+            gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+            &[
+                ("_Ok", gtk::ResponseType::Accept),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        let settings = UI::get_settings(parent);
+        let spacing = 10;
+
+        let content = dialog.get_content_area();
+        content.set_margin_start(spacing as i32);
+        content.set_margin_end(spacing as i32);
+
+        let label = gtk::Label::new(Some("Database (used from the next start)"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let db_path = Preferences::build_db_path(&settings);
+        content.pack_start(&db_path, false, false, spacing);
+
+        let label = gtk::Label::new(Some("Study mode to start with"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let mode = Preferences::build_mode(&settings);
+        content.pack_start(&mode, false, false, spacing);
+
+        let label = gtk::Label::new(Some("Font size of the card text"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let font_size = gtk::SpinButton::with_range(
+            Settings::MIN_FONT_SIZE, Settings::MAX_FONT_SIZE, 1.0);
+        font_size.set_value(settings.font_size);
+        content.pack_start(&font_size, false, false, spacing);
+
+        let label = gtk::Label::new(Some("Days until review per Leitner box (stored in the deck)"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let leitner_intervals = Preferences::build_leitner_intervals(parent);
+        content.pack_start(&leitner_intervals, false, false, spacing);
+
+        let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
+        content.pack_end(&separator, false, false, spacing);
+
+        dialog.connect_response(glib::clone!(@weak parent => move |_, response_type| {
+            if response_type == gtk::ResponseType::Accept {
+                Preferences::response_accept(
+                    &parent, &db_path, &mode, &font_size, &leitner_intervals);
+            }
+        }));
+        dialog
+    }
+
+    /// When the dialog is accepted, save the settings and the Leitner intervals and show the
+    /// current card again
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `db_path_widget` - The widget which holds the path to the database file
+    /// * `mode_widget` - The widget which holds the study mode to start with
+    /// * `font_size_widget` - The widget which holds the font size of the card text
+    /// * `leitner_intervals_widget` - The widget which holds the intervals of the Leitner boxes
+    fn response_accept(
+        parent: &gtk::ApplicationWindow,
+        db_path_widget: &gtk::FileChooserButton,
+        mode_widget: &gtk::ComboBoxText,
+        font_size_widget: &gtk::SpinButton,
+        leitner_intervals_widget: &gtk::Entry,
+    ) {
+        let mut settings = UI::get_settings(parent);
+        // Keep the setting if the path is unchanged, as it may come from the environment
+        if let Some(path) = db_path_widget.get_filename() {
+            if fs::canonicalize(get_path(settings.db_path.as_deref())).ok() != Some(path.clone()) {
+                settings.db_path = Some(path.to_string_lossy().to_string());
+            }
+        }
+        if let Some(mode) = mode_widget.get_active_id().and_then(
+            |name| StudyMode::from_name(&name)) {
+            settings.mode = mode;
+        }
+        settings.font_size = font_size_widget.get_value();
+        if let Err(err) = settings.save() {
+            ErrorDialog::show(parent, &err.to_string());
+        }
+        UI::set_settings(parent, settings);
+        let intervals = leitner_intervals_widget.get_text();
+        if !intervals.trim().is_empty() {
+            let result = match Leitner::parse_intervals(&intervals) {
+                Ok(intervals) => UI::get_repository(parent)
+                    .and_then(|repository| repository.set_leitner(&Leitner { intervals }))
+                    .map_err(|err| err.to_string()),
+                Err(_) => Err(format!(
+                    "The Leitner intervals must be days separated by commas, not {}.", intervals)),
+            };
+            if let Err(message) = result {
+                ErrorDialog::show(parent, &message);
+            }
+        }
+        let card_id = CardNotebook::get_card_id(parent).unwrap_or(0);
+        CardNotebook::replace(parent, card_id);
+    }
+
+    /// Show the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show(parent: &gtk::ApplicationWindow) {
+        let dialog = Preferences::build(parent);
+        dialog.show_all();
+        dialog.run();
+        dialog.close();
+    }
+}
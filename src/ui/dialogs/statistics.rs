@@ -0,0 +1,142 @@
+//! Module for the dialog to show statistics about the flash cards and the learning progress
+
+use gtk::{BoxExt, ContainerExt, DialogExt, GtkWindowExt, LabelExt, ScrolledWindowExt, WidgetExt};
+
+use crate::database::DatabaseError;
+use crate::statistics::Total;
+use crate::ui::UI;
+use crate::ui::widgets::barchart::BarChart;
+use super::error::Error as ErrorDialog;
+
+
+/// A dialog which shows totals, the review history and the forecast of due cards as charts
+pub struct Statistics;
+
+/// Implementation of the dialog to show statistics
+impl Statistics {
+    /// Number of days of the review history and of the forecast of due cards
+    const DAYS: i64 = 30;
+
+    /// Get the labels of consecutive days, e.g. `17 Oct`
+    ///
+    /// # Arguments
+    ///
+    /// * `first_day` - The first day relative to today, e.g. -1 for yesterday
+    /// * `days` - Number of days
+    fn day_labels(first_day: i64, days: i64) -> Vec<String> {
+        let now = glib::DateTime::new_now_local();
+        (first_day..first_day + days)
+            .map(|day| {
+                now.add_days(day as i32)
+                    .and_then(|date| date.format("%e %b"))
+                    .map_or(day.to_string(), |label| label.trim().to_string())
+            })
+            .collect()
+    }
+
+    /// Add a chart with a heading to a container
+    ///
+    /// # Arguments
+    ///
+    /// * `container` - The container
+    /// * `heading` - The heading above the chart
+    /// * `bars` - The label and the value of each bar
+    fn add_chart(container: &gtk::Box, heading: &str, bars: Vec<(String, i64)>) {
+        let label = gtk::Label::new(None);
+        label.set_markup(&format!("<b>{}</b>", glib::markup_escape_text(heading)));
+        label.set_halign(gtk::Align::Start);
+        container.pack_start(&label, false, false, 0);
+        container.pack_start(&BarChart::build(bars), false, false, 0);
+    }
+
+    /// Get the bars of a chart from totals
+    ///
+    /// # Arguments
+    ///
+    /// * `totals` - The totals, e.g. the cards per category
+    fn bars(totals: Vec<Total>) -> Vec<(String, i64)> {
+        totals.into_iter().map(|total| (total.name, total.count)).collect()
+    }
+
+    /// Build the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    fn build(parent: &gtk::ApplicationWindow) -> Result<gtk::Dialog, DatabaseError> {
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Statistics"),
+            Some(parent),
+            gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+            &[("_Close", gtk::ResponseType::Close)],
+        );
+        // GTimeSpan is in microseconds
+        let utc_offset = glib::DateTime::new_now_local().get_utc_offset() / 1_000_000;
+        let data = UI::get_repository(parent)?.statistics(utc_offset, Statistics::DAYS)?;
+        let spacing = 10;
+        dialog.set_default_size(600, 700);
+
+        let container = gtk::Box::new(gtk::Orientation::Vertical, spacing);
+        container.set_margin_start(spacing);
+        container.set_margin_end(spacing);
+
+        let cards: i64 = data.categories.iter().map(|total| total.count).sum();
+        let retention = match data.retention.rate() {
+            Some(rate) => format!(
+                "{:.0} % of {} answers recalled in the last {} days",
+                rate * 100.0, data.retention.answers, Statistics::DAYS),
+            None => format!("No answers in the last {} days", Statistics::DAYS),
+        };
+        let summary = [
+            format!("{} cards, {} of them never studied", cards, data.new_cards),
+            retention,
+            format!(
+                "Streak: {} {} in a row", data.streak, if data.streak == 1 { "day" } else { "days" }),
+        ];
+        for text in summary.iter() {
+            let label = gtk::Label::new(Some(text));
+            label.set_halign(gtk::Align::Start);
+            container.pack_start(&label, false, false, 0);
+        }
+
+        Statistics::add_chart(&container, "Cards per category", Statistics::bars(data.categories));
+        Statistics::add_chart(
+            &container, "Translations per language", Statistics::bars(data.languages));
+        let labels = Statistics::day_labels(1 - Statistics::DAYS, Statistics::DAYS);
+        Statistics::add_chart(
+            &container,
+            &format!("Answers per day in the last {} days", Statistics::DAYS),
+            labels.into_iter().zip(data.reviews_per_day).collect(),
+        );
+        let labels = Statistics::day_labels(0, Statistics::DAYS);
+        Statistics::add_chart(
+            &container,
+            &format!("Due cards per day in the next {} days, overdue ones today", Statistics::DAYS),
+            labels.into_iter().zip(data.due_forecast).collect(),
+        );
+
+        let scrolled = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.add(&container);
+        dialog.get_content_area().pack_start(&scrolled, true, true, spacing as u32);
+        Ok(dialog)
+    }
+
+    /// Show the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show(parent: &gtk::ApplicationWindow) {
+        let dialog = match Statistics::build(parent) {
+            Ok(dialog) => dialog,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        dialog.show_all();
+        dialog.run();
+        dialog.close();
+    }
+}
@@ -0,0 +1,169 @@
+//! Module for the dialog to choose the categories and languages to study
+
+use gtk::{
+    BoxExt,
+    ContainerExt,
+    DialogExt,
+    GtkWindowExt,
+    ScrolledWindowExt,
+    ToggleButtonExt,
+    WidgetExt,
+};
+
+use crate::database::DatabaseError;
+use crate::models::filter::Filter;
+use crate::repository::Repository;
+use crate::ui::UI;
+use crate::ui::widgets::cardnotebook::CardNotebook;
+use crate::ui::widgets::content::Content;
+use super::error::Error as ErrorDialog;
+
+
+/// A dialog to choose the categories and languages to study
+pub struct StudyFilter;
+
+/// Implementation of the dialog to choose the categories and languages to study
+impl StudyFilter {
+    /// Build check buttons for the given items, those with an active id are checked
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - The identifiers and names of the items
+    /// * `active_ids` - Identifiers of the checked items
+    /// * `orientation` - How to arrange the check buttons
+    pub fn build_check_buttons(
+        items: Vec<(i64, String)>,
+        active_ids: &[i64],
+        orientation: gtk::Orientation,
+    ) -> (gtk::Box, Vec<(i64, gtk::CheckButton)>) {
+        let container = gtk::Box::new(orientation, 10);
+        let mut buttons = Vec::new();
+        for (id, name) in items {
+            let button = gtk::CheckButton::with_label(&name);
+            button.set_active(active_ids.contains(&id));
+            container.pack_start(&button, false, false, 0);
+            buttons.push((id, button));
+        }
+        (container, buttons)
+    }
+
+    /// Build the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    fn build(parent: &gtk::ApplicationWindow) -> Result<gtk::Dialog, DatabaseError> {
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Study Filter"),
+            Some(parent),
+            gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+            &[
+                ("_Ok", gtk::ResponseType::Accept),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        let repository = UI::get_repository(parent)?;
+        let filter = repository.filter()?;
+        let spacing = 10;
+        dialog.set_default_size(300, 400);
+
+        let content = dialog.get_content_area();
+        content.set_margin_start(spacing as i32);
+        content.set_margin_end(spacing as i32);
+
+        let label = gtk::Label::new(Some("Categories (none checked for all)"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let categories = repository.categories()?.into_iter()
+            .map(|category| (category.id, category.name))
+            .collect();
+        let (container, category_buttons) = StudyFilter::build_check_buttons(
+            categories, &filter.category_ids, gtk::Orientation::Vertical);
+        let scrolled = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.add(&container);
+        content.pack_start(&scrolled, true, true, spacing);
+
+        let label = gtk::Label::new(Some("Active languages (none checked for all)"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let languages = repository.languages()?.into_iter()
+            .map(|language| (language.id, language.name))
+            .collect();
+        let (container, language_buttons) = StudyFilter::build_check_buttons(
+            languages, &filter.language_ids, gtk::Orientation::Horizontal);
+        content.pack_start(&container, false, false, spacing);
+
+        let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
+        content.pack_end(&separator, false, false, spacing);
+
+        dialog.connect_response(glib::clone!(@weak parent => move |_, response_type| {
+            if response_type == gtk::ResponseType::Accept {
+                StudyFilter::response_accept(
+                    &parent, &repository, &category_buttons, &language_buttons);
+            }
+        }));
+        Ok(dialog)
+    }
+
+    /// When the dialog is accepted, save the filter and show a card which passes it
+    ///
+    /// The current card is shown again if it passes the filter, otherwise the next card. A single
+    /// active language is refused, as no card could be studied then.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `repository` - The repository of flash cards
+    /// * `category_widgets` - The widgets which hold the categories by category id
+    /// * `language_widgets` - The widgets which hold the active languages by language id
+    fn response_accept(
+        parent: &gtk::ApplicationWindow,
+        repository: &Repository,
+        category_widgets: &[(i64, gtk::CheckButton)],
+        language_widgets: &[(i64, gtk::CheckButton)],
+    ) {
+        let checked = |widgets: &[(i64, gtk::CheckButton)]| -> Vec<i64> {
+            widgets.iter()
+                .filter(|(_, button)| button.get_active())
+                .map(|(id, _)| *id)
+                .collect()
+        };
+        let filter = Filter {
+            category_ids: checked(category_widgets),
+            language_ids: checked(language_widgets),
+        };
+        if filter.language_ids.len() == 1 {
+            ErrorDialog::show(
+                parent, "Check at least two languages, one to prompt and one to answer, or none.");
+            return;
+        }
+        if let Err(err) = repository.set_filter(&filter) {
+            ErrorDialog::show(parent, &err.to_string());
+            return;
+        }
+        Content::update_filter(parent);
+        let card_id = CardNotebook::get_card_id(parent).ok()
+            .filter(|card_id| repository.passes_filter(*card_id).unwrap_or(false))
+            .unwrap_or(0);
+        CardNotebook::replace(parent, card_id);
+    }
+
+    /// Show the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show(parent: &gtk::ApplicationWindow) {
+        let dialog = match StudyFilter::build(parent) {
+            Ok(dialog) => dialog,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        dialog.show_all();
+        dialog.run();
+        dialog.close();
+    }
+}
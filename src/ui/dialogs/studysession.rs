@@ -0,0 +1,226 @@
+//! Module for the dialog to configure the study session
+
+use gtk::{
+    BoxExt,
+    ComboBoxExt,
+    ComboBoxTextExt,
+    DialogExt,
+    GtkWindowExt,
+    ToggleButtonExt,
+    WidgetExt,
+};
+
+use crate::compare::Strictness;
+use crate::database::DatabaseError;
+use crate::models::language::Language;
+use crate::session::{Session, StudyMode};
+use crate::ui::UI;
+use crate::ui::widgets::cardnotebook::CardNotebook;
+use super::error::Error as ErrorDialog;
+
+
+/// A dialog to configure the study session
+pub struct StudySession;
+
+/// Implementation of the dialog to configure the study session
+impl StudySession {
+    /// Build the combo box to choose the study mode
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The current study session
+    fn build_mode(session: &Session) -> gtk::ComboBoxText {
+        let combo = gtk::ComboBoxText::new();
+        for mode in StudyMode::ALL.iter() {
+            combo.append(Some(mode.name()), mode.label());
+        }
+        combo.set_active_id(Some(session.mode.name()));
+        combo
+    }
+
+    /// Build the combo box to choose the prompt language
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The current study session
+    /// * `languages` - All available languages
+    fn build_prompt_language(session: &Session, languages: &[Language]) -> gtk::ComboBoxText {
+        let combo = gtk::ComboBoxText::new();
+        combo.append(Some("0"), "First available language");
+        for language in languages {
+            combo.append(Some(&language.id.to_string()), &language.name);
+        }
+        combo.set_active_id(Some(&session.prompt_language_id.to_string()));
+        combo
+    }
+
+    /// Build the check buttons to choose the answer languages
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The current study session
+    /// * `languages` - All available languages
+    fn build_answer_languages(
+        session: &Session,
+        languages: &[Language],
+    ) -> (gtk::Box, Vec<(i64, gtk::CheckButton)>) {
+        let container = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+        let mut buttons = Vec::new();
+        for language in languages {
+            let button = gtk::CheckButton::with_label(&language.name);
+            button.set_active(session.answer_language_ids.contains(&language.id));
+            container.pack_start(&button, false, false, 0);
+            buttons.push((language.id, button));
+        }
+        (container, buttons)
+    }
+
+    /// Build the check buttons to choose how strictly typed answers are compared
+    ///
+    /// The buttons are in order: normalize unicode, ignore case, ignore diacritics and lenient
+    /// apostrophes.
+    ///
+    /// # Arguments
+    ///
+    /// * `strictness` - The current strictness
+    fn build_strictness(strictness: &Strictness) -> (gtk::Box, Vec<gtk::CheckButton>) {
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let options = [
+            ("Compose characters before comparing (Unicode normalization)",
+                strictness.normalize_unicode),
+            ("Ignore upper and lower case", strictness.ignore_case),
+            ("Ignore diacritics like the macron (ā = a)", strictness.ignore_diacritics),
+            ("Accept any apostrophe for the fakauʻa (ʻ = ')", strictness.lenient_apostrophes),
+        ];
+        let mut buttons = Vec::new();
+        for (label, active) in options.iter() {
+            let button = gtk::CheckButton::with_label(label);
+            button.set_active(*active);
+            container.pack_start(&button, false, false, 0);
+            buttons.push(button);
+        }
+        (container, buttons)
+    }
+
+    /// Build the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    fn build(parent: &gtk::ApplicationWindow) -> Result<gtk::Dialog, DatabaseError> {
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Study Session"),
+            Some(parent),
+            gtk::DialogFlags::DESTROY_WITH_PARENT | gtk::DialogFlags::MODAL,
+            &[
+                ("_Ok", gtk::ResponseType::Accept),
+                ("_Cancel", gtk::ResponseType::Cancel),
+            ],
+        );
+        let languages = UI::get_repository(parent)?.languages()?;
+        let session = UI::get_session(parent);
+        let spacing = 10;
+
+        let content = dialog.get_content_area();
+        content.set_margin_start(spacing as i32);
+        content.set_margin_end(spacing as i32);
+
+        let label = gtk::Label::new(Some("Mode"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let mode = StudySession::build_mode(&session);
+        content.pack_start(&mode, false, false, spacing);
+
+        let label = gtk::Label::new(Some("Prompt language"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let prompt_language = StudySession::build_prompt_language(&session, &languages);
+        content.pack_start(&prompt_language, false, false, spacing);
+
+        let label = gtk::Label::new(Some("Answer languages (none checked for all)"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let (container, answer_languages) = StudySession::build_answer_languages(
+            &session, &languages);
+        content.pack_start(&container, false, false, spacing);
+
+        let label = gtk::Label::new(Some("Typed answers"));
+        label.set_halign(gtk::Align::Start);
+        content.pack_start(&label, false, false, spacing);
+        let (container, strictness) = StudySession::build_strictness(&session.strictness);
+        content.pack_start(&container, false, false, spacing);
+
+        let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
+        content.pack_end(&separator, false, false, spacing);
+
+        dialog.connect_response(glib::clone!(@weak parent => move |_, response_type| {
+            if response_type == gtk::ResponseType::Accept {
+                StudySession::response_accept(
+                    &parent, &mode, &prompt_language, &answer_languages, &strictness);
+            }
+        }));
+        Ok(dialog)
+    }
+
+    /// When the dialog is accepted, change the study session and show the current card again
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    /// * `mode_widget` - The widget which holds the study mode
+    /// * `prompt_language_widget` - The widget which holds the prompt language
+    /// * `answer_language_widgets` - The widgets which hold the answer languages by language id
+    /// * `strictness_widgets` - The widgets which hold the strictness of typed answers
+    fn response_accept(
+        parent: &gtk::ApplicationWindow,
+        mode_widget: &gtk::ComboBoxText,
+        prompt_language_widget: &gtk::ComboBoxText,
+        answer_language_widgets: &[(i64, gtk::CheckButton)],
+        strictness_widgets: &[gtk::CheckButton],
+    ) {
+        let mut session = UI::get_session(parent);
+        if let Some(mode) = mode_widget.get_active_id().and_then(
+            |name| StudyMode::from_name(&name)) {
+            session.mode = mode;
+        }
+        if let Some(id) = prompt_language_widget.get_active_id().and_then(
+            |id| id.parse::<i64>().ok()) {
+            session.prompt_language_id = id;
+        }
+        session.answer_language_ids = answer_language_widgets.iter()
+            .filter(|(_, button)| button.get_active())
+            .map(|(id, _)| *id)
+            .collect();
+        let options: Vec<bool> = strictness_widgets.iter().map(|button| button.get_active()).collect();
+        session.strictness = Strictness {
+            normalize_unicode: options[0],
+            ignore_case: options[1],
+            ignore_diacritics: options[2],
+            lenient_apostrophes: options[3],
+        };
+        if let Some(app) = parent.get_application() {
+            UI::add_study_accelerators(&app, session.mode);
+        }
+        UI::set_session(parent, session);
+        let card_id = CardNotebook::get_card_id(parent).unwrap_or(0);
+        CardNotebook::replace(parent, card_id);
+    }
+
+    /// Show the dialog
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The GTK application window
+    pub fn show(parent: &gtk::ApplicationWindow) {
+        let dialog = match StudySession::build(parent) {
+            Ok(dialog) => dialog,
+            Err(err) => {
+                ErrorDialog::show(parent, &err.to_string());
+                return;
+            },
+        };
+        dialog.show_all();
+        dialog.run();
+        dialog.close();
+    }
+}
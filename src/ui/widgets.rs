@@ -1,4 +1,8 @@
 //! Widgets of the application
 
+pub mod barchart;
 pub mod cardnotebook;
-pub mod content;
\ No newline at end of file
+pub mod choice;
+pub mod content;
+pub mod quiz;
+pub mod search;
\ No newline at end of file
@@ -0,0 +1,95 @@
+//! Module for a bar chart drawn with cairo, e.g. to show statistics
+
+use gtk::{Inhibit, StyleContextExt, WidgetExt};
+
+
+/// A drawing area which shows a labelled bar per value
+pub struct BarChart;
+
+/// Implementation of the bar chart
+impl BarChart {
+    /// Height of the chart in pixels
+    const HEIGHT: i32 = 160;
+    /// Size of the labels and values in pixels
+    const FONT_SIZE: f64 = 11.0;
+    /// Space between the bars and the labels in pixels
+    const PADDING: f64 = 4.0;
+
+    /// Build the chart
+    ///
+    /// # Arguments
+    ///
+    /// * `bars` - The label and the value of each bar, from left to right
+    pub fn build(bars: Vec<(String, i64)>) -> gtk::DrawingArea {
+        let area = gtk::DrawingArea::new();
+        area.set_size_request(-1, BarChart::HEIGHT);
+        area.connect_draw(move |area, context| {
+            BarChart::draw(area, context, &bars);
+            Inhibit(false)
+        });
+        area
+    }
+
+    /// Draw the bars, each with its value above and its label below
+    ///
+    /// Labels which would overlap are left out, only every n-th bar is labelled then.
+    ///
+    /// # Arguments
+    ///
+    /// * `area` - The drawing area
+    /// * `context` - The cairo context to draw with
+    /// * `bars` - The label and the value of each bar, from left to right
+    fn draw(area: &gtk::DrawingArea, context: &cairo::Context, bars: &[(String, i64)]) {
+        if bars.is_empty() {
+            return;
+        }
+        let width = area.get_allocated_width() as f64;
+        let height = area.get_allocated_height() as f64;
+        let style = area.get_style_context();
+        let foreground = style.get_color(gtk::StateFlags::NORMAL);
+        let accent = style.lookup_color("theme_selected_bg_color").unwrap_or(
+            gdk::RGBA { red: 0.2, green: 0.4, blue: 0.8, alpha: 1.0 });
+        context.set_font_size(BarChart::FONT_SIZE);
+
+        let text_height = BarChart::FONT_SIZE + BarChart::PADDING;
+        let chart_height = (height - 2.0 * text_height).max(0.0);
+        let slot = width / bars.len() as f64;
+        let max_value = bars.iter().map(|(_, value)| *value).max().unwrap_or(0).max(1) as f64;
+        let widest_label = bars.iter()
+            .map(|(label, _)| context.text_extents(label).width)
+            .fold(0.0, f64::max);
+        // Draw every n-th label only, so that neighbouring labels keep some space in between
+        let label_step = ((widest_label + 2.0 * BarChart::PADDING) / slot).ceil().max(1.0) as usize;
+
+        for (idx, (label, value)) in bars.iter().enumerate() {
+            let center = slot * (idx as f64 + 0.5);
+            let bar_height = chart_height * *value as f64 / max_value;
+            let bar_top = text_height + chart_height - bar_height;
+            context.set_source_rgba(accent.red, accent.green, accent.blue, accent.alpha);
+            context.rectangle(center - slot * 0.35, bar_top, slot * 0.7, bar_height);
+            context.fill();
+
+            context.set_source_rgba(
+                foreground.red, foreground.green, foreground.blue, foreground.alpha);
+            let value_text = value.to_string();
+            let extents = context.text_extents(&value_text);
+            if *value > 0 && extents.width <= slot {
+                context.move_to(center - extents.width / 2.0, bar_top - BarChart::PADDING);
+                context.show_text(&value_text);
+            }
+            if idx % label_step == 0 {
+                let extents = context.text_extents(label);
+                let x = (center - extents.width / 2.0).max(0.0).min(width - extents.width);
+                context.move_to(x, height - BarChart::PADDING);
+                context.show_text(label);
+            }
+        }
+
+        // The base line
+        context.set_source_rgba(foreground.red, foreground.green, foreground.blue, foreground.alpha);
+        context.set_line_width(1.0);
+        context.move_to(0.0, text_height + chart_height + 0.5);
+        context.line_to(width, text_height + chart_height + 0.5);
+        context.stroke();
+    }
+}
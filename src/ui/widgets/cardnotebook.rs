@@ -1,19 +1,21 @@
 //! Module for the flash card widget
 
 use std::error::Error;
+use std::time::Instant;
 
 use glib::{Cast, ObjectExt};
 use gtk::{BoxExt, ContainerExt, LabelExt, Notebook, WidgetExt};
 use gtk::prelude::NotebookExtManual;
 
-use crate::database::{get_connection, DatabaseError};
-use crate::models::Model;
-use crate::models::card::Card;
-use crate::models::category::Category;
-use crate::models::language::Language;
+use crate::database::DatabaseError;
+use crate::models::review::Grade;
+use crate::models::reviewlog::ReviewLog;
 use crate::models::translation::Translation;
-use crate::ui::{WIDGET_NAME_CARD, WIDGET_NAME_CONTENT};
+use crate::repository::Repository;
+use crate::session::StudyMode;
+use crate::ui::{UI, WIDGET_NAME_CARD, WIDGET_NAME_CONTENT};
 use crate::ui::dialogs::error::Error as ErrorDialog;
+use super::content::Content;
 
 
 /// A widget for a flash card
@@ -25,38 +27,51 @@ impl CardNotebook {
     ///
     /// # Arguments
     ///
-    /// * `conn` - Connection to the database
+    /// * `repository` - The repository of flash cards
     /// * `category_id` - Identifier of the category for which to build notebook page
     /// * `translation` - Translation to show on the notebook page
+    /// * `hide_description` - Whether to hide the description until the card is revealed
+    /// * `font_size` - Font size of the translation's text in points
     fn build_page(
-        conn: &sqlite::Connection,
+        repository: &Repository,
         category_id: i64,
         translation: &Translation,
+        hide_description: bool,
+        font_size: f64,
     ) -> Result<(gtk::Box, gtk::Label), DatabaseError> {
         let padding = 10;
         let page = gtk::Box::new(gtk::Orientation::Vertical, 0);
         page.set_homogeneous(false);
 
         let text = gtk::Label::new(Some(""));
-        text.set_markup(&format!("<span font_desc='30.0'>{}</span>", &translation.text));
+        text.set_markup(&format!(
+            "<span font_desc='{:.1}'>{}</span>",
+            font_size,
+            glib::markup_escape_text(&translation.text),
+        ));
         page.pack_start(&text, true, true, padding);
 
         let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
         page.pack_start(&separator, false, false, padding);
 
         let page_bottom = gtk::Box::new(gtk::Orientation::Horizontal, 0);
-        if translation.description != "" {
+        if !translation.description.is_empty() {
             let description = gtk::Label::new(Some(&translation.description));
+            description.set_no_show_all(hide_description);
             page_bottom.pack_start(&description, false, false, padding);
         }
-        let category = Category::load(&conn, category_id)?;
+        let category = repository.category(category_id)?;
         let category_label = gtk::Label::new(Some(""));
-        category_label.set_markup(&format!("Category: <b>{}</b>", category.name));
+        category_label.set_markup(&format!(
+            "Category: <b>{}</b>", glib::markup_escape_text(&category.name)));
         page_bottom.pack_end(&category_label, false, false, padding);
         page.pack_start(&page_bottom, false, false, padding);
 
-        let language = Language::load(&conn, translation.language_id)?;
+        let language = repository.language(translation.language_id)?;
         let label = gtk::Label::new(Some(&language.name));
+        unsafe {
+            page.set_data("language_id", language.id);
+        }
         Ok((page, label))
     }
 
@@ -65,37 +80,50 @@ impl CardNotebook {
     /// # Arguments
     ///
     /// * `window` - The GTK application window
-    /// * `card_id` - Identifier of the card for which to build the notebook. Set to 0 to show a
-    ///               random card.
+    /// * `card_id` - Identifier of the card for which to build the notebook. Set to 0 to show the
+    ///   next card.
+    ///
+    /// # Notes
+    ///
+    /// * The first page shows the prompt, the other pages show the answers of the study session.
+    /// * If answers are to be revealed, only the prompt is shown until `CardNotebook::reveal`.
+    /// * Translations in languages which are inactive in the study filter are skipped.
     pub fn build(window: &gtk::ApplicationWindow, card_id: i64) -> gtk::Notebook {
         let notebook = gtk::Notebook::new();
         notebook.set_widget_name(WIDGET_NAME_CARD);
         notebook.grab_focus();
 
-        let conn = match get_connection() {
-            Ok(conn) => conn,
+        let repository = match UI::get_repository(window) {
+            Ok(repository) => repository,
             Err(err) => {
                 ErrorDialog::show(window, &err.to_string());
                 return notebook;
             }
         };
-        let card = match Card::get(&conn, card_id) {
+        let card = match repository.next_card(card_id) {
             Ok(card) => card,
             Err(err) => {
                 ErrorDialog::show(window, &err.to_string());
                 return notebook;
             }
         };
-        let translations = match Translation::load_for_card(&conn, card.id) {
+        let translations = match repository.study_translations(card.id) {
             Ok(translations) => translations,
             Err(err) => {
                 ErrorDialog::show(window, &err.to_string());
                 return notebook;
             }
         };
-        for translation in translations {
-            match CardNotebook::build_page(&conn, card.category_id, &translation) {
+        let session = UI::get_session(window);
+        let hidden = session.mode == StudyMode::Reveal;
+        let font_size = UI::get_settings(window).font_size;
+        let (prompt, answers) = session.arrange(translations);
+        for (idx, translation) in prompt.iter().chain(answers.iter()).enumerate() {
+            match CardNotebook::build_page(
+                &repository, card.category_id, translation, hidden, font_size) {
                 Ok((page, label)) => {
+                    // Hiding a page's content also hides its tab
+                    page.set_no_show_all(hidden && idx > 0);
                     notebook.append_page(&page, Some(&label));
                 }
                 Err(err) => {
@@ -106,31 +134,38 @@ impl CardNotebook {
         }
         unsafe {
             notebook.set_data("card_id", card.id);
+            notebook.set_data("shown_at", Instant::now());
         }
         notebook
     }
 
-    /// Find the currently shown card widget
+    /// Find the currently shown card widget as notebook
     ///
     /// # Arguments
     ///
     /// * `window` - The GTK application window
     pub fn find(window: &gtk::ApplicationWindow) -> Option<Notebook> {
+        match CardNotebook::find_widget(window) {
+            Some(widget) => widget.downcast::<gtk::Notebook>().ok(),
+            None => None,
+        }
+    }
+
+    /// Find the currently shown card widget, whatever the study mode
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    pub fn find_widget(window: &gtk::ApplicationWindow) -> Option<gtk::Widget> {
         // TODO: Is there a better way to find the box and card?
         for widget in window.get_children() {
             if widget.get_widget_name() == WIDGET_NAME_CONTENT {
-                match widget.downcast::<gtk::Box>() {
-                    Ok(vbox) => {
-                        for child in vbox.get_children() {
-                            if child.get_widget_name() == WIDGET_NAME_CARD {
-                                match child.downcast::<gtk::Notebook>() {
-                                    Ok(card) => { return Some(card); },
-                                    _ => {},
-                                }
-                            }
+                if let Ok(vbox) = widget.downcast::<gtk::Box>() {
+                    for child in vbox.get_children() {
+                        if child.get_widget_name() == WIDGET_NAME_CARD {
+                            return Some(child);
                         }
-                    },
-                    _ => {},
+                    }
                 }
             }
         }
@@ -143,7 +178,7 @@ impl CardNotebook {
     ///
     /// * `window` - The GTK application window
     pub fn get_card_id(window: &gtk::ApplicationWindow) -> Result<i64, Box<dyn Error>> {
-        match CardNotebook::find(window) {
+        match CardNotebook::find_widget(window) {
             Some(card) => {
                 unsafe {
                     match card.get_data::<i64>("card_id") {
@@ -156,6 +191,117 @@ impl CardNotebook {
         }
     }
 
+    /// Get the ids of the prompt language and of the answer language of the given card
+    ///
+    /// The prompt is on the first page. The answer is on the currently shown page or on the second
+    /// page if the prompt is currently shown.
+    ///
+    /// # Arguments
+    ///
+    /// * `card` - The card widget
+    fn get_language_pair(card: &gtk::Notebook) -> Result<(i64, i64), Box<dyn Error>> {
+        let answer_page_num = match card.get_current_page() {
+            Some(0) | None if card.get_n_pages() > 1 => Some(1),
+            page_num => page_num,
+        };
+        let mut pair = Vec::new();
+        for page_num in &[Some(0), answer_page_num] {
+            let page = match card.get_nth_page(*page_num) {
+                Some(page) => page,
+                None => Err("Cannot find card page!")?,
+            };
+            unsafe {
+                match page.get_data::<i64>("language_id") {
+                    Some(id) => pair.push(*id),
+                    None => Err("Cannot get language id from card page!")?,
+                }
+            }
+        }
+        Ok((pair[0], pair[1]))
+    }
+
+    /// Grade the answer to the shown flash card, reschedule it and show the next card
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `grade` - The grade given by the user
+    pub fn grade(window: &gtk::ApplicationWindow, grade: Grade) {
+        match CardNotebook::record(window, grade) {
+            Ok(_) => CardNotebook::replace(window, 0),
+            Err(err) => ErrorDialog::show(window, &err.to_string()),
+        }
+    }
+
+    /// Record the answer to the shown flash card in the review log
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `grade` - The grade given by the user
+    ///
+    /// # Notes
+    ///
+    /// * Widgets other than the notebook provide the `language_pair` themselves and may provide the
+    ///   `response_time` if the answer has been given before grading.
+    pub fn record(window: &gtk::ApplicationWindow, grade: Grade) -> Result<i64, Box<dyn Error>> {
+        let card = match CardNotebook::find_widget(window) {
+            Some(card) => card,
+            None => Err("Cannot find card widget!")?,
+        };
+        let card_id = CardNotebook::get_card_id(window)?;
+        let response_time = unsafe {
+            match (card.get_data::<i64>("response_time"), card.get_data::<Instant>("shown_at")) {
+                (Some(response_time), _) => *response_time,
+                (None, Some(shown_at)) => shown_at.elapsed().as_millis() as i64,
+                (None, None) => 0,
+            }
+        };
+        let language_pair = unsafe { card.get_data::<(i64, i64)>("language_pair").copied() };
+        let (prompt_language_id, answer_language_id) = match language_pair {
+            Some(pair) => pair,
+            None => match card.downcast::<gtk::Notebook>() {
+                Ok(notebook) => CardNotebook::get_language_pair(&notebook)?,
+                Err(_) => Err("Cannot get languages from card widget!")?,
+            },
+        };
+        let mut log = ReviewLog::new(
+            card_id, prompt_language_id, answer_language_id, grade, response_time);
+        let id = UI::get_repository(window)?.record_answer(&mut log)?;
+        Ok(id)
+    }
+
+    /// Reveal the hidden answers of the shown flash card and switch to the first answer
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    pub fn reveal(window: &gtk::ApplicationWindow) {
+        if let Some(card) = CardNotebook::find(window) {
+            for page in card.get_children() {
+                CardNotebook::unhide(&page);
+            }
+            if card.get_n_pages() > 1 {
+                card.set_current_page(Some(1));
+            }
+        }
+    }
+
+    /// Show the given widget and all its children, even those which are hidden from `show_all`
+    ///
+    /// # Arguments
+    ///
+    /// * `widget` - The widget to show
+    fn unhide(widget: &gtk::Widget) {
+        widget.set_no_show_all(false);
+        if let Ok(container) = widget.clone().downcast::<gtk::Container>() {
+            for child in container.get_children() {
+                CardNotebook::unhide(&child);
+            }
+        }
+        widget.show();
+    }
+
     /// Replace the shown flash card by the card with given id
     ///
     /// # Arguments
@@ -163,26 +309,21 @@ impl CardNotebook {
     /// * `window` - The GTK application window
     /// * `card_id` - Identifier of the card to use as replacement
     pub fn replace(window: &gtk::ApplicationWindow, card_id: i64) {
-        match CardNotebook::find(window) {
-            Some(card) => {
-                match card.get_parent() {
-                    Some(parent) => {
-                        match parent.downcast::<gtk::Box>() {
-                            Ok(vbox) => {
-                                vbox.remove(&card);
-                                let card = CardNotebook::build(window, card_id);
-                                vbox.pack_start(&card, true, true, 10);
-                                vbox.show_all();
-                                // Focus must be grabbed after being shown
-                                card.grab_focus();
-                            }
-                            _ => {},
-                        }
-                    },
-                    None => {},
-                }
+        let vbox = CardNotebook::find_widget(window).and_then(|card| {
+            let vbox = card.get_parent()?.downcast::<gtk::Box>().ok()?;
+            vbox.remove(&card);
+            Some(vbox)
+        });
+        if let Some(vbox) = vbox {
+            let card = Content::build_card(window, card_id);
+            vbox.pack_start(&card, true, true, 10);
+            vbox.show_all();
+            // Focus must be grabbed after being shown
+            if card.get_can_focus() {
+                card.grab_focus();
+            } else {
+                card.child_focus(gtk::DirectionType::TabForward);
             }
-            None => {},
         }
     }
 }
\ No newline at end of file
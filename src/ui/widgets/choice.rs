@@ -0,0 +1,234 @@
+//! Module for the multiple choice widget to choose the answer to a flash card
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Instant;
+
+use glib::{Cast, ObjectExt};
+use gtk::{BoxExt, ButtonExt, ContainerExt, LabelExt, StyleContextExt, WidgetExt};
+
+use crate::database::DatabaseError;
+use crate::models::review::Grade;
+use crate::models::translation::Translation;
+use crate::repository::Repository;
+use crate::ui::{UI, WIDGET_NAME_CARD};
+use crate::ui::dialogs::error::Error as ErrorDialog;
+use super::cardnotebook::CardNotebook;
+
+
+/// A widget to quiz a flash card by choosing the answer from several ones
+pub struct Choice;
+
+/// Implementation of the multiple choice widget
+impl Choice {
+    /// Maximum number of answers to choose from
+    pub const MAX_CHOICES: i64 = 6;
+    /// Minimum number of answers to choose from, if the database has enough translations
+    const MIN_CHOICES: i64 = 4;
+
+    /// Build the multiple choice quiz for a card with given id
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `card_id` - Identifier of the card for which to build the quiz. Set to 0 to quiz the next
+    ///   card.
+    ///
+    /// # Notes
+    ///
+    /// * The first answer language of the study session is the one which has to be chosen.
+    pub fn build(window: &gtk::ApplicationWindow, card_id: i64) -> gtk::Box {
+        let quiz = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        quiz.set_widget_name(WIDGET_NAME_CARD);
+        if let Err(err) = Choice::build_content(window, &quiz, card_id) {
+            ErrorDialog::show(window, &err.to_string());
+        }
+        quiz
+    }
+
+    /// Build the content of the quiz, i.e. the prompt, the answers to choose from and the result
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `quiz` - The quiz widget to add the content to
+    /// * `card_id` - Identifier of the card for which to build the quiz
+    fn build_content(
+        window: &gtk::ApplicationWindow,
+        quiz: &gtk::Box,
+        card_id: i64,
+    ) -> Result<(), DatabaseError> {
+        let padding = 10;
+        let repository = UI::get_repository(window)?;
+        let card = repository.next_card(card_id)?;
+        let translations = repository.study_translations(card.id)?;
+        let session = UI::get_session(window);
+        let (prompt, answers) = session.arrange(translations);
+        let (prompt, answer) = match (prompt, answers.into_iter().next()) {
+            (Some(prompt), Some(answer)) => (prompt, answer),
+            _ => { return Err(DatabaseError::NotFound); },
+        };
+        let prompt_language = repository.language(prompt.language_id)?;
+        let answer_language = repository.language(answer.language_id)?;
+        let category = repository.category(card.category_id)?;
+        let choices = Choice::load_choices(&repository, &answer, card.category_id)?;
+
+        let language = gtk::Label::new(Some(&format!(
+            "{} → {}", prompt_language.name, answer_language.name)));
+        quiz.pack_start(&language, false, false, padding);
+
+        let text = gtk::Label::new(Some(""));
+        text.set_markup(&format!(
+            "<span font_desc='{:.1}'>{}</span>",
+            UI::get_settings(window).font_size,
+            glib::markup_escape_text(&prompt.text),
+        ));
+        quiz.pack_start(&text, true, true, padding);
+
+        let mut buttons = Vec::new();
+        let mut correct = 0;
+        for (idx, choice) in choices.iter().enumerate() {
+            let button = gtk::Button::with_label(&format!("{}.  {}", idx + 1, choice.text));
+            button.set_tooltip_text(Some(&format!("Type <{}> to choose this answer", idx + 1)));
+            let is_correct = choice.id == answer.id;
+            if is_correct {
+                correct = idx;
+            }
+            button.connect_clicked(glib::clone!(@weak window, @weak quiz => move |button| {
+                Choice::answer(&window, &quiz, button, is_correct);
+            }));
+            quiz.pack_start(&button, false, false, 0);
+            buttons.push(button);
+        }
+
+        let result = gtk::Label::new(None);
+        result.set_widget_name("result");
+        result.set_no_show_all(true);
+        quiz.pack_start(&result, false, false, padding);
+
+        let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
+        quiz.pack_start(&separator, false, false, padding);
+
+        let category_label = gtk::Label::new(Some(""));
+        category_label.set_markup(&format!(
+            "Category: <b>{}</b>", glib::markup_escape_text(&category.name)));
+        category_label.set_halign(gtk::Align::End);
+        quiz.pack_start(&category_label, false, false, padding);
+
+        unsafe {
+            quiz.set_data("card_id", card.id);
+            quiz.set_data("shown_at", Instant::now());
+            quiz.set_data("language_pair", (prompt.language_id, answer.language_id));
+            quiz.set_data("choices", buttons);
+            quiz.set_data("correct", correct);
+        }
+        Ok(())
+    }
+
+    /// Load the answers to choose from in random order, including the correct one
+    ///
+    /// Wrong answers are taken from the same category. If the category does not provide enough of
+    /// them, they are taken from any category.
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    /// * `answer` - The correct answer
+    /// * `category_id` - Identifier of the category of the card
+    fn load_choices(
+        repository: &Repository,
+        answer: &Translation,
+        category_id: i64,
+    ) -> Result<Vec<Translation>, DatabaseError> {
+        let mut choices = repository.distractors(answer, category_id, Choice::MAX_CHOICES - 1)?;
+        if (choices.len() as i64) < Choice::MIN_CHOICES - 1 {
+            for distractor in repository.distractors(answer, 0, Choice::MAX_CHOICES - 1)? {
+                if (choices.len() as i64) >= Choice::MIN_CHOICES - 1 {
+                    break;
+                }
+                if choices.iter().all(|choice| choice.text != distractor.text) {
+                    choices.push(distractor);
+                }
+            }
+        }
+        // The distractors are in random order already, so only the answer needs a random position
+        let position = RandomState::new().build_hasher().finish() as usize % (choices.len() + 1);
+        choices.insert(position, answer.clone());
+        Ok(choices)
+    }
+
+    /// Handle the chosen answer: record the grade and show whether it was correct
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `quiz` - The quiz widget
+    /// * `button` - The button of the chosen answer
+    /// * `is_correct` - Whether the chosen answer is correct
+    fn answer(
+        window: &gtk::ApplicationWindow,
+        quiz: &gtk::Box,
+        button: &gtk::Button,
+        is_correct: bool,
+    ) {
+        let answered = unsafe { quiz.get_data::<Grade>("grade").is_some() };
+        if answered {
+            return;
+        }
+        let grade = if is_correct { Grade::Good } else { Grade::Again };
+        if let Err(err) = CardNotebook::record(window, grade) {
+            ErrorDialog::show(window, &err.to_string());
+            return;
+        }
+        unsafe {
+            quiz.set_data("grade", grade);
+        }
+        let correct = unsafe {
+            match (quiz.get_data::<Vec<gtk::Button>>("choices"), quiz.get_data::<usize>("correct")) {
+                (Some(buttons), Some(correct)) => buttons.get(*correct).cloned(),
+                _ => None,
+            }
+        };
+        if let Some(correct) = correct {
+            correct.get_style_context().add_class("suggested-action");
+        }
+        if !is_correct {
+            button.get_style_context().add_class("destructive-action");
+        }
+        let message = if is_correct {
+            "Correct! Type <n> for the next card."
+        } else {
+            "Wrong! Type <n> for the next card."
+        };
+        for child in quiz.get_children() {
+            if child.get_widget_name() == "result" {
+                if let Ok(result) = child.downcast::<gtk::Label>() {
+                    result.set_text(message);
+                    result.show();
+                }
+            }
+        }
+    }
+
+    /// Choose the answer with the given number on the shown card, if it is a multiple choice quiz
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `number` - Number of the answer, starting with 1
+    pub fn choose(window: &gtk::ApplicationWindow, number: i32) {
+        let quiz = match CardNotebook::find_widget(window) {
+            Some(quiz) => quiz,
+            None => { return; },
+        };
+        let button = unsafe {
+            match quiz.get_data::<Vec<gtk::Button>>("choices") {
+                Some(buttons) if number > 0 => buttons.get(number as usize - 1).cloned(),
+                _ => None,
+            }
+        };
+        if let Some(button) = button {
+            button.clicked();
+        }
+    }
+}
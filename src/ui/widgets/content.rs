@@ -1,9 +1,17 @@
 //! Module for the content widget
 
-use gtk::{ActionBarExt, BoxExt, ButtonExt, WidgetExt};
+use glib::{Cast, ObjectExt};
+use gtk::{ActionBarExt, ActionableExt, BoxExt, ButtonExt, LabelExt, WidgetExt};
 
-use crate::ui::WIDGET_NAME_CONTENT;
+use crate::database::DatabaseError;
+use crate::models::review::Grade;
+use crate::repository::Repository;
+use crate::session::StudyMode;
+use crate::ui::{UI, WIDGET_NAME_CONTENT, WIDGET_NAME_FILTER};
+use crate::ui::dialogs::error::Error as ErrorDialog;
 use super::cardnotebook::CardNotebook;
+use super::choice::Choice;
+use super::quiz::Quiz;
 
 
 /// The application's content widget
@@ -24,11 +32,116 @@ impl Content {
             CardNotebook::replace(&window, 0);
         }));
         action_bar.pack_start(&next);
-        let label = gtk::Label::new(Some("Press button or type <n> for next random card."));
+        let show_answer = gtk::Button::with_label("Show answer");
+        show_answer.set_tooltip_text(Some("Type <space> to show the answer"));
+        show_answer.connect_clicked(glib::clone!(@weak window => move |_| {
+            CardNotebook::reveal(&window);
+        }));
+        action_bar.pack_start(&show_answer);
+        let label = gtk::Label::new(
+            Some("Grade your answer or press button / type <n> to skip to the next card."));
         action_bar.pack_start(&label);
+        let filter = gtk::Label::new(None);
+        filter.set_widget_name(WIDGET_NAME_FILTER);
+        filter.set_tooltip_text(Some("Choose the cards to study in the study filter"));
+        action_bar.pack_start(&filter);
+        unsafe {
+            window.set_data(WIDGET_NAME_FILTER, filter);
+        }
+        Content::update_filter(window);
+        for grade in Grade::ALL.iter().rev() {
+            let button = Content::build_grade_button(*grade);
+            action_bar.pack_end(&button);
+        }
         action_bar
     }
 
+    /// Describe the study filter, i.e. the categories and languages which are studied
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository of flash cards
+    fn describe_filter(repository: &Repository) -> Result<String, DatabaseError> {
+        let filter = repository.filter()?;
+        let categories: Vec<String> = repository.categories()?.into_iter()
+            .filter(|category| filter.category_ids.contains(&category.id))
+            .map(|category| category.name)
+            .collect();
+        let languages: Vec<String> = repository.languages()?.into_iter()
+            .filter(|language| filter.language_ids.contains(&language.id))
+            .map(|language| language.name)
+            .collect();
+        let categories = if categories.is_empty() {
+            "all categories".to_string()
+        } else {
+            categories.join(", ")
+        };
+        let languages = if languages.is_empty() {
+            "all languages".to_string()
+        } else {
+            languages.join(", ")
+        };
+        Ok(format!(
+            "Studying <b>{}</b> in <b>{}</b>",
+            glib::markup_escape_text(&categories),
+            glib::markup_escape_text(&languages),
+        ))
+    }
+
+    /// Show the current study filter in the action bar
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    pub fn update_filter(window: &gtk::ApplicationWindow) {
+        let label = match unsafe { window.get_data::<gtk::Label>(WIDGET_NAME_FILTER) } {
+            Some(label) => label,
+            None => { return; },
+        };
+        let description = UI::get_repository(window).and_then(
+            |repository| Content::describe_filter(&repository));
+        match description {
+            Ok(description) => label.set_markup(&description),
+            Err(err) => ErrorDialog::show(window, &err.to_string()),
+        }
+    }
+
+    /// Build a button to grade the answer to the shown card
+    ///
+    /// The button activates the grade action, so it is insensitive while the action is disabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `grade` - The grade given when the button is clicked
+    fn build_grade_button(grade: Grade) -> gtk::Button {
+        let (label, key) = match grade {
+            Grade::Again => ("Again", "a"),
+            Grade::Hard => ("Hard", "h"),
+            Grade::Good => ("Good", "g"),
+            Grade::Easy => ("Easy", "e"),
+        };
+        let button = gtk::Button::with_label(label);
+        button.set_tooltip_text(Some(&format!("Type <{}> to grade the answer as '{}'", key, label)));
+        button.set_action_name(Some(&format!("app.grade_{}", grade.name())));
+        button
+    }
+
+    /// Build the flash card widget for the study mode of the session
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `card_id` - Identifier of the card for which to build the widget. Set to 0 to show the
+    ///   next card.
+    pub fn build_card(window: &gtk::ApplicationWindow, card_id: i64) -> gtk::Widget {
+        UI::update_grade_actions(window);
+        match UI::get_session(window).mode {
+            StudyMode::Typed => Quiz::build(window, card_id).upcast(),
+            StudyMode::Choice => Choice::build(window, card_id).upcast(),
+            _ => CardNotebook::build(window, card_id).upcast(),
+        }
+    }
+
     /// Build the application's content area with flash card and action bar
     ///
     /// # Arguments
@@ -38,7 +151,7 @@ impl Content {
         let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
         content.set_widget_name(WIDGET_NAME_CONTENT);
 
-        let card = CardNotebook::build(window, 0);
+        let card = Content::build_card(window, 0);
         content.pack_start(&card, true, true, 10);
 
         let action_bar = Content::build_action_bar(window);
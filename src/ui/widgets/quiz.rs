@@ -0,0 +1,195 @@
+//! Module for the quiz widget to type the answer to a flash card
+
+use std::time::Instant;
+
+use glib::ObjectExt;
+use gtk::{BoxExt, EditableExt, EntryExt, LabelExt, WidgetExt};
+
+use crate::compare::{Comparison, Difference, Strictness};
+use crate::database::DatabaseError;
+use crate::models::review::Grade;
+use crate::ui::{UI, WIDGET_NAME_CARD};
+use crate::ui::dialogs::error::Error as ErrorDialog;
+use super::cardnotebook::CardNotebook;
+
+
+/// A widget to quiz a flash card by typing the answer
+pub struct Quiz;
+
+/// Implementation of the quiz widget
+impl Quiz {
+    /// Build the quiz for a card with given id
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `card_id` - Identifier of the card for which to build the quiz. Set to 0 to quiz the next
+    ///   card.
+    ///
+    /// # Notes
+    ///
+    /// * The first answer language of the study session is the one which has to be typed.
+    pub fn build(window: &gtk::ApplicationWindow, card_id: i64) -> gtk::Box {
+        let quiz = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        quiz.set_widget_name(WIDGET_NAME_CARD);
+        if let Err(err) = Quiz::build_content(window, &quiz, card_id) {
+            ErrorDialog::show(window, &err.to_string());
+        }
+        quiz
+    }
+
+    /// Build the content of the quiz, i.e. the prompt, the entry for the answer and the result
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `quiz` - The quiz widget to add the content to
+    /// * `card_id` - Identifier of the card for which to build the quiz
+    fn build_content(
+        window: &gtk::ApplicationWindow,
+        quiz: &gtk::Box,
+        card_id: i64,
+    ) -> Result<(), DatabaseError> {
+        let padding = 10;
+        let repository = UI::get_repository(window)?;
+        let card = repository.next_card(card_id)?;
+        let translations = repository.study_translations(card.id)?;
+        let session = UI::get_session(window);
+        let (prompt, answers) = session.arrange(translations);
+        let (prompt, answer) = match (prompt, answers.into_iter().next()) {
+            (Some(prompt), Some(answer)) => (prompt, answer),
+            _ => { return Err(DatabaseError::NotFound); },
+        };
+        let prompt_language = repository.language(prompt.language_id)?;
+        let answer_language = repository.language(answer.language_id)?;
+        let category = repository.category(card.category_id)?;
+
+        let language = gtk::Label::new(Some(&format!(
+            "{} → {}", prompt_language.name, answer_language.name)));
+        quiz.pack_start(&language, false, false, padding);
+
+        let text = gtk::Label::new(Some(""));
+        text.set_markup(&format!(
+            "<span font_desc='{:.1}'>{}</span>",
+            UI::get_settings(window).font_size,
+            glib::markup_escape_text(&prompt.text),
+        ));
+        quiz.pack_start(&text, true, true, padding);
+
+        let entry = gtk::Entry::new();
+        entry.set_placeholder_text(Some(&format!(
+            "Type the answer in {} and press Enter ...", answer_language.name)));
+        quiz.pack_start(&entry, false, false, padding);
+
+        let result = gtk::Label::new(None);
+        result.set_no_show_all(true);
+        quiz.pack_start(&result, false, false, padding);
+
+        let separator = gtk::Separator::new(gtk::Orientation::Horizontal);
+        quiz.pack_start(&separator, false, false, padding);
+
+        let category_label = gtk::Label::new(Some(""));
+        category_label.set_markup(&format!(
+            "Category: <b>{}</b>", glib::markup_escape_text(&category.name)));
+        category_label.set_halign(gtk::Align::End);
+        quiz.pack_start(&category_label, false, false, padding);
+
+        let expected = answer.text;
+        let strictness = session.strictness;
+        entry.connect_activate(glib::clone!(
+            @weak window, @weak quiz, @weak result => move |entry| {
+                Quiz::check(&window, &quiz, entry, &result, &expected, &strictness);
+            }
+        ));
+        unsafe {
+            quiz.set_data("card_id", card.id);
+            quiz.set_data("shown_at", Instant::now());
+            quiz.set_data("language_pair", (prompt.language_id, answer.language_id));
+        }
+        Ok(())
+    }
+
+    /// Check the typed answer and show the differences to the expected one
+    ///
+    /// If the answer has already been checked, the suggested grade is given instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `quiz` - The quiz widget
+    /// * `entry` - The entry which holds the typed answer
+    /// * `result` - The label to show the result in
+    /// * `expected` - The expected answer
+    /// * `strictness` - How strictly to compare the answers
+    fn check(
+        window: &gtk::ApplicationWindow,
+        quiz: &gtk::Box,
+        entry: &gtk::Entry,
+        result: &gtk::Label,
+        expected: &str,
+        strictness: &Strictness,
+    ) {
+        let suggested = unsafe { quiz.get_data::<Grade>("grade").copied() };
+        if let Some(grade) = suggested {
+            CardNotebook::grade(window, grade);
+            return;
+        }
+        let comparison = Comparison::new(&entry.get_text(), expected, strictness);
+        let grade = comparison.grade();
+        let response_time = unsafe {
+            match quiz.get_data::<Instant>("shown_at") {
+                Some(shown_at) => shown_at.elapsed().as_millis() as i64,
+                None => 0,
+            }
+        };
+        unsafe {
+            quiz.set_data("grade", grade);
+            quiz.set_data("response_time", response_time);
+        }
+        entry.set_editable(false);
+        result.set_markup(&Quiz::markup(&comparison, grade));
+        result.show();
+    }
+
+    /// Get the markup to show the differences of a comparison
+    ///
+    /// # Arguments
+    ///
+    /// * `comparison` - The comparison of the typed and the expected answer
+    /// * `grade` - The suggested grade
+    fn markup(comparison: &Comparison, grade: Grade) -> String {
+        let mut typed = String::new();
+        let mut expected = String::new();
+        for difference in &comparison.differences {
+            match difference {
+                Difference::Equal(text) => {
+                    let text = glib::markup_escape_text(text);
+                    typed.push_str(&format!("<span foreground='green'>{}</span>", text));
+                    expected.push_str(&text);
+                },
+                Difference::Missing(text) => {
+                    typed.push_str("<span foreground='red'>_</span>");
+                    expected.push_str(&format!(
+                        "<span foreground='red' underline='single'>{}</span>",
+                        glib::markup_escape_text(text)));
+                },
+                Difference::Extra(text) => {
+                    typed.push_str(&format!(
+                        "<span foreground='red' strikethrough='true'>{}</span>",
+                        glib::markup_escape_text(text)));
+                },
+                Difference::Wrong(typed_text, expected_text) => {
+                    typed.push_str(&format!(
+                        "<span foreground='red'>{}</span>", glib::markup_escape_text(typed_text)));
+                    expected.push_str(&format!(
+                        "<span foreground='red' underline='single'>{}</span>",
+                        glib::markup_escape_text(expected_text)));
+                },
+            }
+        }
+        let verdict = if comparison.is_correct() { "Correct!" } else { "Not quite." };
+        format!(
+            "<span font_desc='20.0'>{}\n{}</span>\n\n{} Press Enter to grade as <b>{:?}</b> or use the grade buttons.",
+            typed, expected, verdict, grade)
+    }
+}
@@ -0,0 +1,189 @@
+//! Module for the search widget to find flash cards by their translations
+
+use std::collections::HashMap;
+
+use glib::ObjectExt;
+use gtk::{
+    ContainerExt,
+    EntryExt,
+    GtkWindowExt,
+    Inhibit,
+    LabelExt,
+    ListBoxExt,
+    PopoverExt,
+    ScrolledWindowExt,
+    SearchEntryExt,
+    WidgetExt,
+};
+
+use crate::models::translation::SearchResult;
+use crate::session::StudyMode;
+use crate::ui::{UI, WIDGET_NAME_SEARCH};
+use crate::ui::dialogs::error::Error as ErrorDialog;
+use super::cardnotebook::CardNotebook;
+
+
+/// A search entry with a popover which lists the found cards
+pub struct Search;
+
+/// Implementation of the search widget
+impl Search {
+    /// Build the search entry
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    ///
+    /// # Notes
+    ///
+    /// * While the entry has the focus, the single key accelerators need the `Alt` modifier like
+    ///   when answers are typed.
+    pub fn build(window: &gtk::ApplicationWindow) -> gtk::SearchEntry {
+        let entry = gtk::SearchEntry::new();
+        entry.set_widget_name(WIDGET_NAME_SEARCH);
+        entry.set_placeholder_text(Some("Search cards ..."));
+        entry.set_tooltip_text(Some("Type <Ctrl-F> to search"));
+
+        let results = gtk::ListBox::new();
+        let scrolled = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.set_min_content_width(400);
+        scrolled.set_min_content_height(300);
+        scrolled.add(&results);
+        let popover = gtk::Popover::new(Some(&entry));
+        popover.set_modal(false);
+        popover.set_position(gtk::PositionType::Bottom);
+        popover.add(&scrolled);
+
+        results.connect_row_activated(glib::clone!(@weak window, @weak popover => move |_, row| {
+            let card_id = unsafe { row.get_data::<i64>("card_id").copied() };
+            if let Some(card_id) = card_id {
+                popover.hide();
+                CardNotebook::replace(&window, card_id);
+            }
+        }));
+        entry.connect_search_changed(glib::clone!(
+            @weak window, @weak popover, @weak results => move |entry| {
+                Search::update(&window, entry, &popover, &results);
+            }
+        ));
+        entry.connect_activate(glib::clone!(@weak results => move |_| {
+            if let Some(row) = results.get_row_at_index(0) {
+                row.activate();
+            }
+        }));
+        entry.connect_focus_in_event(glib::clone!(@weak window => @default-return Inhibit(false),
+            move |_, _| {
+                if let Some(app) = window.get_application() {
+                    UI::add_study_accelerators(&app, StudyMode::Typed);
+                }
+                Inhibit(false)
+            }
+        ));
+        entry.connect_focus_out_event(glib::clone!(@weak window => @default-return Inhibit(false),
+            move |_, _| {
+                if let Some(app) = window.get_application() {
+                    UI::add_study_accelerators(&app, UI::get_session(&window).mode);
+                }
+                Inhibit(false)
+            }
+        ));
+        unsafe {
+            window.set_data(WIDGET_NAME_SEARCH, entry.clone());
+        }
+        entry
+    }
+
+    /// Search for the entered text and list the found cards in the popover
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    /// * `entry` - The search entry
+    /// * `popover` - The popover which shows the results
+    /// * `results` - The list of results
+    fn update(
+        window: &gtk::ApplicationWindow,
+        entry: &gtk::SearchEntry,
+        popover: &gtk::Popover,
+        results: &gtk::ListBox,
+    ) {
+        for child in results.get_children() {
+            results.remove(&child);
+        }
+        let query = entry.get_text();
+        if query.trim().is_empty() {
+            popover.hide();
+            return;
+        }
+        let repository = match UI::get_repository(window) {
+            Ok(repository) => repository,
+            Err(err) => {
+                ErrorDialog::show(window, &err.to_string());
+                return;
+            },
+        };
+        let found = repository.search(&query, None).and_then(
+            |found| Ok((found, repository.languages()?)));
+        let (found, languages) = match found {
+            Ok(found) => found,
+            Err(err) => {
+                ErrorDialog::show(window, &err.to_string());
+                return;
+            },
+        };
+        let languages: HashMap<i64, String> = languages.into_iter()
+            .map(|language| (language.id, language.name))
+            .collect();
+        if found.is_empty() {
+            let label = gtk::Label::new(Some("No cards found."));
+            results.add(&label);
+        }
+        for result in found {
+            let label = gtk::Label::new(None);
+            label.set_markup(&format!(
+                "<b>{}</b> <small>({})</small>\n<small>{}</small>",
+                glib::markup_escape_text(&result.translation.text),
+                glib::markup_escape_text(
+                    languages.get(&result.translation.language_id).map_or("", |name| name)),
+                Search::markup(&result.snippet),
+            ));
+            label.set_halign(gtk::Align::Start);
+            let row = gtk::ListBoxRow::new();
+            row.add(&label);
+            unsafe {
+                row.set_data("card_id", result.translation.card_id);
+            }
+            results.add(&row);
+        }
+        popover.show_all();
+    }
+
+    /// Get the markup of a search result's snippet with its matching terms in bold
+    ///
+    /// # Arguments
+    ///
+    /// * `snippet` - The snippet of a search result
+    fn markup(snippet: &str) -> String {
+        let mut markup = String::new();
+        for (idx, part) in snippet.split(SearchResult::HIGHLIGHT_START).enumerate() {
+            match part.split_once(SearchResult::HIGHLIGHT_END) {
+                Some((term, rest)) if idx > 0 => {
+                    markup.push_str(&format!("<b>{}</b>", glib::markup_escape_text(term)));
+                    markup.push_str(&glib::markup_escape_text(rest));
+                },
+                _ => markup.push_str(&glib::markup_escape_text(part)),
+            }
+        }
+        markup
+    }
+
+    /// Find the search entry of the application window
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The GTK application window
+    pub fn find(window: &gtk::ApplicationWindow) -> Option<gtk::SearchEntry> {
+        unsafe { window.get_data::<gtk::SearchEntry>(WIDGET_NAME_SEARCH).cloned() }
+    }
+}